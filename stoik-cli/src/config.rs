@@ -0,0 +1,100 @@
+use std::{env, fs, path::PathBuf};
+
+use stoik::{
+    formula::{Abbreviations, FormulaOrder, ParseOptions},
+    render::OutputFormat,
+};
+
+/// The settings read from the config file, which flags can then change
+#[derive(Debug, Default)]
+pub struct Config {
+    pub options: ParseOptions,
+    /// The order to write elements in, which equations are only rewritten in if it is set
+    pub order: Option<FormulaOrder>,
+    pub output: Option<OutputFormat>,
+}
+
+impl Config {
+    /// Gets where the config file is if `--config` is not given, which is
+    /// `$XDG_CONFIG_HOME/stoik/config` or `~/.config/stoik/config`
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("stoik").join("config"))
+    }
+
+    /// Reads the config file at `path`, or the default config if there is no file there
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                Self::parse(&text).map_err(|e| format!("Error in `{}`: {e}", path.display()))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Parses a config file, which is made of `key = value` lines. Lines after an
+    /// `[abbreviations]` line add abbreviations instead, like `Ts = SO2C6H4CH3`
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut in_abbreviations = false;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "[abbreviations]" {
+                in_abbreviations = true;
+                config.abbreviations();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {} should be written as `KEY = VALUE`", i + 1));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            if in_abbreviations {
+                config
+                    .abbreviations()
+                    .insert(key, value)
+                    .map_err(|e| format!("line {}: {e}", i + 1))?;
+                continue;
+            }
+
+            let flag = || match value {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(format!("line {}: `{value}` should be true or false", i + 1)),
+            };
+            match key {
+                "strict" => config.options.strict = flag()?,
+                "abbreviations" if flag()? => {
+                    config.abbreviations();
+                }
+                "abbreviations" => config.options.abbreviations = None,
+                "keep-abbreviations" if flag()? => config.abbreviations().keep = true,
+                "keep-abbreviations" => {
+                    if let Some(abbreviations) = &mut config.options.abbreviations {
+                        abbreviations.keep = false;
+                    }
+                }
+                "order" => {
+                    config.order = Some(value.parse().map_err(|e| format!("line {}: {e}", i + 1))?)
+                }
+                "output" => {
+                    config.output = Some(value.parse().map_err(|e| format!("line {}: {e}", i + 1))?)
+                }
+                _ => return Err(format!("line {}: `{key}` is not a setting", i + 1)),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Gets the abbreviations to use, starting with the built in ones if there are none yet
+    pub fn abbreviations(&mut self) -> &mut Abbreviations {
+        self.options
+            .abbreviations
+            .get_or_insert_with(Abbreviations::default)
+    }
+}
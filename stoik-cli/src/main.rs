@@ -1,20 +1,39 @@
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    env,
-    time::Instant,
-};
+use std::{env, path::PathBuf, time::Instant};
+
+use config::Config;
 
 use stoik::{
-    formula::{self, Molecule, TokenStream},
-    StoikError,
+    equation::{Amount, Equation, Medium, Species},
+    formula::{self, FormulaOrder, Molecule, ParseOptions, TokenStream},
+    render::OutputFormat,
+    Diagnostic, StoikError,
 };
 
+mod config;
+
 const HELP_MSG: &str = include_str!("help_msg.txt");
+/// Peaks less abundant than this fraction of the tallest are left out of the peak table
+const PEAK_THRESHOLD: f64 = 0.001;
 
 fn main() {
     let mut time_mode = false;
     let mut all_moles = false;
-    let mut equation = String::new();
+    let mut balance_mode = false;
+    let mut medium = Medium::Acidic;
+    let config_path = env::args()
+        .find_map(|x| x.strip_prefix("--config=").map(PathBuf::from))
+        .or_else(Config::default_path);
+    let mut config = match config_path {
+        Some(path) => match Config::load(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("{e}");
+                return;
+            }
+        },
+        None => Config::default(),
+    };
+    let mut args = Vec::new();
     let mut time_table = ["Formula", "Tokenise", "Tree building", "Parsing", "Total"]
         .iter()
         .map(|x| vec![x.to_string()])
@@ -28,70 +47,99 @@ fn main() {
             return;
         } else if arg == "--all-moles" || arg == "-a" {
             all_moles = true;
+        } else if arg == "--balance" || arg == "-b" {
+            balance_mode = true;
+        } else if arg == "--strict" || arg == "-s" {
+            config.options.strict = true;
+        } else if arg == "--abbreviations" || arg == "-A" {
+            config.abbreviations();
+        } else if arg == "--keep-abbreviations" {
+            config.abbreviations().keep = true;
+        } else if arg.starts_with("--config=") {
+            continue;
+        } else if arg == "--basic" || arg == "-B" {
+            medium = Medium::Basic;
+        } else if let Some(name) = arg.strip_prefix("--order=") {
+            match name.parse() {
+                Ok(new) => config.order = Some(new),
+                Err(e) => {
+                    println!("{e}");
+                    return;
+                }
+            }
+        } else if let Some(name) = arg.strip_prefix("--output=") {
+            match name.parse() {
+                Ok(new) => config.output = Some(new),
+                Err(e) => {
+                    println!("{e}");
+                    return;
+                }
+            }
         } else {
-            equation = format!("{equation} {arg}");
+            args.push(arg);
         }
     }
-    equation = equation.trim().to_string();
-
-    if !(equation.contains("->") || equation.contains("=>")) {
-        println!("Products are not given, please use `=>` or `->` to seperate the two sides");
-        return;
-    }
 
-    let temp_equ = equation.replace("=>", "->");
-    let (mut reactants_str, mut products_str) = temp_equ.split_once("->").unwrap();
+    let Config {
+        options,
+        order,
+        output,
+    } = config;
 
-    reactants_str = reactants_str.trim();
-    products_str = products_str.trim();
-
-    let mut reactants = Vec::new();
-    for formula in reactants_str.split('+').map(|x| x.trim()) {
-        match construct_mole(formula, time_mode, &mut time_table) {
-            Ok(mol) => reactants.push((mol, formula.to_string())),
-            Err(e) => {
-                println!("{}", generate_error_msg(e, formula));
-                return;
-            }
-        }
+    if args.first().is_some_and(|x| x == "mass") {
+        print_molar_mass(
+            &args[1..].join(" "),
+            order.unwrap_or_default(),
+            output,
+            &options,
+        );
+        return;
     }
-
-    let mut products = Vec::new();
-    for formula in products_str.split('+').map(|x| x.trim()) {
-        match construct_mole(formula, time_mode, &mut time_table) {
-            Ok(mol) => products.push((mol, formula.to_string())),
-            Err(e) => {
-                println!("{}", generate_error_msg(e, formula));
-                return;
-            }
-        }
+    if args.first().is_some_and(|x| x == "empirical") {
+        print_empirical(&args[1..], order.unwrap_or_default(), output);
+        return;
     }
-
-    let mut lhs = HashMap::new();
-    for mol in reactants {
-        extend_mol_map(&mut lhs, mol.0.get_map());
+    if args.first().is_some_and(|x| x == "yield") {
+        print_yield(&args[1..], &options);
+        return;
     }
-
-    let mut rhs = HashMap::new();
-    for mol in products {
-        extend_mol_map(&mut rhs, mol.0.get_map());
+    if args.first().is_some_and(|x| x == "redox") {
+        print_redox(&args[1..].join(" "), medium, output, &options);
+        return;
     }
+    let equation = args.join(" ");
 
-    let mut keys = lhs.keys().collect::<Vec<_>>();
-    let mut balanced = HashMap::new();
-    keys.extend(rhs.keys());
-    keys.dedup();
+    let Ok(parsed) = Equation::parse_with(&equation, &options) else {
+        let diagnostics = Equation::diagnose(&equation, &options);
+        println!("{}", Diagnostic::format_all(&diagnostics, &equation));
+        return;
+    };
 
-    for key in keys {
-        balanced.insert(key.to_string(), lhs.get(key) == rhs.get(key));
+    if time_mode {
+        // SMILES species do not go through the formula parser, so are not timed
+        let formulas = parsed.reactants.iter().chain(&parsed.products);
+        for species in formulas.filter(|x| x.smiles().is_none()) {
+            // The equation has already parsed, so this cannot fail
+            let _ = construct_mole(&species.formula, &options, &mut time_table);
+        }
     }
 
-    let is_balanced = balanced.values().all(|x| *x);
+    let lhs = parsed.reactant_map();
+    let rhs = parsed.product_map();
+    let balanced = parsed.element_balance();
+
+    // the equation is only rewritten if an order is given, otherwise it is shown as written
+    let ordered = order.map(|order| reorder(&parsed, order, &options));
+    let is_balanced = parsed.is_balanced();
+    let shown = match (output, &ordered) {
+        (None, None) => equation.clone(),
+        (_, ordered) => show_equation(ordered.as_ref().unwrap_or(&parsed), output),
+    };
 
     if is_balanced {
-        println!("`{equation}` is balanced")
+        println!("`{shown}` is balanced")
     } else {
-        println!("`{equation}` is not balanced")
+        println!("`{shown}` is not balanced")
     }
 
     if !is_balanced || all_moles {
@@ -99,53 +147,298 @@ fn main() {
             .iter()
             .map(|x| vec![x.to_string()])
             .collect::<Vec<_>>();
-        for (element, bal) in balanced {
+        for element in element_order(&parsed, order.unwrap_or_default()) {
+            let Some(&bal) = balanced.get(&element) else {
+                continue;
+            };
             if !bal || all_moles {
-                table[1].push(lhs.get(&element).unwrap_or(&0).to_string());
-                table[2].push(rhs.get(&element).unwrap_or(&0).to_string());
+                table[1].push(lhs.get(&element).copied().unwrap_or_default().to_string());
+                table[2].push(rhs.get(&element).copied().unwrap_or_default().to_string());
                 table[0].push(element);
                 table[3].push(bal.to_string());
             }
         }
+        if !parsed.is_charge_balanced() || all_moles {
+            table[0].push("Charge".to_string());
+            table[1].push(parsed.reactant_charge().to_string());
+            table[2].push(parsed.product_charge().to_string());
+            table[3].push(parsed.is_charge_balanced().to_string());
+        }
         print_table(table);
     }
 
+    let warnings = parsed.valence_warnings();
+    if !warnings.is_empty() {
+        println!("\n{}", Diagnostic::format_all(&warnings, &equation));
+    }
+
+    if balance_mode && !is_balanced {
+        match ordered.as_ref().unwrap_or(&parsed).balance() {
+            Ok(balanced) => println!(
+                "\nBalanced equation: `{}`",
+                show_equation(&balanced, output)
+            ),
+            Err(e) => println!("\n{e}"),
+        }
+    }
+
     if time_mode {
         println!("\nTime summary");
         print_table(time_table);
     }
 }
 
-// Move to a pub func from being copy pasted here and in stoik-gui/src/main.rs
-fn generate_error_msg(e: StoikError, formula: &str) -> String {
-    match e {
-        StoikError::InvalidToken(loc) => {
-            loc.format_msg(formula, "Malformed formula", "Illegal token")
+fn print_molar_mass(
+    formula: &str,
+    order: FormulaOrder,
+    output: Option<OutputFormat>,
+    options: &ParseOptions,
+) {
+    let Ok(Species { molecule: mol, .. }) = Species::new_with(formula, options) else {
+        let diagnostics = Species::diagnose(formula, options);
+        println!("{}", Diagnostic::format_all(&diagnostics, formula));
+        return;
+    };
+    // electrons have a mass but no atoms to make up a composition
+    let (mass, mut composition) =
+        match mol
+            .molar_mass()
+            .and_then(|mass| match mol.mass_composition() {
+                Err(StoikError::EmptyMolecule) => Ok((mass, Vec::new())),
+                composition => Ok((mass, composition?)),
+            }) {
+            Ok(result) => result,
+            Err(e) => {
+                println!("{}", Diagnostic::from_error(&e, formula).format(formula));
+                return;
+            }
+        };
+
+    println!(
+        "The molar mass of `{}` is {mass}",
+        show_formula(&mol.to_string_with(order), output)
+    );
+    if let Ok(degree) = mol.degree_of_unsaturation() {
+        println!("The degree of unsaturation is {degree}");
+    }
+    if let Some(warning) = mol.check_valence() {
+        println!("{}", warning.format(formula));
+    }
+    if !composition.is_empty() {
+        let isotopes = mol.isotopes(order);
+        composition.sort_by_key(|x| isotopes.iter().position(|i| i.symbol == x.element));
+        let mut table = ["Element", "Count", "Mass %", "Atom fraction"]
+            .iter()
+            .map(|x| vec![x.to_string()])
+            .collect::<Vec<_>>();
+        for element in composition {
+            table[0].push(element.element);
+            table[1].push(element.count.to_string());
+            table[2].push(format!("{:.3}%", element.mass_percent));
+            table[3].push(format!("{:.4}", element.atom_fraction));
+        }
+        print_table(table);
+    }
+
+    // elements without natural isotopes, like technetium, only have a molar mass
+    let (Ok(monoisotopic), Ok(most_abundant), Ok(envelope)) = (
+        mol.monoisotopic_mass(),
+        mol.most_abundant_mass(),
+        mol.isotopic_envelope(PEAK_THRESHOLD),
+    ) else {
+        return;
+    };
+    println!("\nThe monoisotopic mass is {monoisotopic:.6} Da");
+    println!("The most abundant mass is {most_abundant:.6} Da");
+    let mut table = ["Mass (Da)", "Relative abundance"]
+        .iter()
+        .map(|x| vec![x.to_string()])
+        .collect::<Vec<_>>();
+    for peak in envelope {
+        table[0].push(format!("{:.4}", peak.mass));
+        table[1].push(format!("{:.2}%", peak.abundance * 100.0));
+    }
+    print_table(table);
+}
+
+fn print_redox(
+    equation: &str,
+    medium: Medium,
+    output: Option<OutputFormat>,
+    options: &ParseOptions,
+) {
+    let Ok(parsed) = Equation::parse_with(equation, options) else {
+        let diagnostics = Equation::diagnose(equation, options);
+        println!("{}", Diagnostic::format_all(&diagnostics, equation));
+        return;
+    };
+    match parsed.balance_redox(medium) {
+        Ok(redox) => {
+            println!("Oxidation: `{}`", show_equation(&redox.oxidation, output));
+            println!("Reduction: `{}`", show_equation(&redox.reduction, output));
+            println!("Overall:   `{}`", show_equation(&redox.overall, output));
         }
-        StoikError::NumberFirst(loc) => loc.format_msg(
-            formula,
-            "Malformed formula",
-            "Compound groups cannot start with numbers",
+        Err(e) => println!("{}", Diagnostic::from_error(&e, equation).format(equation)),
+    }
+}
+
+fn print_empirical(args: &[String], order: FormulaOrder, output: Option<OutputFormat>) {
+    let mut masses = Vec::new();
+    let mut molar_mass = None;
+    for arg in args {
+        let Some((key, value)) = arg.split_once('=') else {
+            println!("`{arg}` should be written as `ELEMENT=MASS`, e.g. `C=40.0`");
+            return;
+        };
+        let Ok(value) = value.trim_end_matches(['%', 'g']).parse::<f64>() else {
+            println!("`{value}` is not a number");
+            return;
+        };
+        if key == "molar" {
+            molar_mass = Some(value);
+        } else {
+            masses.push((key, value));
+        }
+    }
+
+    let formula = match molar_mass {
+        Some(molar_mass) => Molecule::molecular_formula(&masses, molar_mass),
+        None => Molecule::empirical_formula(&masses),
+    };
+    match formula {
+        Ok(formula) if molar_mass.is_some() => println!(
+            "The molecular formula is `{}`",
+            show_formula(&formula.to_string_with(order), output)
+        ),
+        Ok(formula) => println!(
+            "The empirical formula is `{}`",
+            show_formula(&formula.to_string_with(order), output)
         ),
-        StoikError::UnpairedParenthesis(loc) => {
-            loc.format_msg(formula, "Malformed formula", "Unpaired parenthesis")
+        Err(e) => println!("{e}"),
+    }
+}
+
+fn print_yield(args: &[String], options: &ParseOptions) {
+    let Some((equation, amounts)) = args.split_first() else {
+        println!("{HELP_MSG}");
+        return;
+    };
+    let Ok(parsed) = Equation::parse_with(equation, options) else {
+        let diagnostics = Equation::diagnose(equation, options);
+        println!("{}", Diagnostic::format_all(&diagnostics, equation));
+        return;
+    };
+    let amounts = match amounts
+        .iter()
+        .map(|x| x.parse::<Amount>())
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(amounts) => amounts,
+        Err(e) => {
+            println!("{e}");
+            return;
         }
-        StoikError::UnpairedBracket(loc) => {
-            loc.format_msg(formula, "Malformed formula", "Unpaired bracket")
+    };
+    let (amounts, actual) = amounts.split_at(parsed.reactants.len().min(amounts.len()));
+
+    let yields = match parsed.reaction_yield(amounts) {
+        Ok(yields) => yields,
+        Err(e) => {
+            println!("{e}");
+            return;
         }
-        e => e.to_string(),
+    };
+
+    println!(
+        "The limiting reagent is `{}`",
+        parsed.reactants[yields.limiting].without_coefficient()
+    );
+    let mut table = ["Reactant", "Available", "Used", "Left"]
+        .iter()
+        .map(|x| vec![x.to_string()])
+        .collect::<Vec<_>>();
+    for (species, reactant) in parsed.reactants.iter().zip(&yields.reactants) {
+        table[0].push(species.to_string());
+        table[1].push(reactant.available.to_string());
+        table[2].push(reactant.used.to_string());
+        table[3].push(reactant.left.to_string());
+    }
+    print_table(table);
+
+    let mut table = ["Product", "Theoretical yield", "Percent yield"]
+        .iter()
+        .map(|x| vec![x.to_string()])
+        .collect::<Vec<_>>();
+    for (i, (species, product)) in parsed.products.iter().zip(&yields.products).enumerate() {
+        table[0].push(species.to_string());
+        table[1].push(product.theoretical.to_string());
+        table[2].push(match actual.get(i) {
+            Some(actual) => match product.percent_yield(*actual) {
+                Some(percent) => format!("{percent:.2}%"),
+                None => "-".to_string(),
+            },
+            None => "-".to_string(),
+        });
     }
+    print_table(table);
 }
 
-// same as generate_error_msg
-fn extend_mol_map(main: &mut HashMap<String, i64>, mol: HashMap<String, i64>) {
-    for (key, mol_val) in mol {
-        if let Entry::Occupied(mut entry) = main.entry(key.clone()) {
-            *entry.get_mut() += mol_val;
-        } else {
-            main.insert(key, mol_val);
+/// Writes a formula in the `--output` format, or as it is if no format was given
+fn show_formula(formula: &str, output: Option<OutputFormat>) -> String {
+    output
+        .and_then(|x| x.render(formula).ok())
+        .unwrap_or_else(|| formula.to_string())
+}
+
+/// Writes an equation in the `--output` format, or as it is if no format was given
+/// Writes each species of the equation with its elements in `order`,
+/// keeping the equation as it is if it does not parse again
+fn reorder(equation: &Equation, order: FormulaOrder, options: &ParseOptions) -> Equation {
+    let side = |species: &[Species]| {
+        species
+            .iter()
+            .map(|x| {
+                let mut unit = x.molecule.clone();
+                unit.moles = 1;
+                match x.molecule.moles {
+                    1 => unit.to_string_with(order),
+                    moles => format!("{moles}{}", unit.to_string_with(order)),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" + ")
+    };
+    let text = format!(
+        "{} {} {}",
+        side(&equation.reactants),
+        equation.arrow.symbol(),
+        side(&equation.products)
+    );
+    Equation::parse_with(&text, options).unwrap_or_else(|_| equation.clone())
+}
+
+/// Gets the elements in an equation in `order`, as if it was all one molecule
+fn element_order(equation: &Equation, order: FormulaOrder) -> Vec<String> {
+    let mut all = Molecule::default();
+    for species in equation.reactants.iter().chain(&equation.products) {
+        for isotope in species.molecule.isotopes(FormulaOrder::Written) {
+            all.increase_isotope(isotope.clone(), 1);
         }
     }
+    let mut symbols = all
+        .isotopes(order)
+        .into_iter()
+        .map(|x| x.symbol.clone())
+        .collect::<Vec<_>>();
+    // isotopes of the same element are next to each other
+    symbols.dedup();
+    symbols
+}
+
+fn show_equation(equation: &Equation, output: Option<OutputFormat>) -> String {
+    output
+        .and_then(|x| equation.render(x).ok())
+        .unwrap_or_else(|| equation.to_string())
 }
 
 // This is some *sus* code
@@ -219,27 +512,23 @@ fn pad_string(input: &String, length: usize) -> String {
 
 fn construct_mole(
     formula: &str,
-    time_mode: bool,
+    options: &ParseOptions,
     time_table: &mut [Vec<String>],
 ) -> Result<Molecule, StoikError> {
-    if time_mode {
-        time_table[0].push(formula.to_string());
+    time_table[0].push(formula.to_string());
 
-        let tokenise_inst = Instant::now();
-        let tokenstream = TokenStream::new(formula);
-        time_table[1].push(format!("{:>09.3?}", tokenise_inst.elapsed()));
+    let tokenise_inst = Instant::now();
+    let tokenstream = TokenStream::new(formula);
+    time_table[1].push(format!("{:>09.3?}", tokenise_inst.elapsed()));
 
-        let tree_inst = Instant::now();
-        let root = formula::assemble_tree(tokenstream)?;
-        time_table[2].push(format!("{:>09.3?}", tree_inst.elapsed()));
+    let tree_inst = Instant::now();
+    let root = formula::assemble_tree(tokenstream)?;
+    time_table[2].push(format!("{:>09.3?}", tree_inst.elapsed()));
 
-        let mol_inst = Instant::now();
-        let mol = Molecule::construct_from_tree(root)?;
-        time_table[3].push(format!("{:>09.3?}", mol_inst.elapsed()));
+    let mol_inst = Instant::now();
+    let mol = Molecule::construct_from_tree_with(root, options)?;
+    time_table[3].push(format!("{:>09.3?}", mol_inst.elapsed()));
 
-        time_table[4].push(format!("{:>09.3?}", tokenise_inst.elapsed()));
-        Ok(mol)
-    } else {
-        Molecule::from_formula(formula)
-    }
+    time_table[4].push(format!("{:>09.3?}", tokenise_inst.elapsed()));
+    Ok(mol)
 }
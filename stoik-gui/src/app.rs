@@ -1,20 +1,24 @@
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    fmt::Display,
-};
+use std::{collections::HashMap, fmt::Display};
 
 use eframe::{App, CreationContext, Frame};
 use egui::{
-    widgets, CentralPanel, Context, RichText, SidePanel, Slider, TextEdit, TextStyle,
-    TopBottomPanel, Ui,
+    pos2, vec2, widgets, Align2, CentralPanel, Context, RichText, Sense, SidePanel, Slider, Stroke,
+    TextEdit, TextStyle, TopBottomPanel, Ui,
 };
 use egui_extras::{Column, TableBody, TableBuilder};
-use stoik::{formula::Molecule, StoikError};
+use stoik::{
+    equation::{Amount, Equation, Medium, Redox, Species},
+    formula::{Abbreviations, FormulaOrder, Molecule, ParseOptions, Peak},
+    render::OutputFormat,
+    Diagnostic, Rational,
+};
 use strum::{EnumIter, IntoEnumIterator};
 
 #[allow(unused)]
 pub const APP_NAME: &str = "stoik-gui";
 pub const APP_NAME_FORMATTED: &str = "Stoik GUI";
+/// Peaks less abundant than this fraction of the tallest are left out of the isotope plots
+const PEAK_THRESHOLD: f64 = 0.001;
 
 #[derive(Default)]
 pub struct StoikApp {
@@ -22,6 +26,12 @@ pub struct StoikApp {
     display_mode: Mode,
     mode_data: ModeData,
     all_atoms: bool,
+    parse_options: ParseOptions,
+    order: FormulaOrder,
+    use_abbreviations: bool,
+    abbreviations: Abbreviations,
+    new_abbreviation: (String, String),
+    abbreviation_error: Option<String>,
 }
 
 impl StoikApp {
@@ -77,23 +87,7 @@ impl StoikApp {
             {
                 self.mode_data.changed = true;
                 if self.display_mode == Mode::Text {
-                    self.mode_data.text_input = self
-                        .mode_data
-                        .lhs_mols
-                        .iter()
-                        .cloned()
-                        .map(|(_, formula)| formula)
-                        .collect::<Vec<_>>()
-                        .join(" + ")
-                        + " -> "
-                        + &self
-                            .mode_data
-                            .rhs_mols
-                            .iter()
-                            .cloned()
-                            .map(|(_, formula)| formula)
-                            .collect::<Vec<_>>()
-                            .join(" + ");
+                    self.mode_data.text_input = self.mode_data.equation.to_string();
                 }
             };
         }
@@ -130,12 +124,146 @@ impl StoikApp {
         ui.separator();
 
         ui.checkbox(&mut self.all_atoms, "Show balanced atoms in summery");
+        let mut changed = ui
+            .checkbox(
+                &mut self.parse_options.strict,
+                "Only allow elements from the periodic table",
+            )
+            .changed();
+        changed |= self.abbreviation_settings(ui);
+        if changed {
+            self.reparse();
+        }
+        egui::ComboBox::from_label("Element order")
+            .selected_text(self.order.to_string())
+            .show_ui(ui, |ui| {
+                for order in FormulaOrder::ALL {
+                    ui.selectable_value(&mut self.order, order, order.to_string());
+                }
+            });
+    }
+
+    /// Shows the abbreviation settings, giving whether they changed
+    fn abbreviation_settings(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = ui
+            .checkbox(
+                &mut self.use_abbreviations,
+                "Expand abbreviations like Me and Ph",
+            )
+            .changed();
+        ui.add_enabled_ui(self.use_abbreviations, |ui| {
+            changed |= ui
+                .checkbox(
+                    &mut self.abbreviations.keep,
+                    "Keep abbreviations in formulas",
+                )
+                .changed();
+            ui.collapsing("Abbreviations", |ui| {
+                let mut to_del = None;
+                for (name, formula) in self.abbreviations.iter() {
+                    ui.horizontal(|ui| {
+                        if ui.button("-").clicked() {
+                            to_del = Some(name.to_string());
+                        }
+                        ui.monospace(format!("{name} = {formula}"));
+                    });
+                }
+                if let Some(name) = to_del {
+                    self.abbreviations.remove(&name);
+                    changed = true;
+                }
+
+                let (name, formula) = &mut self.new_abbreviation;
+                ui.horizontal(|ui| {
+                    ui.add(
+                        TextEdit::singleline(name)
+                            .hint_text("Ts")
+                            .desired_width(40.0),
+                    );
+                    ui.label("=");
+                    ui.add(
+                        TextEdit::singleline(formula)
+                            .hint_text("SO2C6H4CH3")
+                            .desired_width(100.0),
+                    );
+                    if ui.button("+").clicked() {
+                        match self.abbreviations.insert(name.trim(), formula) {
+                            Ok(_) => {
+                                name.clear();
+                                formula.clear();
+                                self.abbreviation_error = None;
+                                changed = true;
+                            }
+                            Err(e) => self.abbreviation_error = Some(e.to_string()),
+                        }
+                    }
+                });
+                if let Some(e) = &self.abbreviation_error {
+                    ui.label(RichText::new(e).color(ui.visuals().error_fg_color));
+                }
+            });
+        });
+
+        if changed {
+            self.parse_options.abbreviations =
+                self.use_abbreviations.then(|| self.abbreviations.clone());
+        }
+        changed
+    }
+
+    /// Parses the equation again with the new parse options. Outside of text mode each
+    /// species is parsed on its own, as a list may only have one side, and a species
+    /// the new options reject is kept until it is removed
+    fn reparse(&mut self) {
+        if self.display_mode == Mode::Text {
+            self.parse_text();
+            return;
+        }
+
+        let mut errors = Vec::new();
+        let equation = &mut self.mode_data.equation;
+        for species in equation.reactants.iter_mut().chain(&mut equation.products) {
+            match Species::new_with(&species.formula, &self.parse_options) {
+                Ok(new) => *species = new,
+                Err(_) => {
+                    let diagnostics = Species::diagnose(&species.formula, &self.parse_options);
+                    errors.push(Diagnostic::format_all(&diagnostics, &species.formula));
+                }
+            }
+        }
+        self.mode_data.error_msg = (!errors.is_empty()).then(|| errors.join("\n"));
+        self.mode_data.changed = true;
+    }
+
+    fn parse_text(&mut self) {
+        self.mode_data.changed = true;
+        if self.mode_data.text_input.trim().is_empty() {
+            self.mode_data.error_msg = None;
+            self.mode_data.equation = Equation::default();
+            return;
+        }
+        match Equation::parse_with(&self.mode_data.text_input, &self.parse_options) {
+            Ok(equation) => {
+                self.mode_data.error_msg = None;
+                self.mode_data.equation = equation;
+            }
+            Err(_) => {
+                let input = &self.mode_data.text_input;
+                let diagnostics = Equation::diagnose(input, &self.parse_options);
+                self.mode_data.error_msg = Some(Diagnostic::format_all(&diagnostics, input));
+                self.mode_data.equation = Equation::default();
+            }
+        }
     }
 
     fn main_win(&mut self, ui: &mut Ui, _frame: &mut Frame, mode: Mode) {
         match mode {
             Mode::Text => self.ui_text(ui),
             Mode::List => self.ui_list(ui),
+            Mode::Empirical => {
+                self.ui_empirical(ui);
+                return;
+            }
         }
 
         if let Some(s) = &self.mode_data.error_msg {
@@ -144,7 +272,9 @@ impl StoikApp {
                     .color(ui.visuals().error_fg_color)
                     .monospace(),
             );
-        } else if !self.mode_data.lhs_mols.is_empty() && !self.mode_data.rhs_mols.is_empty() {
+        } else if !self.mode_data.equation.reactants.is_empty()
+            && !self.mode_data.equation.products.is_empty()
+        {
             self.show_balance_summary(ui);
         }
     }
@@ -165,62 +295,23 @@ impl StoikApp {
         }
 
         if self.mode_data.changed && res.lost_focus() {
-            if !self.mode_data.text_input.contains("->") {
-                if !self.mode_data.text_input.is_empty() {
-                    self.mode_data.error_msg =
-                        Some("No RHS is given, use -> or => to indicate between them".to_string());
-                } else {
-                    self.mode_data.error_msg = None;
-                    self.mode_data.lhs_mols.clear();
-                    self.mode_data.rhs_mols.clear();
-                }
-            } else {
-                self.mode_data.error_msg = None;
-                let (mut lhs_str, mut rhs_str) =
-                    self.mode_data.text_input.split_once("->").unwrap();
-
-                lhs_str = lhs_str.trim();
-                rhs_str = rhs_str.trim();
-
-                self.mode_data.lhs_mols.clear();
-                for formula in lhs_str.split('+').map(|x| x.trim()) {
-                    match Molecule::from_formula(formula) {
-                        Ok(mol) => self.mode_data.lhs_mols.push((mol, formula.to_string())),
-                        Err(e) => {
-                            self.mode_data.error_msg = Some(generate_error_msg(e, formula));
-                            break;
-                        }
-                    }
-                }
-
-                self.mode_data.rhs_mols.clear();
-                if self.mode_data.error_msg.is_none() {
-                    for formula in rhs_str.split('+').map(|x| x.trim()) {
-                        match Molecule::from_formula(formula) {
-                            Ok(mol) => self.mode_data.rhs_mols.push((mol, formula.to_string())),
-                            Err(e) => {
-                                self.mode_data.error_msg = Some(generate_error_msg(e, formula))
-                            }
-                        }
-                    }
-                }
-            }
+            self.parse_text();
         }
     }
 
     fn ui_list(&mut self, ui: &mut Ui) {
         ui.heading("LHS");
         let mut to_del = None;
-        for (i, (_, formula)) in self.mode_data.lhs_mols.iter().enumerate() {
+        for (i, species) in self.mode_data.equation.reactants.iter().enumerate() {
             ui.horizontal(|ui| {
                 if ui.button("-").clicked() {
                     to_del = Some(i);
                 }
-                ui.label(format_formula(formula));
+                ui.label(render_species(species));
             });
         }
         if let Some(i) = to_del {
-            self.mode_data.lhs_mols.remove(i);
+            self.mode_data.equation.reactants.remove(i);
             self.mode_data.changed = true;
         }
 
@@ -231,34 +322,34 @@ impl StoikApp {
                 .lost_focus()
                 || ui.button("+").clicked();
         });
-        if lost_focus && !self.mode_data.new_lhs.is_empty() && self.mode_data.error_msg.is_none() {
-            match Molecule::from_formula(&self.mode_data.new_lhs) {
-                Ok(mol) => {
-                    self.mode_data
-                        .lhs_mols
-                        .push((mol, self.mode_data.new_lhs.to_string()));
+        if lost_focus && !self.mode_data.new_lhs.is_empty() {
+            match Species::new_with(&self.mode_data.new_lhs, &self.parse_options) {
+                Ok(species) => {
+                    self.mode_data.equation.reactants.push(species);
                     self.mode_data.new_lhs.clear();
                     self.mode_data.error_msg = None;
                     self.mode_data.changed = true;
                 }
-                Err(e) => {
-                    self.mode_data.error_msg = Some(generate_error_msg(e, &self.mode_data.new_lhs));
+                Err(_) => {
+                    let input = &self.mode_data.new_lhs;
+                    let diagnostics = Species::diagnose(input, &self.parse_options);
+                    self.mode_data.error_msg = Some(Diagnostic::format_all(&diagnostics, input));
                 }
             }
         }
 
         ui.heading("RHS");
         let mut to_del = None;
-        for (i, (_, formula)) in self.mode_data.rhs_mols.iter().enumerate() {
+        for (i, species) in self.mode_data.equation.products.iter().enumerate() {
             ui.horizontal(|ui| {
                 if ui.button("-").clicked() {
                     to_del = Some(i);
                 }
-                ui.label(format_formula(formula));
+                ui.label(render_species(species));
             });
         }
         if let Some(i) = to_del {
-            self.mode_data.rhs_mols.remove(i);
+            self.mode_data.equation.products.remove(i);
             self.mode_data.changed = true;
         }
 
@@ -269,23 +360,99 @@ impl StoikApp {
                 .lost_focus()
                 || ui.button("+").clicked();
         });
-        if lost_focus && !self.mode_data.new_rhs.is_empty() && self.mode_data.error_msg.is_none() {
-            match Molecule::from_formula(&self.mode_data.new_rhs) {
-                Ok(mol) => {
-                    self.mode_data
-                        .rhs_mols
-                        .push((mol, self.mode_data.new_rhs.to_string()));
+        if lost_focus && !self.mode_data.new_rhs.is_empty() {
+            match Species::new_with(&self.mode_data.new_rhs, &self.parse_options) {
+                Ok(species) => {
+                    self.mode_data.equation.products.push(species);
                     self.mode_data.new_rhs.clear();
                     self.mode_data.error_msg = None;
                     self.mode_data.changed = true;
                 }
-                Err(e) => {
-                    self.mode_data.error_msg = Some(generate_error_msg(e, &self.mode_data.new_rhs));
+                Err(_) => {
+                    let input = &self.mode_data.new_rhs;
+                    let diagnostics = Species::diagnose(input, &self.parse_options);
+                    self.mode_data.error_msg = Some(Diagnostic::format_all(&diagnostics, input));
                 }
             }
         }
     }
 
+    fn ui_empirical(&mut self, ui: &mut Ui) {
+        ui.heading("Element masses");
+        ui.label("Enter the mass or mass percentage of each element");
+        let masses = &mut self.mode_data.empirical_masses;
+        let mut to_del = None;
+        for (i, (element, mass)) in masses.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.button("-").clicked() {
+                    to_del = Some(i);
+                }
+                ui.add(TextEdit::singleline(element).desired_width(40.0));
+                ui.add(TextEdit::singleline(mass).desired_width(80.0));
+            });
+        }
+        if let Some(i) = to_del {
+            masses.remove(i);
+        }
+        if ui.button("+").clicked() {
+            masses.push(Default::default());
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Molar mass (optional)");
+            ui.add(
+                TextEdit::singleline(&mut self.mode_data.empirical_molar_mass).desired_width(80.0),
+            );
+        });
+        ui.separator();
+
+        let parsed = masses
+            .iter()
+            .filter(|(element, _)| !element.trim().is_empty())
+            .map(|(element, mass)| {
+                let mass = mass.trim().trim_end_matches(['%', 'g']).trim();
+                mass.parse::<f64>()
+                    .map(|mass| (element.trim(), mass))
+                    .map_err(|_| format!("`{mass}` is not a mass for `{element}`"))
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let molar_mass = self.mode_data.empirical_molar_mass.trim();
+        let result = parsed.and_then(|masses| {
+            if masses.is_empty() {
+                return Err(String::new());
+            }
+            let formula = if molar_mass.is_empty() {
+                Molecule::empirical_formula(&masses)
+            } else {
+                let molar_mass = molar_mass
+                    .parse::<f64>()
+                    .map_err(|_| format!("`{molar_mass}` is not a molar mass"))?;
+                Molecule::molecular_formula(&masses, molar_mass)
+            };
+            formula.map_err(|e| e.to_string())
+        });
+
+        match result {
+            Ok(formula) => {
+                let kind = if molar_mass.is_empty() {
+                    "Empirical"
+                } else {
+                    "Molecular"
+                };
+                ui.heading(format!(
+                    "{kind} formula: {}",
+                    OutputFormat::Unicode
+                        .render(&formula.to_string_with(self.order))
+                        .unwrap_or_else(|_| formula.to_string_with(self.order))
+                ));
+            }
+            Err(e) if !e.is_empty() => {
+                ui.label(RichText::new(e).color(ui.visuals().error_fg_color));
+            }
+            Err(_) => {}
+        }
+    }
+
     fn table_body_contents(&mut self, mut body: TableBody) {
         let height = body
             .ui_mut()
@@ -300,13 +467,13 @@ impl StoikApp {
                     row.col(|ui| {
                         ui.monospace(format!(
                             "{key}: {}",
-                            self.mode_data.lhs.get(key).unwrap_or(&0)
+                            self.mode_data.lhs.get(key).copied().unwrap_or_default()
                         ));
                     });
                     row.col(|ui| {
                         ui.monospace(format!(
                             "{key}: {}",
-                            self.mode_data.rhs.get(key).unwrap_or(&0)
+                            self.mode_data.rhs.get(key).copied().unwrap_or_default()
                         ));
                     });
                     if self.all_atoms {
@@ -321,41 +488,107 @@ impl StoikApp {
                 })
             }
         }
+
+        let (lhs_charge, rhs_charge) = self.mode_data.charges;
+        if self.all_atoms || lhs_charge != rhs_charge {
+            body.row(height, |mut row| {
+                row.col(|ui| {
+                    ui.monospace(format!("Charge: {lhs_charge}"));
+                });
+                row.col(|ui| {
+                    ui.monospace(format!("Charge: {rhs_charge}"));
+                });
+                if self.all_atoms {
+                    row.col(|ui| {
+                        if lhs_charge == rhs_charge {
+                            ui.label("✅");
+                        } else {
+                            ui.label("❌");
+                        }
+                    });
+                }
+            })
+        }
     }
 
     fn show_balance_summary(&mut self, ui: &mut Ui) {
         if self.mode_data.changed {
-            self.mode_data.lhs.clear();
-            self.mode_data.rhs.clear();
-            self.mode_data.balanced.clear();
-
-            for (mol, _) in &self.mode_data.lhs_mols {
-                extend_mol_map(&mut self.mode_data.lhs, mol.get_map());
-            }
-
-            for (mol, _) in &self.mode_data.rhs_mols {
-                extend_mol_map(&mut self.mode_data.rhs, mol.get_map());
-            }
-
-            let mut keys = self.mode_data.lhs.keys().collect::<Vec<_>>();
-            keys.extend(self.mode_data.rhs.keys());
-            keys.dedup();
-
-            for key in keys {
-                self.mode_data.balanced.insert(
-                    key.to_string(),
-                    self.mode_data.lhs.get(key) == self.mode_data.rhs.get(key),
-                );
-            }
+            self.mode_data.lhs = self.mode_data.equation.reactant_map();
+            self.mode_data.rhs = self.mode_data.equation.product_map();
+            self.mode_data.balanced = self.mode_data.equation.element_balance();
+            self.mode_data.charges = (
+                self.mode_data.equation.reactant_charge(),
+                self.mode_data.equation.product_charge(),
+            );
+            self.mode_data.action_error = None;
             self.mode_data.changed = false;
         }
 
-        let balanced = self.mode_data.balanced.values().all(|x| *x);
+        let balanced = self.mode_data.balanced.values().all(|x| *x)
+            && self.mode_data.charges.0 == self.mode_data.charges.1;
 
         if balanced {
             ui.heading("Your equation is balanced");
         } else {
-            ui.heading("Your equation is not balanced");
+            ui.horizontal(|ui| {
+                ui.heading("Your equation is not balanced");
+                if ui.button("⚖ Balance").clicked() {
+                    self.mode_data.action_error = None;
+                    match self.mode_data.equation.balance() {
+                        Ok(equation) => {
+                            self.mode_data.text_input = equation.to_string();
+                            self.mode_data.equation = equation;
+                            self.mode_data.changed = true;
+                        }
+                        Err(e) => self.mode_data.action_error = Some(e.to_string()),
+                    }
+                }
+            });
+        }
+
+        // species that cannot be closed-shell molecules are probably mistyped
+        let equation = &self.mode_data.equation;
+        for species in equation.reactants.iter().chain(&equation.products) {
+            let Some(warning) = species.molecule.check_valence() else {
+                continue;
+            };
+            let text = format!("⚠ {}: {}", render_species(species), warning.message);
+            let label = ui.label(RichText::new(text).color(ui.visuals().warn_fg_color));
+            if let Some(suggestion) = &warning.suggestion {
+                label.on_hover_text(suggestion);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.mode_data.medium, Medium::Acidic, "Acidic");
+            ui.selectable_value(&mut self.mode_data.medium, Medium::Basic, "Basic");
+            if ui.button("⚗ Redox half-reactions").clicked() {
+                self.mode_data.action_error = None;
+                match self.mode_data.equation.balance_redox(self.mode_data.medium) {
+                    Ok(redox) => {
+                        self.mode_data.text_input = redox.overall.to_string();
+                        self.mode_data.equation = redox.overall.clone();
+                        self.mode_data.redox = Some(redox);
+                        self.mode_data.changed = true;
+                    }
+                    Err(e) => self.mode_data.action_error = Some(e.to_string()),
+                }
+            }
+        });
+        // the equation is still shown when balancing it fails
+        if let Some(e) = &self.mode_data.action_error {
+            ui.label(RichText::new(e).color(ui.visuals().error_fg_color));
+        }
+        // only show the half-reactions while the equation is still the one they made
+        let equation = &self.mode_data.equation;
+        if let Some(redox) = self
+            .mode_data
+            .redox
+            .as_ref()
+            .filter(|x| x.overall == *equation)
+        {
+            ui.monospace(format!("Oxidation: {}", render_equation(&redox.oxidation)));
+            ui.monospace(format!("Reduction: {}", render_equation(&redox.reduction)));
         }
 
         if self.all_atoms || !balanced {
@@ -380,69 +613,289 @@ impl StoikApp {
                 })
                 .body(|body| self.table_body_contents(body));
         }
-    }
-}
 
-pub fn id(id: &str) -> String {
-    format!("{APP_NAME}-{id}")
-}
+        ui.separator();
+        self.show_mass_table(ui);
+        self.show_composition(ui);
 
-fn generate_error_msg(e: StoikError, formula: &str) -> String {
-    match e {
-        StoikError::InvalidToken(loc) => {
-            loc.format_msg(formula, "Malformed formula", "Illegal token")
+        if balanced {
+            ui.separator();
+            self.show_yield_table(ui);
         }
-        StoikError::NumberFirst(loc) => loc.format_msg(
-            formula,
-            "Malformed formula",
-            "Compound groups cannot start with numbers",
-        ),
-        StoikError::UnpairedParenthesis(loc) => {
-            loc.format_msg(formula, "Malformed formula", "Unpaired parenthesis")
+    }
+
+    fn show_composition(&self, ui: &mut Ui) {
+        let equation = &self.mode_data.equation;
+        for (i, species) in equation
+            .reactants
+            .iter()
+            .chain(&equation.products)
+            .enumerate()
+        {
+            let Ok(mut composition) = species.molecule.mass_composition() else {
+                continue;
+            };
+            let isotopes = species.molecule.isotopes(self.order);
+            composition.sort_by_key(|x| isotopes.iter().position(|i| i.symbol == x.element));
+            ui.collapsing(render_species(species), |ui| {
+                egui::Grid::new(("composition", i))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Element");
+                        ui.label("Count");
+                        ui.label("Mass %");
+                        ui.label("Atom fraction");
+                        ui.end_row();
+                        for element in composition {
+                            ui.monospace(element.element);
+                            ui.monospace(element.count.to_string());
+                            ui.monospace(format!("{:.3}%", element.mass_percent));
+                            ui.monospace(format!("{:.4}", element.atom_fraction));
+                            ui.end_row();
+                        }
+                    });
+                if let Ok(degree) = species.molecule.degree_of_unsaturation() {
+                    ui.label(format!("Degree of unsaturation: {degree}"));
+                }
+                let molecule = &species.molecule;
+                if let (Ok(monoisotopic), Ok(most_abundant), Ok(envelope)) = (
+                    molecule.monoisotopic_mass(),
+                    molecule.most_abundant_mass(),
+                    molecule.isotopic_envelope(PEAK_THRESHOLD),
+                ) {
+                    ui.label(format!("Monoisotopic mass: {monoisotopic:.6} Da"));
+                    ui.label(format!("Most abundant mass: {most_abundant:.6} Da"));
+                    stick_spectrum(ui, &envelope);
+                }
+            });
         }
-        StoikError::UnpairedBracket(loc) => {
-            loc.format_msg(formula, "Malformed formula", "Unpaired bracket")
+    }
+
+    fn show_yield_table(&mut self, ui: &mut Ui) {
+        let height = ui
+            .style()
+            .text_styles
+            .get(&TextStyle::Monospace)
+            .unwrap()
+            .size;
+        // the equation can change earlier in the frame, like when it is balanced
+        let equation = &self.mode_data.equation;
+        self.mode_data
+            .lhs_mols
+            .resize(equation.reactants.len(), String::new());
+        self.mode_data
+            .rhs_mols
+            .resize(equation.products.len(), String::new());
+        let amounts = self
+            .mode_data
+            .lhs_mols
+            .iter()
+            .map(|x| x.parse::<Amount>())
+            .collect::<Result<Vec<_>, _>>();
+        let yields = amounts.map(|x| equation.reaction_yield(&x));
+
+        ui.label("Enter an amount for each reactant, like `2.5g` or `0.1mol`, and optionally the actual yield of each product");
+        TableBuilder::new(ui)
+            .id_salt("yields")
+            .striped(true)
+            .columns(Column::auto(), 4)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .header(24.0, |mut header| {
+                for label in ["Species", "Amount", "Used / Theoretical", "Left / Percent"] {
+                    header.col(|ui| {
+                        ui.label(label);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for (i, species) in equation.reactants.iter().enumerate() {
+                    body.row(height, |mut row| {
+                        row.col(|ui| {
+                            ui.monospace(render_species(species));
+                        });
+                        row.col(|ui| {
+                            ui.text_edit_singleline(&mut self.mode_data.lhs_mols[i]);
+                        });
+                        if let Ok(Ok(yields)) = &yields {
+                            let reactant = &yields.reactants[i];
+                            row.col(|ui| {
+                                ui.monospace(reactant.used.to_string());
+                            });
+                            row.col(|ui| {
+                                if i == yields.limiting {
+                                    ui.strong("Limiting");
+                                } else {
+                                    ui.monospace(reactant.left.to_string());
+                                }
+                            });
+                        }
+                    });
+                }
+                for (i, species) in equation.products.iter().enumerate() {
+                    body.row(height, |mut row| {
+                        row.col(|ui| {
+                            ui.monospace(render_species(species));
+                        });
+                        row.col(|ui| {
+                            ui.text_edit_singleline(&mut self.mode_data.rhs_mols[i]);
+                        });
+                        if let Ok(Ok(yields)) = &yields {
+                            let product = &yields.products[i];
+                            row.col(|ui| {
+                                ui.monospace(product.theoretical.to_string());
+                            });
+                            row.col(|ui| {
+                                let actual = self.mode_data.rhs_mols[i].parse().ok();
+                                if let Some(percent) = actual.and_then(|x| product.percent_yield(x))
+                                {
+                                    ui.monospace(format!("{percent:.2}%"));
+                                }
+                            });
+                        }
+                    });
+                }
+            });
+
+        if let Ok(Err(e)) = yields {
+            ui.label(RichText::new(e.to_string()).color(ui.visuals().error_fg_color));
         }
-        e => e.to_string(),
     }
+
+    fn show_mass_table(&mut self, ui: &mut Ui) {
+        let height = ui
+            .style()
+            .text_styles
+            .get(&TextStyle::Monospace)
+            .unwrap()
+            .size;
+        let equation = &self.mode_data.equation;
+
+        TableBuilder::new(ui)
+            .id_salt("masses")
+            .striped(true)
+            .columns(Column::auto(), 2)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .header(24.0, |mut header| {
+                header.col(|ui| {
+                    ui.label("Species");
+                });
+                header.col(|ui| {
+                    ui.label("Molar mass");
+                });
+            })
+            .body(|mut body| {
+                for species in equation.reactants.iter().chain(&equation.products) {
+                    body.row(height, |mut row| {
+                        row.col(|ui| {
+                            ui.monospace(render_species(species));
+                        });
+                        row.col(|ui| match species.molecule.molar_mass() {
+                            Ok(mass) => {
+                                ui.monospace(mass.to_string());
+                            }
+                            Err(e) => {
+                                ui.label(
+                                    RichText::new(e.to_string()).color(ui.visuals().error_fg_color),
+                                );
+                            }
+                        });
+                    });
+                }
+            });
+    }
+}
+
+pub fn id(id: &str) -> String {
+    format!("{APP_NAME}-{id}")
 }
 
-fn format_formula(formula: &str) -> String {
-    let mut chars = formula.trim().chars().peekable();
-    let mut new = String::new();
+/// Writes a species with subscripts and superscripts
+fn render_species(species: &Species) -> String {
+    species
+        .render(OutputFormat::Unicode)
+        .unwrap_or_else(|_| species.formula.clone())
+}
 
-    while chars.peek().unwrap_or(&'a').is_ascii_digit() {
-        new.push(chars.next().unwrap());
+/// Draws an isotopic envelope as a stick spectrum, with a line for each peak as tall as its
+/// abundance. Hovering over the plot shows the nearest peak
+fn stick_spectrum(ui: &mut Ui, envelope: &[Peak]) {
+    let (Some(first), Some(last)) = (envelope.first(), envelope.last()) else {
+        return;
+    };
+    // a mass unit either side keeps the outer peaks off the edge
+    let (low, high) = (first.mass.floor() - 1.0, last.mass.ceil() + 1.0);
+    let font = TextStyle::Small.resolve(ui.style());
+    let (response, painter) =
+        ui.allocate_painter(vec2(ui.available_width().min(400.0), 120.0), Sense::hover());
+    let mut plot = response.rect;
+    plot.max.y -= font.size + 4.0;
+    let x = |mass: f64| plot.left() + plot.width() * ((mass - low) / (high - low)) as f32;
+
+    let visuals = ui.visuals();
+    let text_color = visuals.text_color();
+    painter.line_segment(
+        [plot.left_bottom(), plot.right_bottom()],
+        visuals.widgets.noninteractive.fg_stroke,
+    );
+    let stick = Stroke::new(2.0, visuals.selection.bg_fill);
+    for peak in envelope {
+        let top = plot.bottom() - plot.height() * peak.abundance as f32;
+        painter.line_segment(
+            [pos2(x(peak.mass), plot.bottom()), pos2(x(peak.mass), top)],
+            stick,
+        );
     }
 
-    for i in chars {
-        if i.is_ascii_digit() {
-            let num = i.to_digit(10).unwrap();
-            new.push(char::from_u32(0x2080 + num).unwrap());
-        } else {
-            new.push(i)
+    // label the tallest and outer peaks, skipping any that would overlap
+    let tallest = envelope
+        .iter()
+        .max_by(|a, b| a.abundance.total_cmp(&b.abundance))
+        .unwrap_or(first);
+    let mut labelled: Vec<f32> = Vec::new();
+    for peak in [tallest, first, last] {
+        let x = x(peak.mass);
+        if labelled.iter().any(|other| (other - x).abs() < 50.0) {
+            continue;
         }
+        labelled.push(x);
+        painter.text(
+            pos2(x, plot.bottom() + 2.0),
+            Align2::CENTER_TOP,
+            format!("{:.1}", peak.mass),
+            font.clone(),
+            text_color,
+        );
     }
 
-    new
-}
-
-// maybe make generic
-fn extend_mol_map(main: &mut HashMap<String, i64>, mol: HashMap<String, i64>) {
-    for (key, mol_val) in mol {
-        if let Entry::Occupied(mut entry) = main.entry(key.clone()) {
-            *entry.get_mut() += mol_val;
-        } else {
-            main.insert(key, mol_val);
+    if let Some(pointer) = response.hover_pos() {
+        let nearest = envelope.iter().min_by(|a, b| {
+            (x(a.mass) - pointer.x)
+                .abs()
+                .total_cmp(&(x(b.mass) - pointer.x).abs())
+        });
+        if let Some(peak) = nearest {
+            response.on_hover_text_at_pointer(format!(
+                "{:.4} Da, {:.2}%",
+                peak.mass,
+                peak.abundance * 100.0
+            ));
         }
     }
 }
 
+/// Writes an equation with subscripts, superscripts and a proper arrow
+fn render_equation(equation: &Equation) -> String {
+    equation
+        .render(OutputFormat::Unicode)
+        .unwrap_or_else(|_| equation.to_string())
+}
+
 #[derive(Debug, Default, PartialEq, Eq, EnumIter, Clone, Copy)]
 enum Mode {
     #[default]
     Text,
     List,
+    Empirical,
 }
 
 impl Display for Mode {
@@ -450,6 +903,7 @@ impl Display for Mode {
         match self {
             Self::Text => write!(f, "Text"),
             Self::List => write!(f, "List"),
+            Self::Empirical => write!(f, "Empirical formula"),
         }
     }
 }
@@ -458,11 +912,19 @@ impl Display for Mode {
 struct ModeData {
     text_input: String,
     error_msg: Option<String>,
-    lhs_mols: Vec<(Molecule, String)>,
-    rhs_mols: Vec<(Molecule, String)>,
-    lhs: HashMap<String, i64>,
-    rhs: HashMap<String, i64>,
+    /// The error from the last time the balance or redox button was pressed
+    action_error: Option<String>,
+    equation: Equation,
+    lhs: HashMap<String, Rational>,
+    rhs: HashMap<String, Rational>,
     balanced: HashMap<String, bool>,
+    charges: (i128, i128),
+    medium: Medium,
+    redox: Option<Redox>,
+    empirical_masses: Vec<(String, String)>,
+    empirical_molar_mass: String,
+    lhs_mols: Vec<String>,
+    rhs_mols: Vec<String>,
     changed: bool,
     new_lhs: String,
     new_rhs: String,
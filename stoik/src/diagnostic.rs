@@ -0,0 +1,183 @@
+use std::fmt::Display;
+
+use crate::{elements, equation::ARROWS, err::StoikError, formula::TokenLoc};
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// The input cannot be used
+    Error,
+    /// The input can be used, but is probably not what was meant
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+        }
+    }
+}
+
+/// A problem found in a formula or equation, with where it is and how it could be fixed
+///
+/// These are made from a [`StoikError`] with [`Diagnostic::from_error`], or all at once by
+/// [`Molecule::diagnose`](crate::formula::Molecule::diagnose) and
+/// [`Equation::diagnose`](crate::equation::Equation::diagnose)
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{Molecule, TokenLoc};
+/// use stoik::{Diagnostic, Severity};
+///
+/// let err = Molecule::from_formula("H2O)").unwrap_err();
+/// let diagnostic = Diagnostic::from_error(&err, "H2O)");
+/// assert_eq!(diagnostic.severity, Severity::Error);
+/// assert_eq!(diagnostic.span, Some(TokenLoc::new(4, 1)));
+/// assert_eq!(diagnostic.message, "Unpaired parenthesis");
+/// assert_eq!(
+///     diagnostic.format("H2O)"),
+///     "Error: H2O)
+///           ^
+///           Unpaired parenthesis
+///           Remove it, or add a `(` before it"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    /// How serious the problem is
+    pub severity: Severity,
+    /// Where the problem is in the input, if it is somewhere in particular
+    pub span: Option<TokenLoc>,
+    /// What the problem is
+    pub message: String,
+    /// A suggested way to fix the problem
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    /// Makes an error diagnostic from a [`StoikError`], where `input` is the text the
+    /// error's location is in. This is used to suggest fixes, like elements an
+    /// [`UnknownElement`](StoikError::UnknownElement) may have been meant to be
+    pub fn from_error(err: &StoikError, input: &str) -> Self {
+        let span = err.get_loc().cloned();
+        let text = span
+            .as_ref()
+            .map(|loc| {
+                input
+                    .chars()
+                    .skip(loc.start().saturating_sub(1))
+                    .take(loc.len())
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+
+        let (message, suggestion) = match err {
+            StoikError::InvalidToken(_) => (
+                "Illegal token".to_string(),
+                Some(format!("Remove `{text}`")),
+            ),
+            StoikError::NumberFirst(_) => (
+                "Compound groups cannot start with numbers".to_string(),
+                Some("Move the number after the atom or group it multiplies".to_string()),
+            ),
+            StoikError::UnpairedParenthesis(_) | StoikError::UnpairedBracket(_) => {
+                let message = match err {
+                    StoikError::UnpairedBracket(_) => "Unpaired bracket",
+                    _ => "Unpaired parenthesis",
+                };
+                let suggestion = match text.as_str() {
+                    "(" => "Add a `)` to close it",
+                    "[" => "Add a `]` to close it",
+                    "{" => "Add a `}` to close it",
+                    ")" => "Remove it, or add a `(` before it",
+                    _ => "Remove it, or add a `[` before it",
+                };
+                (message.to_string(), Some(suggestion.to_string()))
+            }
+            StoikError::MissingSpecies(loc) => (
+                "Missing species".to_string(),
+                Some(missing_species_suggestion(input, loc).to_string()),
+            ),
+            StoikError::Overflow(_) => ("Number too big".to_string(), None),
+            StoikError::UnclosedRing(_) => (
+                "Unclosed ring".to_string(),
+                Some(format!(
+                    "Add another `{text}` to close the ring, or remove it"
+                )),
+            ),
+            StoikError::UnknownElement(_) => {
+                let suggestions = elements::suggest(&text);
+                let suggestion = (!suggestions.is_empty())
+                    .then(|| format!("Did you mean {}", suggestions.join(", ")));
+                ("Unknown element".to_string(), suggestion)
+            }
+            e => (e.to_string(), None),
+        };
+
+        Self {
+            severity: Severity::Error,
+            span,
+            message,
+            suggestion,
+        }
+    }
+
+    /// Prints the diagnostic with the relevant bit of `input` highlighted, see [`TokenLoc::format_msg`]
+    pub fn format(&self, input: &str) -> String {
+        let mut diag = self.message.clone();
+        if let Some(suggestion) = &self.suggestion {
+            diag += "\n";
+            diag += suggestion;
+        }
+        match &self.span {
+            Some(span) => span.format_msg(input, &self.severity.to_string(), &diag),
+            None => format!("{}: {diag}", self.severity),
+        }
+    }
+
+    /// Prints every diagnostic with [`format`](Self::format), with a blank line between each one
+    pub fn format_all(diagnostics: &[Diagnostic], input: &str) -> String {
+        diagnostics
+            .iter()
+            .map(|x| x.format(input))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Moves the span of the diagnostic right past `before`
+    pub(crate) fn offset(mut self, before: &str) -> Self {
+        if let Some(span) = &mut self.span {
+            span.offset(before);
+        }
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Suggests how to fill an empty species slot from what is on either side of it,
+/// so only a slot next to a `+` between species mentions removing it
+fn missing_species_suggestion(input: &str, loc: &TokenLoc) -> &'static str {
+    let before = input.chars().take(loc.start() - 1).collect::<String>();
+    let before = before.trim_end();
+    let after = input.chars().skip(loc.start() - 1).collect::<String>();
+    let after = after.trim_start();
+    if before.ends_with('+') || after.starts_with('+') {
+        "Add a species, or remove the extra `+`"
+    } else if ARROWS.iter().any(|arrow| after.starts_with(arrow)) {
+        "Add a reactant before the arrow"
+    } else if ARROWS.iter().any(|arrow| before.ends_with(arrow)) {
+        "Add a product after the arrow"
+    } else {
+        "Add a species"
+    }
+}
@@ -0,0 +1,403 @@
+//! Data about the chemical elements, based on the IUPAC periodic table
+//!
+//! ```
+//! use stoik::elements;
+//!
+//! let iron = elements::get("Fe").unwrap();
+//! assert_eq!(iron.name, "Iron");
+//! assert_eq!(iron.atomic_number, 26);
+//! assert_eq!(iron.electronegativity, Some(1.83));
+//! assert!(elements::get("Xy").is_none());
+//! ```
+
+/// The standard atomic weight of an element, as given by IUPAC
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomicWeight {
+    /// A single value with its standard uncertainty, e.g. `22.98976928 ± 0.00000002` for sodium,
+    /// which IUPAC writes as `22.98976928(2)`
+    Value {
+        /// The atomic weight
+        value: f64,
+        /// The standard uncertainty of the weight, in the same units as the weight
+        /// rather than in its last digits
+        uncertainty: f64,
+    },
+    /// An interval that covers the weight found in normal materials, e.g. `[1.00784, 1.00811]` for hydrogen
+    Interval {
+        /// The lower bound of the weight
+        low: f64,
+        /// The upper bound of the weight
+        high: f64,
+    },
+    /// The element has no stable isotopes, so this is the mass number of its longest lived isotope
+    MassNumber(u16),
+}
+
+/// One element in the periodic table
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Element {
+    /// The symbol, like `Fe`
+    pub symbol: &'static str,
+    /// The English name, like `Iron`
+    pub name: &'static str,
+    /// The number of protons
+    pub atomic_number: u8,
+    /// The standard atomic weight
+    pub atomic_weight: AtomicWeight,
+    /// The Pauling electronegativity, if it is known
+    pub electronegativity: Option<f64>,
+}
+
+impl Element {
+    /// Gets the period, or row, of the periodic table the element is in
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::elements;
+    ///
+    /// assert_eq!(elements::get("H").unwrap().period(), 1);
+    /// assert_eq!(elements::get("Fe").unwrap().period(), 4);
+    /// ```
+    pub fn period(&self) -> u8 {
+        // the atomic number of the last element in each period
+        let ends = [2, 10, 18, 36, 54, 86];
+        ends.iter().filter(|x| **x < self.atomic_number).count() as u8 + 1
+    }
+
+    /// Gets the group, or column, of the periodic table the element is in, numbered 1 to 18.
+    /// This is [`None`] for the lanthanides and actinides, other than lutetium and lawrencium
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::elements;
+    ///
+    /// assert_eq!(elements::get("He").unwrap().group(), Some(18));
+    /// assert_eq!(elements::get("Cl").unwrap().group(), Some(17));
+    /// assert_eq!(elements::get("Pt").unwrap().group(), Some(10));
+    /// assert_eq!(elements::get("U").unwrap().group(), None);
+    /// ```
+    pub fn group(&self) -> Option<u8> {
+        let start = [1, 3, 11, 19, 37, 55, 87][self.period() as usize - 1];
+        let offset = self.atomic_number - start;
+        match self.period() {
+            1 if offset == 0 => Some(1),
+            1 => Some(18),
+            2 | 3 if offset < 2 => Some(offset + 1),
+            2 | 3 => Some(offset + 11),
+            4 | 5 => Some(offset + 1),
+            _ if offset < 2 => Some(offset + 1),
+            _ if offset < 16 => None,
+            _ => Some(offset - 13),
+        }
+    }
+
+    /// Gets the standard valences of the element, lowest first, which are worked out from its
+    /// group and period. Elements after the second period can use their d orbitals, so
+    /// sulfur can be 2, 4 or 6. This is empty for the transition metals, lanthanides and
+    /// actinides, which have no standard valence
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::elements;
+    ///
+    /// assert_eq!(elements::get("C").unwrap().valences(), [4]);
+    /// assert_eq!(elements::get("N").unwrap().valences(), [3]);
+    /// assert_eq!(elements::get("P").unwrap().valences(), [3, 5]);
+    /// assert!(elements::get("Fe").unwrap().valences().is_empty());
+    /// ```
+    pub fn valences(&self) -> &'static [u8] {
+        let expanded = self.period() > 2;
+        match self.group() {
+            Some(1) => &[1],
+            Some(2) => &[2],
+            Some(13) => &[3],
+            Some(14) => &[4],
+            Some(15) if expanded => &[3, 5],
+            Some(15) => &[3],
+            Some(16) if expanded => &[2, 4, 6],
+            Some(16) => &[2],
+            Some(17) if expanded => &[1, 3, 5, 7],
+            Some(17) => &[1],
+            // only the heavier noble gases form compounds
+            Some(18) if self.period() > 3 => &[0, 2, 4, 6, 8],
+            Some(18) => &[0],
+            _ => &[],
+        }
+    }
+}
+
+const fn element(
+    symbol: &'static str,
+    name: &'static str,
+    atomic_number: u8,
+    atomic_weight: AtomicWeight,
+    electronegativity: Option<f64>,
+) -> Element {
+    Element {
+        symbol,
+        name,
+        atomic_number,
+        atomic_weight,
+        electronegativity,
+    }
+}
+
+const fn value(value: f64, uncertainty: f64) -> AtomicWeight {
+    AtomicWeight::Value { value, uncertainty }
+}
+
+const fn interval(low: f64, high: f64) -> AtomicWeight {
+    AtomicWeight::Interval { low, high }
+}
+
+const fn mass(mass_number: u16) -> AtomicWeight {
+    AtomicWeight::MassNumber(mass_number)
+}
+
+/// Every known element, ordered by atomic number
+pub const ELEMENTS: [Element; 118] = [
+    element("H", "Hydrogen", 1, interval(1.00784, 1.00811), Some(2.20)),
+    element("He", "Helium", 2, value(4.002602, 0.000002), None),
+    element("Li", "Lithium", 3, interval(6.938, 6.997), Some(0.98)),
+    element(
+        "Be",
+        "Beryllium",
+        4,
+        value(9.0121831, 0.0000005),
+        Some(1.57),
+    ),
+    element("B", "Boron", 5, interval(10.806, 10.821), Some(2.04)),
+    element("C", "Carbon", 6, interval(12.0096, 12.0116), Some(2.55)),
+    element("N", "Nitrogen", 7, interval(14.00643, 14.00728), Some(3.04)),
+    element("O", "Oxygen", 8, interval(15.99903, 15.99977), Some(3.44)),
+    element(
+        "F",
+        "Fluorine",
+        9,
+        value(18.998403162, 0.000000005),
+        Some(3.98),
+    ),
+    element("Ne", "Neon", 10, value(20.1797, 0.0006), None),
+    element(
+        "Na",
+        "Sodium",
+        11,
+        value(22.98976928, 0.00000002),
+        Some(0.93),
+    ),
+    element("Mg", "Magnesium", 12, interval(24.304, 24.307), Some(1.31)),
+    element(
+        "Al",
+        "Aluminium",
+        13,
+        value(26.9815384, 0.0000003),
+        Some(1.61),
+    ),
+    element("Si", "Silicon", 14, interval(28.084, 28.086), Some(1.90)),
+    element(
+        "P",
+        "Phosphorus",
+        15,
+        value(30.973761998, 0.000000005),
+        Some(2.19),
+    ),
+    element("S", "Sulfur", 16, interval(32.059, 32.076), Some(2.58)),
+    element("Cl", "Chlorine", 17, interval(35.446, 35.457), Some(3.16)),
+    element("Ar", "Argon", 18, interval(39.792, 39.963), None),
+    element("K", "Potassium", 19, value(39.0983, 0.0001), Some(0.82)),
+    element("Ca", "Calcium", 20, value(40.078, 0.004), Some(1.00)),
+    element("Sc", "Scandium", 21, value(44.955907, 0.000004), Some(1.36)),
+    element("Ti", "Titanium", 22, value(47.867, 0.001), Some(1.54)),
+    element("V", "Vanadium", 23, value(50.9415, 0.0001), Some(1.63)),
+    element("Cr", "Chromium", 24, value(51.9961, 0.0006), Some(1.66)),
+    element(
+        "Mn",
+        "Manganese",
+        25,
+        value(54.938043, 0.000002),
+        Some(1.55),
+    ),
+    element("Fe", "Iron", 26, value(55.845, 0.002), Some(1.83)),
+    element("Co", "Cobalt", 27, value(58.933194, 0.000003), Some(1.88)),
+    element("Ni", "Nickel", 28, value(58.6934, 0.0004), Some(1.91)),
+    element("Cu", "Copper", 29, value(63.546, 0.003), Some(1.90)),
+    element("Zn", "Zinc", 30, value(65.38, 0.02), Some(1.65)),
+    element("Ga", "Gallium", 31, value(69.723, 0.001), Some(1.81)),
+    element("Ge", "Germanium", 32, value(72.630, 0.008), Some(2.01)),
+    element("As", "Arsenic", 33, value(74.921595, 0.000006), Some(2.18)),
+    element("Se", "Selenium", 34, value(78.971, 0.008), Some(2.55)),
+    element("Br", "Bromine", 35, interval(79.901, 79.907), Some(2.96)),
+    element("Kr", "Krypton", 36, value(83.798, 0.002), Some(3.00)),
+    element("Rb", "Rubidium", 37, value(85.4678, 0.0003), Some(0.82)),
+    element("Sr", "Strontium", 38, value(87.62, 0.01), Some(0.95)),
+    element("Y", "Yttrium", 39, value(88.905838, 0.000002), Some(1.22)),
+    element("Zr", "Zirconium", 40, value(91.222, 0.003), Some(1.33)),
+    element("Nb", "Niobium", 41, value(92.90637, 0.00001), Some(1.60)),
+    element("Mo", "Molybdenum", 42, value(95.95, 0.01), Some(2.16)),
+    element("Tc", "Technetium", 43, mass(97), Some(1.90)),
+    element("Ru", "Ruthenium", 44, value(101.07, 0.02), Some(2.20)),
+    element("Rh", "Rhodium", 45, value(102.90549, 0.00002), Some(2.28)),
+    element("Pd", "Palladium", 46, value(106.42, 0.01), Some(2.20)),
+    element("Ag", "Silver", 47, value(107.8682, 0.0002), Some(1.93)),
+    element("Cd", "Cadmium", 48, value(112.414, 0.004), Some(1.69)),
+    element("In", "Indium", 49, value(114.818, 0.001), Some(1.78)),
+    element("Sn", "Tin", 50, value(118.710, 0.007), Some(1.96)),
+    element("Sb", "Antimony", 51, value(121.760, 0.001), Some(2.05)),
+    element("Te", "Tellurium", 52, value(127.60, 0.03), Some(2.10)),
+    element("I", "Iodine", 53, value(126.90447, 0.00003), Some(2.66)),
+    element("Xe", "Xenon", 54, value(131.293, 0.006), Some(2.60)),
+    element(
+        "Cs",
+        "Caesium",
+        55,
+        value(132.90545196, 0.00000006),
+        Some(0.79),
+    ),
+    element("Ba", "Barium", 56, value(137.327, 0.007), Some(0.89)),
+    element("La", "Lanthanum", 57, value(138.90547, 0.00007), Some(1.10)),
+    element("Ce", "Cerium", 58, value(140.116, 0.001), Some(1.12)),
+    element(
+        "Pr",
+        "Praseodymium",
+        59,
+        value(140.90766, 0.00001),
+        Some(1.13),
+    ),
+    element("Nd", "Neodymium", 60, value(144.242, 0.003), Some(1.14)),
+    element("Pm", "Promethium", 61, mass(145), Some(1.13)),
+    element("Sm", "Samarium", 62, value(150.36, 0.02), Some(1.17)),
+    element("Eu", "Europium", 63, value(151.964, 0.001), Some(1.20)),
+    element("Gd", "Gadolinium", 64, value(157.249, 0.002), Some(1.20)),
+    element("Tb", "Terbium", 65, value(158.925354, 0.000007), Some(1.10)),
+    element("Dy", "Dysprosium", 66, value(162.500, 0.001), Some(1.22)),
+    element("Ho", "Holmium", 67, value(164.930329, 0.000005), Some(1.23)),
+    element("Er", "Erbium", 68, value(167.259, 0.003), Some(1.24)),
+    element("Tm", "Thulium", 69, value(168.934219, 0.000005), Some(1.25)),
+    element("Yb", "Ytterbium", 70, value(173.045, 0.010), Some(1.10)),
+    element("Lu", "Lutetium", 71, value(174.96669, 0.00005), Some(1.27)),
+    element("Hf", "Hafnium", 72, value(178.486, 0.006), Some(1.30)),
+    element("Ta", "Tantalum", 73, value(180.94788, 0.00002), Some(1.50)),
+    element("W", "Tungsten", 74, value(183.84, 0.01), Some(2.36)),
+    element("Re", "Rhenium", 75, value(186.207, 0.001), Some(1.90)),
+    element("Os", "Osmium", 76, value(190.23, 0.03), Some(2.20)),
+    element("Ir", "Iridium", 77, value(192.217, 0.002), Some(2.20)),
+    element("Pt", "Platinum", 78, value(195.084, 0.009), Some(2.28)),
+    element("Au", "Gold", 79, value(196.966570, 0.000004), Some(2.54)),
+    element("Hg", "Mercury", 80, value(200.592, 0.003), Some(2.00)),
+    element("Tl", "Thallium", 81, interval(204.382, 204.385), Some(1.62)),
+    element("Pb", "Lead", 82, interval(206.14, 207.94), Some(2.33)),
+    element("Bi", "Bismuth", 83, value(208.98040, 0.00001), Some(2.02)),
+    element("Po", "Polonium", 84, mass(209), Some(2.00)),
+    element("At", "Astatine", 85, mass(210), Some(2.20)),
+    element("Rn", "Radon", 86, mass(222), Some(2.20)),
+    element("Fr", "Francium", 87, mass(223), Some(0.79)),
+    element("Ra", "Radium", 88, mass(226), Some(0.90)),
+    element("Ac", "Actinium", 89, mass(227), Some(1.10)),
+    element("Th", "Thorium", 90, value(232.0377, 0.0004), Some(1.30)),
+    element(
+        "Pa",
+        "Protactinium",
+        91,
+        value(231.03588, 0.00001),
+        Some(1.50),
+    ),
+    element("U", "Uranium", 92, value(238.02891, 0.00003), Some(1.38)),
+    element("Np", "Neptunium", 93, mass(237), Some(1.36)),
+    element("Pu", "Plutonium", 94, mass(244), Some(1.28)),
+    element("Am", "Americium", 95, mass(243), Some(1.13)),
+    element("Cm", "Curium", 96, mass(247), Some(1.28)),
+    element("Bk", "Berkelium", 97, mass(247), Some(1.30)),
+    element("Cf", "Californium", 98, mass(251), Some(1.30)),
+    element("Es", "Einsteinium", 99, mass(252), Some(1.30)),
+    element("Fm", "Fermium", 100, mass(257), Some(1.30)),
+    element("Md", "Mendelevium", 101, mass(258), Some(1.30)),
+    element("No", "Nobelium", 102, mass(259), Some(1.30)),
+    element("Lr", "Lawrencium", 103, mass(266), Some(1.30)),
+    element("Rf", "Rutherfordium", 104, mass(267), None),
+    element("Db", "Dubnium", 105, mass(268), None),
+    element("Sg", "Seaborgium", 106, mass(269), None),
+    element("Bh", "Bohrium", 107, mass(270), None),
+    element("Hs", "Hassium", 108, mass(269), None),
+    element("Mt", "Meitnerium", 109, mass(278), None),
+    element("Ds", "Darmstadtium", 110, mass(281), None),
+    element("Rg", "Roentgenium", 111, mass(282), None),
+    element("Cn", "Copernicium", 112, mass(285), None),
+    element("Nh", "Nihonium", 113, mass(286), None),
+    element("Fl", "Flerovium", 114, mass(289), None),
+    element("Mc", "Moscovium", 115, mass(290), None),
+    element("Lv", "Livermorium", 116, mass(293), None),
+    element("Ts", "Tennessine", 117, mass(294), None),
+    element("Og", "Oganesson", 118, mass(294), None),
+];
+
+/// Gets an element from its symbol, this is case sensitive
+///
+/// # Examples
+/// ```
+/// use stoik::elements;
+///
+/// assert_eq!(elements::get("Co").unwrap().name, "Cobalt");
+/// assert!(elements::get("CO").is_none());
+/// ```
+pub fn get(symbol: &str) -> Option<&'static Element> {
+    ELEMENTS.iter().find(|x| x.symbol == symbol)
+}
+
+/// Gets an element from its atomic number
+///
+/// # Examples
+/// ```
+/// use stoik::elements;
+///
+/// assert_eq!(elements::by_number(8).unwrap().symbol, "O");
+/// assert!(elements::by_number(0).is_none());
+/// ```
+pub fn by_number(atomic_number: u8) -> Option<&'static Element> {
+    ELEMENTS.get((atomic_number as usize).checked_sub(1)?)
+}
+
+/// Suggests what an unknown symbol may have been intended to be
+///
+/// This first tries reading each letter as its own element, so `Hh` could be `HH`,
+/// then it looks for elements that are one letter off, so `Xy` could be `Xe`.
+///
+/// # Examples
+/// ```
+/// use stoik::elements;
+///
+/// assert_eq!(elements::suggest("Hh")[0], "HH");
+/// assert_eq!(elements::suggest("Xy"), vec!["Xe", "Dy"]);
+/// assert!(elements::suggest("Cq").contains(&"Co".to_string()));
+/// ```
+pub fn suggest(symbol: &str) -> Vec<String> {
+    let mut suggestions = Vec::new();
+
+    let split = symbol.chars().map(|c| c.to_uppercase().to_string());
+    if symbol.chars().count() > 1 && split.clone().all(|x| get(&x).is_some()) {
+        suggestions.push(split.collect());
+    }
+
+    let chars = symbol.chars().collect::<Vec<_>>();
+    for element in ELEMENTS {
+        let other = element.symbol.chars().collect::<Vec<_>>();
+        let close = if other.len() == chars.len() {
+            other.iter().zip(&chars).filter(|(a, b)| a != b).count() == 1
+        } else {
+            // one letter added or removed
+            let (short, long) = if other.len() < chars.len() {
+                (&other, &chars)
+            } else {
+                (&chars, &other)
+            };
+            long.len() == short.len() + 1 && long.starts_with(short)
+        };
+        if close && !suggestions.iter().any(|x| x == element.symbol) {
+            suggestions.push(element.symbol.to_string());
+        }
+    }
+
+    suggestions
+}
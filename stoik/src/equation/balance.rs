@@ -0,0 +1,188 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    err::StoikError,
+    formula::{Molecule, TokenLoc},
+    rational::{gcd, lcm, Rational},
+};
+
+/// Finds the smallest positive integer coefficients that balance a reaction
+///
+/// The coefficients are returned in the order of `reactants` then `products`,
+/// and the [`moles`](Molecule::moles) of the given molecules are ignored.
+/// This works by finding the null space of the element-by-species matrix, with
+/// an extra row for charge, using exact rational arithmetic so the result is never rounded.
+///
+/// # Errors
+///
+/// - [`StoikError::Unbalanceable`] if there is no way to balance the reaction
+/// - [`StoikError::AmbiguousBalance`] if there is more than one independent way to
+///   balance it, e.g. when two reactions are mixed into one equation
+/// - [`StoikError::Overflow`] if the coefficients are too big, with an empty location
+///   as molecules do not know where they were written
+///
+/// # Examples
+///
+/// ```
+/// use stoik::equation::balance;
+/// use stoik::formula::Molecule;
+/// use stoik::StoikError;
+///
+/// let reactants = [Molecule::from_formula("C3H8")?, Molecule::from_formula("O2")?];
+/// let products = [Molecule::from_formula("CO2")?, Molecule::from_formula("H2O")?];
+/// assert_eq!(balance(&reactants, &products)?, vec![1, 5, 3, 4]);
+///
+/// let products = [Molecule::from_formula("Fe")?];
+/// assert!(matches!(
+///     balance(&reactants, &products),
+///     Err(StoikError::Unbalanceable)
+/// ));
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+pub fn balance(reactants: &[Molecule], products: &[Molecule]) -> Result<Vec<i64>, StoikError> {
+    if reactants.is_empty() || products.is_empty() {
+        return Err(StoikError::Unbalanceable);
+    }
+
+    let columns = reactants
+        .iter()
+        .map(|x| (x, 1))
+        .chain(products.iter().map(|x| (x, -1)))
+        .collect::<Vec<_>>();
+    let solution = null_vector(&columns)?;
+
+    if solution.iter().any(|x| *x <= 0) {
+        // either a species cannot take part, or it would have to be on the other side
+        return Err(StoikError::Unbalanceable);
+    }
+
+    solution
+        .into_iter()
+        .map(|x| i64::try_from(x).map_err(|_| overflow()))
+        .collect()
+}
+
+/// Finds the only integer combination of `columns` that conserves every element and the charge,
+/// where each column is a molecule and the sign it is counted with.
+///
+/// The result is the smallest integers, with the sign chosen so that the first non-zero one is positive.
+/// Each one fits in an [`i64`], or this gives [`StoikError::Overflow`]
+pub(crate) fn null_vector(columns: &[(&Molecule, i64)]) -> Result<Vec<i128>, StoikError> {
+    let maps = columns
+        .iter()
+        .map(|(mol, _)| {
+            let mut mol = (*mol).clone();
+            mol.moles = 1;
+            mol.checked_map()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(overflow)?;
+    let elements = maps
+        .iter()
+        .flat_map(|map| map.keys())
+        .collect::<BTreeSet<_>>();
+
+    // one row per element, one column per species with its sign
+    let mut matrix = elements
+        .iter()
+        .map(|element| {
+            maps.iter()
+                .zip(columns)
+                .map(|(map, (_, sign))| {
+                    map.get(*element)
+                        .copied()
+                        .unwrap_or_default()
+                        .checked_mul(Rational::from(*sign))
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(overflow)?;
+    matrix.push(
+        columns
+            .iter()
+            .map(|(mol, sign)| mol.charge.checked_mul(*sign).map(Rational::from))
+            .collect::<Option<_>>()
+            .ok_or_else(overflow)?,
+    );
+
+    let pivots = row_reduce(&mut matrix, maps.len()).ok_or_else(overflow)?;
+    let free = (0..maps.len())
+        .filter(|col| !pivots.contains(col))
+        .collect::<Vec<_>>();
+
+    let free = match free[..] {
+        [] => return Err(StoikError::Unbalanceable),
+        [free] => free,
+        _ => return Err(StoikError::AmbiguousBalance),
+    };
+
+    // set the free variable to 1, then each pivot variable is minus its row's free column
+    let mut solution = vec![Rational::ZERO; maps.len()];
+    solution[free] = Rational::ONE;
+    for (row, pivot) in pivots.iter().enumerate() {
+        solution[*pivot] = Rational::ZERO
+            .checked_sub(matrix[row][free])
+            .ok_or_else(overflow)?;
+    }
+
+    let den_lcm = solution
+        .iter()
+        .try_fold(1, |acc, x| lcm(acc, x.den()))
+        .ok_or_else(overflow)?;
+    let nums = solution
+        .iter()
+        .map(|x| x.num().checked_mul(den_lcm / x.den()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(overflow)?;
+    let num_gcd = nums.iter().fold(0, |acc, x| gcd(acc, *x));
+    let sign = nums.iter().find(|x| **x != 0).map_or(1, |x| x.signum());
+
+    // keeping to the range of an `i64` means the coefficients can be safely multiplied together
+    nums.into_iter()
+        .map(|x| i64::try_from(x / num_gcd).map(|x| i128::from(x) * sign))
+        .collect::<Result<_, _>>()
+        .map_err(|_| overflow())
+}
+
+/// The error for coefficients that are too big, which has no location to give
+fn overflow() -> StoikError {
+    StoikError::Overflow(TokenLoc::default())
+}
+
+/// Turns `matrix` into reduced row echelon form, returning the pivot column of each non-zero row,
+/// or `None` if it overflows
+fn row_reduce(matrix: &mut [Vec<Rational>], columns: usize) -> Option<Vec<usize>> {
+    let mut pivots = Vec::new();
+    let mut row = 0;
+
+    for col in 0..columns {
+        let Some(found) = (row..matrix.len()).find(|r| !matrix[*r][col].is_zero()) else {
+            continue;
+        };
+        matrix.swap(row, found);
+
+        let pivot = matrix[row][col];
+        for x in matrix[row].iter_mut() {
+            *x = x.checked_div(pivot)?;
+        }
+
+        let pivot_row = matrix[row].clone();
+        for (i, other) in matrix.iter_mut().enumerate() {
+            let factor = other[col];
+            if i != row && !factor.is_zero() {
+                for (x, pivot_x) in other.iter_mut().zip(&pivot_row) {
+                    *x = x.checked_sub(pivot_x.checked_mul(factor)?)?;
+                }
+            }
+        }
+
+        pivots.push(col);
+        row += 1;
+        if row == matrix.len() {
+            break;
+        }
+    }
+
+    Some(pivots)
+}
@@ -0,0 +1,742 @@
+//! This module is for parsing whole chemical equations, built on top of [`formula`](crate::formula)
+//!
+//! See the documentation for [`Equation`] and [`Species`] for more info
+//! ```
+//! use stoik::equation::Equation;
+//!
+//! let equation: Equation = "2H2 + O2 -> 2H2O".parse()?;
+//! assert_eq!(equation.reactants.len(), 2);
+//! assert!(equation.is_balanced());
+//! # Ok::<(), stoik::StoikError>(())
+//! ```
+mod balance;
+mod redox;
+mod yields;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    ops::Range,
+    str::FromStr,
+};
+
+use crate::{
+    diagnostic::Diagnostic,
+    err::StoikError,
+    formula::{Molecule, ParseOptions, State, TokenLoc},
+    rational::Rational,
+};
+
+pub use balance::*;
+pub use redox::*;
+pub use yields::*;
+
+/// The arrows that can be used to seperate reactants from products, including the
+/// Unicode arrows used when an equation is pasted from elsewhere
+pub const ARROWS: [&str; 6] = ["->", "=>", "→", "⟶", "⇌", "⇄"];
+
+/// The kind of arrow between the reactants and products of an equation,
+/// which is kept so the equation is written back out with it
+///
+/// # Examples
+/// ```
+/// use stoik::equation::{Arrow, Equation};
+///
+/// let equation: Equation = "N2 + H2 ⇌ NH3".parse()?;
+/// assert_eq!(equation.arrow, Arrow::Equilibrium);
+/// assert_eq!(equation.balance()?.to_string(), "N2 + 3H2 ⇌ 2NH3");
+///
+/// assert_eq!(Arrow::from_symbol("=>"), Some(Arrow::Forward));
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Arrow {
+    /// A reaction that goes one way, written `->`, `=>`, `→` or `⟶`
+    #[default]
+    Forward,
+    /// A reaction at equilibrium, written `⇌`
+    Equilibrium,
+    /// A reaction that goes both ways, written `⇄`
+    Reversible,
+}
+
+impl Arrow {
+    /// Gets the symbol the arrow is written with, which is `->` for a forward arrow
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Arrow::Forward => "->",
+            Arrow::Equilibrium => "⇌",
+            Arrow::Reversible => "⇄",
+        }
+    }
+
+    /// Gets the kind of arrow from one of the [`ARROWS`]
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "->" | "=>" | "→" | "⟶" => Some(Arrow::Forward),
+            "⇌" => Some(Arrow::Equilibrium),
+            "⇄" => Some(Arrow::Reversible),
+            _ => None,
+        }
+    }
+}
+
+/// One species in an equation, e.g. the `2H2O` in `2H2 + O2 -> 2H2O`
+///
+/// # Examples
+///
+/// ```
+/// use stoik::equation::Species;
+///
+/// let water = Species::new("2H2O")?;
+/// assert_eq!(water.molecule.moles, 2);
+/// assert_eq!(water.formula, "2H2O");
+///
+/// // species can also be written as SMILES in braces
+/// let ethanol = Species::new("2{CCO}(l)")?;
+/// assert_eq!(ethanol.molecule.to_string(), "2 C2H6O(l)");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Species {
+    /// The parsed molecule
+    pub molecule: Molecule,
+    /// The formula as it was written
+    pub formula: String,
+    /// The location of the formula in the source text
+    pub loc: TokenLoc,
+}
+
+impl Species {
+    /// Parses a single species from a formula, ignoring surrounding whitespace
+    pub fn new(formula: &str) -> Result<Self, StoikError> {
+        Self::new_with(formula, &ParseOptions::default())
+    }
+
+    /// Parses a single species from a formula using the given [`ParseOptions`]
+    pub fn new_with(formula: &str, options: &ParseOptions) -> Result<Self, StoikError> {
+        let leading = &formula[..formula.len() - formula.trim_start().len()];
+        let trimmed = formula.trim();
+        if trimmed.is_empty() {
+            return Err(StoikError::MissingSpecies(TokenLoc::locate(
+                formula,
+                1,
+                formula.chars().count().max(1),
+            )));
+        }
+
+        let mut molecule = match is_smiles(trimmed) {
+            true => smiles_molecule(trimmed),
+            false => Molecule::from_formula_with(trimmed, options),
+        }
+        .map_err(|e| e.offset(leading))?;
+        molecule.offset_locs(leading);
+        Ok(Self {
+            molecule,
+            formula: trimmed.to_string(),
+            loc: TokenLoc::locate(
+                formula,
+                leading.chars().count() + 1,
+                trimmed.chars().count(),
+            ),
+        })
+    }
+
+    /// Finds every problem in a species with [`Molecule::diagnose`], instead of
+    /// stopping at the first one like [`new_with`](Self::new_with) does
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Species;
+    /// use stoik::formula::{ParseOptions, TokenLoc};
+    ///
+    /// let diagnostics = Species::diagnose("  2H2$O", &ParseOptions::default());
+    /// assert_eq!(diagnostics[0].span, Some(TokenLoc::new(6, 1)));
+    /// ```
+    pub fn diagnose(formula: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+        let leading = &formula[..formula.len() - formula.trim_start().len()];
+        let trimmed = formula.trim();
+        if trimmed.is_empty() {
+            let loc = TokenLoc::locate(formula, 1, formula.chars().count().max(1));
+            return vec![Diagnostic::from_error(
+                &StoikError::MissingSpecies(loc),
+                formula,
+            )];
+        }
+
+        if is_smiles(trimmed) {
+            return match smiles_molecule(trimmed) {
+                Ok(_) => Vec::new(),
+                Err(e) => vec![Diagnostic::from_error(&e, trimmed).offset(leading)],
+            };
+        }
+        Molecule::diagnose(trimmed, options)
+            .into_iter()
+            .map(|x| x.offset(leading))
+            .collect()
+    }
+
+    /// Gets the formula without its leading coefficient
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Species;
+    ///
+    /// assert_eq!(Species::new("2 H2O")?.without_coefficient(), "H2O");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn without_coefficient(&self) -> &str {
+        strip_coefficient(&self.formula)
+    }
+
+    /// Gets the SMILES string of the species, if it was written as SMILES in braces
+    /// like `2{CCO}`, see [`smiles`](crate::smiles)
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    ///
+    /// let equation: Equation = "{CCO} + 3O2 -> 2CO2 + 3H2O".parse()?;
+    /// assert_eq!(equation.reactants[0].smiles(), Some("CCO"));
+    /// assert_eq!(equation.reactants[1].smiles(), None);
+    /// assert!(equation.is_balanced());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn smiles(&self) -> Option<&str> {
+        let inner = strip_coefficient(&self.formula).strip_prefix('{')?;
+        inner.split('}').next()
+    }
+}
+
+impl Display for Species {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.formula)
+    }
+}
+
+/// A chemical equation, made up of reactants and products
+///
+/// Any errors from parsing an equation have their [`TokenLoc`] relative to the
+/// whole equation, not just the formula the error is in.
+///
+/// # Examples
+///
+/// ```
+/// use stoik::equation::Equation;
+/// use stoik::formula::TokenLoc;
+/// use stoik::StoikError;
+///
+/// let equation: Equation = "CH4 + 2O2 => CO2 + 2H2O".parse()?;
+/// assert_eq!(equation.products[1].formula, "2H2O");
+/// assert_eq!(equation.products[1].loc, TokenLoc::new(20, 4));
+/// assert_eq!(equation.to_string(), "CH4 + 2O2 -> CO2 + 2H2O");
+///
+/// // The error points at the `)` in the whole equation
+/// let err = "H2 + O2) -> H2O".parse::<Equation>().unwrap_err();
+/// assert_eq!(err.get_loc(), Some(&TokenLoc::new(8, 1)));
+///
+/// // Equations can also be written with Unicode arrows, subscripts and superscripts
+/// let equation: Equation = "2H₂ + O₂ → 2H₂O".parse()?;
+/// assert!(equation.is_balanced());
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Equation {
+    /// The left hand side of the equation
+    pub reactants: Vec<Species>,
+    /// The right hand side of the equation
+    pub products: Vec<Species>,
+    /// The arrow between the two sides, which is kept when the equation is balanced
+    pub arrow: Arrow,
+}
+
+impl Equation {
+    /// Parses an equation using the given [`ParseOptions`] for every species
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    /// use stoik::formula::{ParseOptions, TokenLoc};
+    /// use stoik::StoikError;
+    ///
+    /// let options = ParseOptions {
+    ///     strict: true,
+    ///     ..Default::default()
+    /// };
+    /// let err = Equation::parse_with("H2 + Oo2 -> H2O", &options).unwrap_err();
+    /// assert!(matches!(err, StoikError::UnknownElement(loc) if loc == TokenLoc::new(6, 2)));
+    /// ```
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<Self, StoikError> {
+        let (arrow_start, arrow) = ARROWS
+            .iter()
+            .filter_map(|arrow| s.find(arrow).map(|pos| (pos, *arrow)))
+            .min()
+            .ok_or(StoikError::MissingArrow)?;
+
+        let products_start = arrow_start + arrow.len();
+        Ok(Self {
+            reactants: parse_side(s, 0..arrow_start, options)?,
+            products: parse_side(s, products_start..s.len(), options)?,
+            arrow: Arrow::from_symbol(arrow).unwrap_or_default(),
+        })
+    }
+
+    /// Finds every problem in an equation, instead of stopping at the first one like
+    /// [`parse_with`](Self::parse_with) does
+    ///
+    /// Each species is checked with [`Molecule::diagnose`], with the spans moved to be
+    /// relative to the whole equation. There are no diagnostics if the equation can be parsed
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    /// use stoik::formula::{ParseOptions, TokenLoc};
+    ///
+    /// let diagnostics = Equation::diagnose("H2 + + O2) -> 2H2$O", &ParseOptions::default());
+    /// let spans = diagnostics.iter().map(|x| x.span.clone()).collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     spans,
+    ///     [Some(TokenLoc::new(5, 1)), Some(TokenLoc::new(10, 1)), Some(TokenLoc::new(18, 1))]
+    /// );
+    /// assert_eq!(diagnostics[2].suggestion.as_deref(), Some("Remove `$`"));
+    /// ```
+    pub fn diagnose(s: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+        let arrow = ARROWS
+            .iter()
+            .filter_map(|arrow| s.find(arrow).map(|pos| (pos, arrow.len())))
+            .min();
+
+        let Some((arrow_start, arrow_len)) = arrow else {
+            // check the species anyway, as if they were all reactants
+            let mut diagnostics = vec![Diagnostic::from_error(&StoikError::MissingArrow, s)];
+            diagnostics.extend(diagnose_side(s, 0..s.len(), options));
+            return diagnostics;
+        };
+        let products_start = arrow_start + arrow_len;
+        let mut diagnostics = diagnose_side(s, 0..arrow_start, options);
+        diagnostics.extend(diagnose_side(s, products_start..s.len(), options));
+        diagnostics
+    }
+
+    /// Checks every species with [`Molecule::check_valence`], giving the warnings with
+    /// spans that point at the species they are for
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    /// use stoik::formula::TokenLoc;
+    ///
+    /// let equation: Equation = "CH5 + O2 -> CO2 + H2O".parse()?;
+    /// let warnings = equation.valence_warnings();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(warnings[0].span, Some(TokenLoc::new(1, 3)));
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn valence_warnings(&self) -> Vec<Diagnostic> {
+        self.reactants
+            .iter()
+            .chain(&self.products)
+            .filter_map(|species| {
+                let mut warning = species.molecule.check_valence()?;
+                warning.span = Some(species.loc.clone());
+                Some(warning)
+            })
+            .collect()
+    }
+
+    /// Gets the total count of each element in the reactants
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    ///
+    /// let equation: Equation = "2H2 + O2 -> 2H2O".parse()?;
+    /// let map = equation.reactant_map();
+    /// assert_eq!(map["H"], 4);
+    /// assert_eq!(map["O"], 2);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn reactant_map(&self) -> HashMap<String, Rational> {
+        sum_maps(&self.reactants)
+    }
+
+    /// Gets the total count of each element in the products
+    pub fn product_map(&self) -> HashMap<String, Rational> {
+        sum_maps(&self.products)
+    }
+
+    /// Gets whether each element in the equation is balanced
+    ///
+    /// The counts are compared exactly, so decimal subscripts never suffer from rounding
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    ///
+    /// let equation: Equation = "H2 + O2 -> H2O".parse()?;
+    /// let balance = equation.element_balance();
+    /// assert_eq!(Some(&true), balance.get("H"));
+    /// assert_eq!(Some(&false), balance.get("O"));
+    ///
+    /// let equation: Equation = "19Fe + 10O2 -> 20Fe0.95O".parse()?;
+    /// assert!(equation.element_balance().values().all(|x| *x));
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn element_balance(&self) -> HashMap<String, bool> {
+        let lhs = self.reactant_map();
+        let rhs = self.product_map();
+
+        lhs.keys()
+            .chain(rhs.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|key| {
+                (
+                    key.clone(),
+                    lhs.get(key).copied().unwrap_or_default()
+                        == rhs.get(key).copied().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// Gets the total charge of the reactants
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    ///
+    /// let equation: Equation = "Fe3+ + e- -> Fe2+".parse()?;
+    /// assert_eq!(equation.reactant_charge(), 2);
+    /// assert_eq!(equation.product_charge(), 2);
+    /// assert!(equation.is_balanced());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn reactant_charge(&self) -> i128 {
+        self.reactants
+            .iter()
+            .map(|x| x.molecule.total_charge())
+            .sum()
+    }
+
+    /// Gets the total charge of the products
+    pub fn product_charge(&self) -> i128 {
+        self.products
+            .iter()
+            .map(|x| x.molecule.total_charge())
+            .sum()
+    }
+
+    /// Checks if the charge is the same on both sides of the equation
+    pub fn is_charge_balanced(&self) -> bool {
+        self.reactant_charge() == self.product_charge()
+    }
+
+    /// Checks if every element and the charge is balanced in the equation
+    pub fn is_balanced(&self) -> bool {
+        self.element_balance().values().all(|x| *x) && self.is_charge_balanced()
+    }
+
+    /// Creates a balanced copy of this equation, replacing the coefficient of each species
+    ///
+    /// See [`balance`] for how the coefficients are found and the errors this can return
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Equation;
+    ///
+    /// let equation: Equation = "Fe + O2 -> Fe2O3".parse()?;
+    /// let balanced = equation.balance()?;
+    /// assert!(balanced.is_balanced());
+    /// assert_eq!(balanced.to_string(), "4Fe + 3O2 -> 2Fe2O3");
+    ///
+    /// let equation: Equation = "Fe + O2 -> Fe0.95O".parse()?;
+    /// assert_eq!(equation.balance()?.to_string(), "19Fe + 10O2 -> 20Fe0.95O");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn balance(&self) -> Result<Self, StoikError> {
+        self.balance_coefficients()
+            .map_err(|e| self.locate_overflow(e))
+    }
+
+    fn balance_coefficients(&self) -> Result<Self, StoikError> {
+        let reactants = self
+            .reactants
+            .iter()
+            .map(|x| x.molecule.clone())
+            .collect::<Vec<_>>();
+        let products = self
+            .products
+            .iter()
+            .map(|x| x.molecule.clone())
+            .collect::<Vec<_>>();
+        let coefficients = balance(&reactants, &products)?;
+
+        let species = self
+            .reactants
+            .iter()
+            .chain(&self.products)
+            .collect::<Vec<_>>();
+        let terms = coefficients
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| match i < self.reactants.len() {
+                true => i128::from(x),
+                false => -i128::from(x),
+            })
+            .collect::<Vec<_>>();
+        from_terms(&species, &terms, self.arrow)
+    }
+
+    /// Points an overflow at the whole equation, as the coefficients that caused it
+    /// do not come from any one place
+    fn locate_overflow(&self, err: StoikError) -> StoikError {
+        match err {
+            StoikError::Overflow(_) => {
+                let mut locs = self.reactants.iter().chain(&self.products).map(|x| &x.loc);
+                let first = locs.next();
+                let loc = match (first, locs.next_back().or(first)) {
+                    (Some(first), Some(last)) => first.join(last),
+                    _ => TokenLoc::new(1, 0),
+                };
+                StoikError::Overflow(loc)
+            }
+            e => e,
+        }
+    }
+}
+
+impl FromStr for Equation {
+    type Err = StoikError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(s, &ParseOptions::default())
+    }
+}
+
+impl Display for Equation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            join_species(&self.reactants),
+            self.arrow.symbol(),
+            join_species(&self.products)
+        )
+    }
+}
+
+/// Removes the leading coefficient from a formula, e.g. `2 H2O` becomes `H2O`
+fn strip_coefficient(formula: &str) -> &str {
+    formula
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start()
+}
+
+/// Checks if a formula is a species written as SMILES in braces, like `2{CCO}`
+fn is_smiles(formula: &str) -> bool {
+    strip_coefficient(formula).starts_with('{')
+}
+
+/// Parses a species written as SMILES in braces, which can have a coefficient before
+/// the braces and a state symbol after them, like `2{CCO}(l)`
+fn smiles_molecule(formula: &str) -> Result<Molecule, StoikError> {
+    let rest = strip_coefficient(formula);
+    let coefficient_len = formula.len() - rest.len();
+    let coefficient = formula[..coefficient_len].trim_end();
+    // the location of `text`, which starts at byte `start` of the formula
+    let locate = |start: usize, text: &str| {
+        let start = formula[..start].chars().count() + 1;
+        TokenLoc::locate(formula, start, text.chars().count())
+    };
+
+    let Some((smiles, after)) = rest[1..].split_once('}') else {
+        return Err(StoikError::UnpairedBracket(locate(coefficient_len, "{")));
+    };
+    let mut molecule =
+        Molecule::from_smiles(smiles).map_err(|e| e.offset(&formula[..coefficient_len + 1]))?;
+    if !coefficient.is_empty() {
+        molecule.moles = coefficient
+            .parse()
+            .map_err(|_| StoikError::Overflow(locate(0, coefficient)))?;
+    }
+    if !after.is_empty() {
+        let state = after
+            .strip_prefix('(')
+            .and_then(|x| x.strip_suffix(')'))
+            .and_then(State::from_symbol)
+            .ok_or_else(|| StoikError::InvalidToken(locate(formula.len() - after.len(), after)))?;
+        molecule.state = Some(state);
+    }
+    Ok(molecule)
+}
+
+/// Adds a coefficient to the start of a formula, leaving it out if it is 1
+fn with_coefficient(formula: &str, coefficient: i128) -> String {
+    if coefficient == 1 {
+        formula.to_string()
+    } else {
+        format!("{coefficient}{formula}")
+    }
+}
+
+/// Builds an equation from species and signed coefficients, with positive ones being reactants
+/// and negative ones being products. Species with a coefficient of zero are left out
+///
+/// Each species keeps the molecule it was parsed as, with its moles changed, so nothing that was
+/// used to parse it is lost, like the abbreviations. The locations are moved to match the new text
+fn from_terms(species: &[&Species], terms: &[i128], arrow: Arrow) -> Result<Equation, StoikError> {
+    let overflow = || StoikError::Overflow(TokenLoc::default());
+    let mut sides = [Vec::new(), Vec::new()];
+    for (species, term) in species.iter().zip(terms) {
+        if *term == 0 {
+            continue;
+        }
+        let mut molecule = species.molecule.clone();
+        molecule.moles = i64::try_from(term.abs()).map_err(|_| overflow())?;
+        if !molecule.fits_i64() {
+            return Err(overflow());
+        }
+        sides[usize::from(*term < 0)].push(Species {
+            molecule,
+            formula: with_coefficient(strip_coefficient(&species.formula), term.abs()),
+            loc: TokenLoc::default(),
+        });
+    }
+
+    let [mut reactants, mut products] = sides;
+    let lhs = join_species(&reactants);
+    let arrow_text = format!(" {} ", arrow.symbol());
+    let text = format!("{lhs}{arrow_text}{}", join_species(&products));
+    for (side, mut start) in [
+        (&mut reactants, 1),
+        (
+            &mut products,
+            lhs.chars().count() + arrow_text.chars().count() + 1,
+        ),
+    ] {
+        for species in side.iter_mut() {
+            let len = species.formula.chars().count();
+            species.loc = TokenLoc::locate(&text, start, len);
+            species.molecule.relocate(species.loc.clone());
+            start += len + " + ".len();
+        }
+    }
+    Ok(Equation {
+        reactants,
+        products,
+        arrow,
+    })
+}
+
+fn join_species(species: &[Species]) -> String {
+    species
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Parses one side of an equation, with `side` being the bytes of the equation the side covers
+fn parse_side(
+    equation: &str,
+    side: Range<usize>,
+    options: &ParseOptions,
+) -> Result<Vec<Species>, StoikError> {
+    let mut species = Vec::new();
+    let mut start = side.start;
+    for formula in split_species(&equation[side]) {
+        let before = &equation[..start];
+        if formula.trim().is_empty() {
+            return Err(missing_species(equation, before, formula));
+        }
+        let mut new = Species::new_with(formula, options).map_err(|e| e.offset(before))?;
+        new.loc.offset(before);
+        new.molecule.offset_locs(before);
+        species.push(new);
+        // + 1 for the `+`
+        start += formula.len() + 1;
+    }
+    Ok(species)
+}
+
+/// Finds every problem in one side of an equation, like [`parse_side`]
+fn diagnose_side(equation: &str, side: Range<usize>, options: &ParseOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut start = side.start;
+    for formula in split_species(&equation[side]) {
+        let before = &equation[..start];
+        if formula.trim().is_empty() {
+            let err = missing_species(equation, before, formula);
+            diagnostics.push(Diagnostic::from_error(&err, equation));
+        } else {
+            let found = Species::diagnose(formula, options);
+            diagnostics.extend(found.into_iter().map(|x| x.offset(before)));
+        }
+        // + 1 for the `+`
+        start += formula.len() + 1;
+    }
+    diagnostics
+}
+
+/// Makes the error for a species with no formula, which points at the species' whitespace,
+/// or the character after it if there is none, such as the arrow in `-> H2O`
+fn missing_species(equation: &str, before: &str, formula: &str) -> StoikError {
+    StoikError::MissingSpecies(TokenLoc::locate(
+        equation,
+        before.chars().count() + 1,
+        formula.chars().count().max(1),
+    ))
+}
+
+/// Splits one side of an equation on the `+`s between species, leaving the `+`s that are charges
+///
+/// A `+` is a charge if it comes straight after a formula, and is followed by
+/// only a number and state symbol, so `Na+ + Cl-`, `Fe+3 + e-` and
+/// `Fe3+(aq) + e-` are split correctly.
+fn split_species(side: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    let mut prev = None;
+    // the `+`s in SMILES are always charges
+    let mut in_smiles = false;
+    for (i, c) in side.char_indices() {
+        match c {
+            '{' => in_smiles = true,
+            '}' => in_smiles = false,
+            '+' if !in_smiles && is_separator(prev, &side[i + 1..]) => {
+                parts.push(&side[last..i]);
+                last = i + 1;
+            }
+            _ => {}
+        }
+        prev = Some(c);
+    }
+    parts.push(&side[last..]);
+    parts
+}
+
+fn is_separator(prev: Option<char>, rest: &str) -> bool {
+    if prev.is_none_or(|c| c.is_whitespace()) {
+        return true;
+    }
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    match rest.chars().next() {
+        Some('(') => rest[1..]
+            .split_once(')')
+            .is_none_or(|(symbol, _)| State::from_symbol(symbol).is_none()),
+        Some(c) => !(c.is_whitespace() || c == '+' || c == '-'),
+        None => false,
+    }
+}
+
+fn sum_maps(species: &[Species]) -> HashMap<String, Rational> {
+    let mut map = HashMap::new();
+    for (key, count) in species.iter().flat_map(|x| x.molecule.get_map()) {
+        *map.entry(key).or_default() += count;
+    }
+    map
+}
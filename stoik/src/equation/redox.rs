@@ -0,0 +1,255 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    err::StoikError,
+    formula::{Molecule, TokenLoc},
+    rational::gcd,
+};
+
+use super::{balance::null_vector, from_terms, Equation, Species};
+
+/// The medium a redox reaction happens in, which decides how oxygen and hydrogen are balanced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Medium {
+    /// Balanced with `H2O` and `H+`
+    #[default]
+    Acidic,
+    /// Balanced with `H2O` and `OH-`
+    Basic,
+}
+
+impl Medium {
+    /// Gets the formula of the ion used to balance hydrogen in this medium
+    pub fn ion(&self) -> &'static str {
+        match self {
+            Medium::Acidic => "H+",
+            Medium::Basic => "OH-",
+        }
+    }
+}
+
+/// A redox reaction split into its balanced half-reactions
+///
+/// See [`Equation::balance_redox`] for how this is made
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Redox {
+    /// The half-reaction that gives out electrons
+    pub oxidation: Equation,
+    /// The half-reaction that takes in electrons
+    pub reduction: Equation,
+    /// Both half-reactions added together, so that the electrons cancel out
+    pub overall: Equation,
+}
+
+impl Equation {
+    /// Balances a redox reaction with the half-reaction method
+    ///
+    /// The species are split into two halves by the elements other than hydrogen and oxygen
+    /// they share. Each half is balanced with `H2O`, `H+` (or `OH-` in a basic [`Medium`])
+    /// and `e-`, before the halves are scaled so their electrons cancel and added together.
+    /// Any `H2O`, `H+`, `OH-` or `e-` in the equation are ignored, as they are added where needed.
+    ///
+    /// If every species shares an element, it is a disproportionation like `Cl2 -> Cl- + ClO3-`
+    /// or a comproportionation like `Ag + Ag2+ -> Ag+`. One species has to be alone on its side,
+    /// and each half is made from it and one of the two species on the other side
+    ///
+    /// # Errors
+    ///
+    /// - [`StoikError::NotRedox`] if the equation does not split into one oxidation and one reduction
+    /// - [`StoikError::Disproportionation`] if hydrogen or oxygen is both oxidised and reduced
+    /// - [`StoikError::Unbalanceable`] or [`StoikError::AmbiguousBalance`] if a half cannot be balanced
+    /// - [`StoikError::Overflow`] if the coefficients are too big
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stoik::equation::{Equation, Medium};
+    ///
+    /// let equation: Equation = "MnO4- + Fe2+ -> Mn2+ + Fe3+".parse()?;
+    /// let redox = equation.balance_redox(Medium::Acidic)?;
+    /// assert_eq!(redox.oxidation.to_string(), "Fe2+ -> Fe3+ + e-");
+    /// assert_eq!(redox.reduction.to_string(), "MnO4- + 8H+ + 5e- -> Mn2+ + 4H2O");
+    /// assert_eq!(redox.overall.to_string(), "MnO4- + 5Fe2+ + 8H+ -> Mn2+ + 5Fe3+ + 4H2O");
+    /// assert!(redox.overall.is_balanced());
+    ///
+    /// let equation: Equation = "MnO4- + I- -> MnO2 + I2".parse()?;
+    /// let redox = equation.balance_redox(Medium::Basic)?;
+    /// assert_eq!(redox.reduction.to_string(), "MnO4- + 2H2O + 3e- -> MnO2 + 4OH-");
+    /// assert_eq!(redox.overall.to_string(), "2MnO4- + 6I- + 4H2O -> 2MnO2 + 3I2 + 8OH-");
+    ///
+    /// let equation: Equation = "Cl2 -> Cl- + ClO3-".parse()?;
+    /// let redox = equation.balance_redox(Medium::Basic)?;
+    /// assert_eq!(redox.oxidation.to_string(), "Cl2 + 12OH- -> 2ClO3- + 6H2O + 10e-");
+    /// assert_eq!(redox.overall.to_string(), "3Cl2 + 6OH- -> 5Cl- + ClO3- + 3H2O");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn balance_redox(&self, medium: Medium) -> Result<Redox, StoikError> {
+        self.redox_halves(medium)
+            .map_err(|e| self.locate_overflow(e))
+    }
+
+    fn redox_halves(&self, medium: Medium) -> Result<Redox, StoikError> {
+        let overflow = || StoikError::Overflow(TokenLoc::default());
+        let extras = ["H2O", medium.ion(), "e-"];
+        let extra_species = extras
+            .iter()
+            .map(|x| Species::new(x))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let species = self
+            .reactants
+            .iter()
+            .map(|x| (x, 1))
+            .chain(self.products.iter().map(|x| (x, -1)))
+            .filter(|(x, _)| !is_medium_species(&x.molecule))
+            .collect::<Vec<_>>();
+        let mut groups = group_species(&species);
+        let shared = groups.len() == 1;
+        if shared {
+            groups = split_shared(&species, &groups[0])?;
+        }
+        if groups.len() != 2 {
+            return Err(StoikError::NotRedox);
+        }
+        // a half of a shared group that cannot be balanced means it was split wrongly
+        let unbalanceable = |e: StoikError| match e {
+            StoikError::Unbalanceable | StoikError::AmbiguousBalance if shared => {
+                StoikError::NotRedox
+            }
+            e => e,
+        };
+
+        // the coefficient of every species then every extra, positive for reactants
+        let mut halves = Vec::new();
+        for group in &groups {
+            let columns = group
+                .iter()
+                .map(|i| (&species[*i].0.molecule, species[*i].1))
+                .chain(extra_species.iter().map(|x| (&x.molecule, 1)))
+                .collect::<Vec<_>>();
+            let solution = null_vector(&columns).map_err(unbalanceable)?;
+            if solution[..group.len()].iter().any(|x| *x <= 0) {
+                return Err(unbalanceable(StoikError::Unbalanceable));
+            }
+
+            let mut terms = vec![0; species.len() + extras.len()];
+            for (i, coefficient) in group.iter().zip(&solution) {
+                terms[*i] = coefficient * species[*i].1 as i128;
+            }
+            terms[species.len()..].copy_from_slice(&solution[group.len()..]);
+            halves.push(terms);
+        }
+
+        let electrons = halves.iter().map(|x| x[x.len() - 1]).collect::<Vec<_>>();
+        let (oxidation, reduction) = match electrons[..] {
+            [a, b] if a < 0 && b > 0 => (&halves[0], &halves[1]),
+            [a, b] if a > 0 && b < 0 => (&halves[1], &halves[0]),
+            _ => return Err(StoikError::NotRedox),
+        };
+
+        // scale both halves to the same number of electrons
+        let lost = -oxidation[oxidation.len() - 1];
+        let gained = reduction[reduction.len() - 1];
+        let common = gcd(lost, gained);
+        let mut overall = oxidation
+            .iter()
+            .zip(reduction)
+            .map(|(ox, red)| {
+                ox.checked_mul(gained / common)?
+                    .checked_add(red.checked_mul(lost / common)?)
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(overflow)?;
+        let overall_gcd = overall.iter().fold(0, |acc, x| gcd(acc, *x));
+        overall.iter_mut().for_each(|x| *x /= overall_gcd);
+        if overall.iter().any(|x| i64::try_from(*x).is_err()) {
+            return Err(overflow());
+        }
+
+        let species = species
+            .iter()
+            .map(|(x, _)| *x)
+            .chain(&extra_species)
+            .collect::<Vec<_>>();
+        Ok(Redox {
+            oxidation: from_terms(&species, oxidation, self.arrow)?,
+            reduction: from_terms(&species, reduction, self.arrow)?,
+            overall: from_terms(&species, &overall, self.arrow)?,
+        })
+    }
+}
+
+/// Checks if a molecule is one that is added to balance a half-reaction
+fn is_medium_species(mol: &Molecule) -> bool {
+    let mut mol = mol.clone();
+    mol.moles = 1;
+    mol.state = None;
+    ["H2O", "H+", "OH-", "e-"]
+        .iter()
+        .any(|x| Molecule::from_formula(x).is_ok_and(|x| x == mol))
+}
+
+/// Splits a group of species that all share an element into the halves of a disproportionation
+/// or comproportionation, where one species alone on its side is in both halves
+fn split_shared(
+    species: &[(&Species, i64)],
+    group: &[usize],
+) -> Result<Vec<Vec<usize>>, StoikError> {
+    let heavy = group.iter().any(|i| {
+        let map = species[*i].0.molecule.get_map();
+        map.keys().any(|x| x != "H" && x != "O")
+    });
+    let (reactants, products): (Vec<usize>, Vec<usize>) =
+        group.iter().partition(|i| species[**i].1 > 0);
+    match (&reactants[..], &products[..]) {
+        // like `H2O2 -> H2O + O2`, where the water is ignored
+        ([_], [_] | [_, _]) | ([_, _], [_]) if !heavy => Err(StoikError::Disproportionation),
+        ([shared], [a, b]) | ([a, b], [shared]) => Ok(vec![vec![*shared, *a], vec![*shared, *b]]),
+        _ => Err(StoikError::NotRedox),
+    }
+}
+
+/// Groups the species that share an element other than hydrogen and oxygen, giving the
+/// indices of the species in each group
+fn group_species(species: &[(&Species, i64)]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(BTreeSet<String>, Vec<usize>)> = Vec::new();
+    for (i, (x, _)) in species.iter().enumerate() {
+        let map = x.molecule.get_map();
+        let heavy = map
+            .keys()
+            .filter(|x| *x != "H" && *x != "O")
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let mut merged = (
+            if heavy.is_empty() {
+                map.into_keys().collect()
+            } else {
+                heavy
+            },
+            vec![i],
+        );
+
+        // merge every group that shares an element with this species
+        let mut rest = Vec::new();
+        for group in groups.drain(..) {
+            if group.0.is_disjoint(&merged.0) {
+                rest.push(group);
+            } else {
+                merged.0.extend(group.0);
+                merged.1.extend(group.1);
+            }
+        }
+        rest.push(merged);
+        groups = rest;
+    }
+
+    groups
+        .into_iter()
+        .map(|(_, mut x)| {
+            x.sort();
+            x
+        })
+        .collect()
+}
@@ -0,0 +1,281 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{err::StoikError, formula::Molecule};
+
+use super::{Equation, Species};
+
+/// An amount of a species, either as a mass or as a number of moles
+///
+/// # Examples
+///
+/// ```
+/// use stoik::equation::Amount;
+///
+/// assert_eq!("2.5g".parse::<Amount>()?, Amount::Grams(2.5));
+/// assert_eq!("250 mg".parse::<Amount>()?, Amount::Grams(0.25));
+/// assert_eq!("0.1 mol".parse::<Amount>()?, Amount::Moles(0.1));
+/// // a plain number is taken as moles
+/// assert_eq!("3".parse::<Amount>()?, Amount::Moles(3.0));
+/// assert!("3 apples".parse::<Amount>().is_err());
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Amount {
+    /// A mass in grams
+    Grams(f64),
+    /// A number of moles
+    Moles(f64),
+}
+
+impl Amount {
+    /// Converts the amount to moles of one formula unit of `molecule`, ignoring its `moles`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoikError::NoAtomicWeight`] if a mass is given for a molecule without a molar mass,
+    /// and [`StoikError::InvalidInput`] if it is given for a molecule with no mass
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Amount;
+    /// use stoik::formula::Molecule;
+    ///
+    /// let water = Molecule::from_formula("H2O")?;
+    /// assert!((Amount::Grams(18.015).to_moles(&water)? - 1.0).abs() < 0.001);
+    ///
+    /// let empty = Molecule::from_formula("0H2O")?;
+    /// assert!(Amount::Grams(1.0).to_moles(&empty).is_ok());
+    /// assert!(Amount::Grams(1.0).to_moles(&Molecule::default()).is_err());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn to_moles(self, molecule: &Molecule) -> Result<f64, StoikError> {
+        match self {
+            Amount::Moles(moles) => Ok(moles),
+            Amount::Grams(grams) => match unit_mass(molecule)? {
+                mass if mass > 0.0 => Ok(grams / mass),
+                _ => Err(StoikError::InvalidInput(format!(
+                    "`{molecule}` has no mass, so its amount has to be given in moles"
+                ))),
+            },
+        }
+    }
+}
+
+impl FromStr for Amount {
+    type Err = StoikError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| c.is_alphabetic() || c.is_whitespace())
+            .unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+        let invalid =
+            || StoikError::InvalidInput(format!("`{s}` is not an amount, e.g. `2.5g` or `0.1mol`"));
+
+        let value = value.parse::<f64>().map_err(|_| invalid())?;
+        if !value.is_finite() || value < 0.0 {
+            return Err(invalid());
+        }
+        Ok(match unit.trim() {
+            "" | "mol" => Amount::Moles(value),
+            "mmol" => Amount::Moles(value / 1000.0),
+            "g" => Amount::Grams(value),
+            "mg" => Amount::Grams(value / 1000.0),
+            "kg" => Amount::Grams(value * 1000.0),
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+impl Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Amount::Grams(grams) => write!(f, "{grams} g"),
+            Amount::Moles(moles) => write!(f, "{moles} mol"),
+        }
+    }
+}
+
+/// An amount of a species given in both moles and grams
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quantity {
+    /// The number of moles
+    pub moles: f64,
+    /// The mass in grams
+    pub grams: f64,
+}
+
+impl Quantity {
+    fn new(moles: f64, molar_mass: f64) -> Self {
+        Self {
+            moles,
+            grams: moles * molar_mass,
+        }
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.4} mol ({:.4} g)", self.moles, self.grams)
+    }
+}
+
+/// How much of a reactant there is, and how much of it is used up
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReactantYield {
+    /// How much of the reactant there is at the start
+    pub available: Quantity,
+    /// How much of the reactant is used by the reaction
+    pub used: Quantity,
+    /// How much of the reactant is left over once the reaction is done
+    pub left: Quantity,
+}
+
+/// How much of a product can be made
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProductYield {
+    /// The molar mass of one formula unit of the product in g/mol
+    pub molar_mass: f64,
+    /// The most of the product that can be made
+    pub theoretical: Quantity,
+}
+
+impl ProductYield {
+    /// Gets the percent yield of the product from how much was actually made, or [`None`]
+    /// if none of it can be made, or a mass is given for a product with no mass
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::{Amount, Equation};
+    ///
+    /// let equation: Equation = "2H2 + O2 -> 2H2O".parse()?;
+    /// let yields = equation.reaction_yield(&[Amount::Moles(2.0), Amount::Moles(2.0)])?;
+    /// assert_eq!(yields.products[0].percent_yield(Amount::Moles(1.5)), Some(75.0));
+    ///
+    /// let yields = equation.reaction_yield(&[Amount::Moles(0.0), Amount::Moles(2.0)])?;
+    /// assert_eq!(yields.products[0].percent_yield(Amount::Moles(1.5)), None);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn percent_yield(&self, actual: Amount) -> Option<f64> {
+        let moles = match actual {
+            Amount::Moles(moles) => moles,
+            Amount::Grams(_) if self.molar_mass <= 0.0 => return None,
+            Amount::Grams(grams) => grams / self.molar_mass,
+        };
+        (self.theoretical.moles > 0.0).then(|| moles / self.theoretical.moles * 100.0)
+    }
+}
+
+/// The result of working out the limiting reagent of a reaction
+///
+/// See [`Equation::reaction_yield`] for how this is made
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Yield {
+    /// The index of the reactant that runs out first
+    pub limiting: usize,
+    /// The amounts of each reactant, in the same order as the equation
+    pub reactants: Vec<ReactantYield>,
+    /// The theoretical yield of each product, in the same order as the equation
+    pub products: Vec<ProductYield>,
+}
+
+impl Equation {
+    /// Finds the limiting reagent and the theoretical yields of a balanced equation,
+    /// from the starting amount of each reactant
+    ///
+    /// # Errors
+    ///
+    /// - [`StoikError::NotBalanced`] if the equation is not balanced
+    /// - [`StoikError::InvalidInput`] if there is not one amount for each reactant,
+    ///   or a mass is given for a reactant with no mass
+    /// - [`StoikError::NoAtomicWeight`] if a species has no molar mass
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stoik::equation::{Amount, Equation};
+    ///
+    /// let equation: Equation = "2H2 + O2 -> 2H2O".parse()?;
+    /// let yields = equation.reaction_yield(&[Amount::Grams(4.0), Amount::Grams(64.0)])?;
+    /// // the hydrogen runs out first
+    /// assert_eq!(yields.limiting, 0);
+    /// assert!((yields.reactants[1].left.moles - 1.008).abs() < 0.001);
+    /// assert!((yields.products[0].theoretical.grams - 35.74).abs() < 0.01);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn reaction_yield(&self, amounts: &[Amount]) -> Result<Yield, StoikError> {
+        if !self.is_balanced() {
+            return Err(StoikError::NotBalanced);
+        }
+        if amounts.len() != self.reactants.len() {
+            return Err(StoikError::InvalidInput(format!(
+                "Expected an amount for each of the {} reactants, but got {}",
+                self.reactants.len(),
+                amounts.len()
+            )));
+        }
+
+        let reactant_masses = molar_masses(&self.reactants)?;
+        let product_masses = molar_masses(&self.products)?;
+        let available = amounts
+            .iter()
+            .zip(&self.reactants)
+            .map(|(amount, species)| amount.to_moles(&species.molecule))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // how many times the reaction as written can happen before each reactant runs out
+        let (limiting, extent) = available
+            .iter()
+            .zip(&self.reactants)
+            .map(|(moles, species)| moles / species.molecule.moles as f64)
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap_or_default();
+
+        let reactants = available
+            .iter()
+            .zip(&self.reactants)
+            .zip(reactant_masses)
+            .map(|((moles, species), molar_mass)| {
+                let used = extent * species.molecule.moles as f64;
+                ReactantYield {
+                    available: Quantity::new(*moles, molar_mass),
+                    used: Quantity::new(used, molar_mass),
+                    left: Quantity::new(moles - used, molar_mass),
+                }
+            })
+            .collect();
+        let products = self
+            .products
+            .iter()
+            .zip(product_masses)
+            .map(|(species, molar_mass)| ProductYield {
+                molar_mass,
+                theoretical: Quantity::new(extent * species.molecule.moles as f64, molar_mass),
+            })
+            .collect();
+
+        Ok(Yield {
+            limiting,
+            reactants,
+            products,
+        })
+    }
+}
+
+/// Gets the molar mass of one formula unit of a molecule, ignoring its `moles`
+fn unit_mass(molecule: &Molecule) -> Result<f64, StoikError> {
+    let mut molecule = molecule.clone();
+    molecule.moles = 1;
+    Ok(molecule.molar_mass()?.value)
+}
+
+fn molar_masses(species: &[Species]) -> Result<Vec<f64>, StoikError> {
+    species.iter().map(|x| unit_mass(&x.molecule)).collect()
+}
@@ -21,7 +21,32 @@ pub enum StoikError {
     /// Empty molecule
     EmptyMolecule,
     /// Invalid syntax node and the half build molecule
-    InvalidNode(SyntaxNode, Molecule),
+    InvalidNode(SyntaxNode, Box<Molecule>),
+    /// An equation has no arrow (`->` or `=>`) seperating the reactants from the products
+    MissingArrow,
+    /// A species was expected in an equation but nothing was found, e.g. between the `+`s in `A + + B`
+    MissingSpecies(TokenLoc),
+    /// An atom that is not in the periodic table, only given in strict mode
+    UnknownElement(TokenLoc),
+    /// An atom has no known atomic weight, as it is not in the periodic table
+    NoAtomicWeight(String),
+    /// An atom has no standard valence, like a transition metal or an atom not in the periodic table
+    NoValence(String),
+    /// There is no way to balance an equation with positive coefficients
+    Unbalanceable,
+    /// There is more than one independent way to balance an equation
+    AmbiguousBalance,
+    /// An equation cannot be split into an oxidation and a reduction half-reaction
+    NotRedox,
+    /// Hydrogen or oxygen is both oxidised and reduced, like in `H2O2 -> H2O + O2`, which cannot
+    /// be split into half-reactions as they are balanced with `H2O`
+    Disproportionation,
+    /// An equation needs to be balanced before it can be used
+    NotBalanced,
+    /// A number, or a count worked out from the numbers, is too big to store
+    Overflow(TokenLoc),
+    /// A ring bond in a SMILES string is opened but never closed, like the `1` in `C1CC`
+    UnclosedRing(TokenLoc),
 }
 
 impl Display for StoikError {
@@ -34,10 +59,55 @@ impl Display for StoikError {
             StoikError::UnpairedParenthesis(_) => write!(f, "Unpaired parenthesis"),
             StoikError::EmptyMolecule => write!(f, "Cannot have an empty molicule"),
             StoikError::InvalidNode(node, s) => write!(f, "Invalid syntax node {node:?}. Molicule: {s}"),
+            StoikError::MissingArrow => write!(f, "Products are not given, please use `=>` or `->` to seperate the two sides"),
+            StoikError::MissingSpecies(_) => write!(f, "Missing species"),
+            StoikError::UnknownElement(_) => write!(f, "Unknown element"),
+            StoikError::NoAtomicWeight(atom) => write!(f, "`{atom}` has no known atomic weight"),
+            StoikError::NoValence(atom) => write!(f, "`{atom}` has no standard valence"),
+            StoikError::Unbalanceable => write!(f, "The equation cannot be balanced"),
+            StoikError::AmbiguousBalance => write!(f, "The equation can be balanced in more than one independent way"),
+            StoikError::NotRedox => write!(f, "The equation cannot be split into an oxidation and a reduction half-reaction"),
+            StoikError::Disproportionation => write!(f, "Hydrogen or oxygen is both oxidised and reduced, so the equation cannot be split into half-reactions balanced with water"),
+            StoikError::NotBalanced => write!(f, "The equation needs to be balanced first"),
+            StoikError::Overflow(_) => write!(f, "A number is too big"),
+            StoikError::UnclosedRing(_) => write!(f, "Unclosed ring"),
         }
     }
 }
 
+impl StoikError {
+    /// Gets the location in the input that caused the error, if there is one
+    pub fn get_loc(&self) -> Option<&TokenLoc> {
+        match self {
+            StoikError::InvalidToken(loc)
+            | StoikError::NumberFirst(loc)
+            | StoikError::UnpairedParenthesis(loc)
+            | StoikError::UnpairedBracket(loc)
+            | StoikError::MissingSpecies(loc)
+            | StoikError::UnknownElement(loc)
+            | StoikError::Overflow(loc)
+            | StoikError::UnclosedRing(loc) => Some(loc),
+            _ => None,
+        }
+    }
+
+    /// Moves the location of the error right past `before`
+    pub(crate) fn offset(mut self, before: &str) -> Self {
+        match &mut self {
+            StoikError::InvalidToken(loc)
+            | StoikError::NumberFirst(loc)
+            | StoikError::UnpairedParenthesis(loc)
+            | StoikError::UnpairedBracket(loc)
+            | StoikError::MissingSpecies(loc)
+            | StoikError::UnknownElement(loc)
+            | StoikError::Overflow(loc)
+            | StoikError::UnclosedRing(loc) => loc.offset(before),
+            _ => {}
+        }
+        self
+    }
+}
+
 #[allow(clippy::match_single_binding)]
 impl Error for StoikError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
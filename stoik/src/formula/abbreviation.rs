@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+use crate::err::StoikError;
+
+use super::{Molecule, SyntaxNode};
+
+/// A table of abbreviations for groups of atoms, like `Me` for `CH3` or `Ph` for `C6H5`,
+/// which are expanded into their atoms when a formula is parsed with them in its
+/// [`ParseOptions`](super::ParseOptions)
+///
+/// The [`default`](Self::default) table has the common abbreviations in [`BUILTIN`](Self::BUILTIN),
+/// and more can be added with [`insert`](Self::insert). While abbreviations are used, `Ac` is
+/// read as acetyl instead of actinium
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{Abbreviations, FormulaOrder, Molecule, ParseOptions};
+///
+/// let mut abbreviations = Abbreviations::default();
+/// abbreviations.insert("Ts", "SO2C6H4CH3")?;
+/// let mut options = ParseOptions {
+///     abbreviations: Some(abbreviations),
+///     ..Default::default()
+/// };
+///
+/// let methanol = Molecule::from_formula_with("MeOH", &options)?;
+/// assert_eq!(methanol, Molecule::from_formula("CH3OH")?);
+/// let triflate = Molecule::from_formula_with("MeOTf", &options)?;
+/// assert_eq!(triflate.to_string_with(FormulaOrder::Hill), "C2H3F3O3S");
+/// let tosylate = Molecule::from_formula_with("EtOTs", &options)?;
+/// assert_eq!(tosylate.get_count("C"), 9);
+///
+/// // abbreviations can be kept when the molecule is written out
+/// if let Some(abbreviations) = &mut options.abbreviations {
+///     abbreviations.keep = true;
+/// }
+/// let acetate = Molecule::from_formula_with("AcOEt", &options)?;
+/// assert_eq!(acetate.to_string_with(FormulaOrder::Written), "AcEtO");
+/// assert_eq!(acetate, Molecule::from_formula("CH3COOCH2CH3")?);
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Abbreviations {
+    map: BTreeMap<String, String>,
+    /// Keep abbreviations when a molecule is written out with
+    /// [`to_string_with`](Molecule::to_string_with), instead of the atoms they stand for
+    pub keep: bool,
+}
+
+impl Abbreviations {
+    /// The abbreviations in the default table, and the formulas they stand for
+    pub const BUILTIN: [(&'static str, &'static str); 7] = [
+        ("Me", "CH3"),
+        ("Et", "CH2CH3"),
+        ("Ph", "C6H5"),
+        ("Ac", "COCH3"),
+        ("Bn", "CH2C6H5"),
+        ("OTf", "OSO2CF3"),
+        ("Boc", "CO2C(CH3)3"),
+    ];
+
+    /// Makes a table with no abbreviations in it
+    pub fn empty() -> Self {
+        Self {
+            map: BTreeMap::new(),
+            keep: false,
+        }
+    }
+
+    /// Adds an abbreviation to the table, giving the formula it used to stand for if it was
+    /// already in the table. The name is written like atoms are, so `OTf` is read as `O` then `Tf`
+    /// and is only an abbreviation when they are written together
+    ///
+    /// # Errors
+    ///
+    /// [`StoikError::InvalidInput`] if the name does not start with a capital letter or is not
+    /// all letters, and the errors for [`Molecule::from_formula`] if the formula is not a formula.
+    /// The formula cannot use other abbreviations
+    pub fn insert(&mut self, name: &str, formula: &str) -> Result<Option<String>, StoikError> {
+        if !name.starts_with(|c: char| c.is_uppercase()) || !name.chars().all(char::is_alphabetic) {
+            return Err(StoikError::InvalidInput(format!(
+                "`{name}` cannot be an abbreviation, it has to be letters starting with a capital"
+            )));
+        }
+        Molecule::from_formula(formula)?;
+        Ok(self
+            .map
+            .insert(name.to_string(), formula.trim().to_string()))
+    }
+
+    /// Removes an abbreviation from the table, giving the formula it stood for
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.map.remove(name)
+    }
+
+    /// Gets the formula an abbreviation stands for
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.map.get(name).map(String::as_str)
+    }
+
+    /// Gets every abbreviation and the formula it stands for, sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.map.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Gets how many of the atom `symbols` at the start make up the longest abbreviation
+    pub(crate) fn longest_match(&self, symbols: &[&str]) -> Option<usize> {
+        (1..=symbols.len())
+            .rev()
+            .find(|len| self.map.contains_key(&symbols[..*len].concat()))
+    }
+
+    /// Joins runs of atoms that make up an abbreviation, like the `O` and `Tf` of `OTf`,
+    /// into one atom
+    pub(crate) fn join_atoms(&self, nodes: Vec<SyntaxNode>) -> Vec<SyntaxNode> {
+        let mut joined = Vec::new();
+        let mut nodes = nodes.into_iter().peekable();
+        while let Some(node) = nodes.next() {
+            let SyntaxNode::Atom(first) = node else {
+                joined.push(node);
+                continue;
+            };
+            let mut run = vec![first];
+            while let Some(SyntaxNode::Atom(atom)) =
+                nodes.next_if(|x| matches!(x, SyntaxNode::Atom(_)))
+            {
+                run.push(atom);
+            }
+
+            let mut rest = &run[..];
+            while !rest.is_empty() {
+                let symbols = rest.iter().map(String::as_str).collect::<Vec<_>>();
+                let len = self.longest_match(&symbols).unwrap_or(1);
+                joined.push(SyntaxNode::Atom(rest[..len].concat()));
+                rest = &rest[len..];
+            }
+        }
+        joined
+    }
+}
+
+impl Default for Abbreviations {
+    /// The table of [`BUILTIN`](Self::BUILTIN) abbreviations
+    fn default() -> Self {
+        Self {
+            map: Self::BUILTIN
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            keep: false,
+        }
+    }
+}
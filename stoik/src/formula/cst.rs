@@ -0,0 +1,812 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+};
+
+use crate::{err::StoikError, rational::Rational};
+
+use super::{tokenstream::Locator, Isotope, State, SyntaxNode, Token, TokenLoc, TokenStream};
+
+/// The style of bracket a group is written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Bracket {
+    /// A round bracket, like `(SO4)`
+    Paren,
+    /// A square bracket, like `[Fe(CN)6]`
+    Square,
+}
+
+/// What a [`CstNode`] is
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CstKind {
+    /// A whole formula, the root of the tree
+    Formula,
+    /// The number of moles at the start of a formula or adduct part, like the `2` in `2H2O`
+    Moles(i64),
+    /// An adduct, with each part seperated by an adduct token, like `CuSO4·5H2O`
+    Adduct,
+    /// One part of an adduct, like the `5H2O` in `CuSO4·5H2O`
+    Part,
+    /// A run of atoms and groups, like the `H2O` in `2H2O`
+    Sequence,
+    /// A group in brackets, holding its brackets and a sequence
+    Group(Bracket),
+    /// A node followed by the number it is multiplied by, like `O2` or `(SO4)3`
+    Multiplier(Rational),
+    /// An atom, like the `Rh` in `Rh2(SO4)3`
+    Atom(String),
+    /// An atom with a mass number, like `[13C]` or `^13C`
+    Isotope {
+        /// The symbol of the element
+        symbol: String,
+        /// The mass number of the isotope
+        mass_number: u16,
+    },
+    /// The `e` of an electron
+    Electron,
+    /// The charge at the end of a formula, like the `^2-` in `SO4^2-`
+    Charge(i64),
+    /// The state symbol at the end of a formula, like the `(aq)` in `Fe3+(aq)`
+    State(State),
+    /// Tokens that were skipped over when recovering from an error,
+    /// see [`assemble_cst_recovering`]
+    Error,
+}
+
+/// A child of a [`CstNode`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CstElement {
+    /// A node with its own children
+    Node(CstNode),
+    /// A token along with the exact text it was written as
+    Token(Token, String),
+    /// Whitespace between tokens, and where it is
+    Whitespace(String, TokenLoc),
+}
+
+/// A node in a concrete syntax tree, which keeps every token of a formula along with the
+/// whitespace between them, so the exact text can be printed back out.
+///
+/// The [`SyntaxNode`] tree that the rest of the crate uses is derived from this,
+/// see [`assemble_cst`] for how to make one
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CstNode {
+    kind: CstKind,
+    span: TokenLoc,
+    children: Vec<CstElement>,
+}
+
+/// Parses a formula into a lossless [`CstNode`], where every node knows where it is in the text
+///
+/// # Errors
+///
+/// The same as [`assemble_tree`](super::assemble_tree)
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{assemble_cst, Bracket, CstKind, SyntaxNode, TokenLoc};
+///
+/// let formula = "2 [Cu(NH3)4] SO4 ^2- (aq)";
+/// let cst = assemble_cst(formula)?;
+/// // printing the tree gives back the exact text
+/// assert_eq!(cst.to_string(), formula);
+///
+/// // the innermost node at the 6th character is the `(NH3)4` group
+/// let group = cst.node_at(6).unwrap();
+/// assert_eq!(group.kind(), &CstKind::Group(Bracket::Paren));
+/// assert_eq!(group.span(), &TokenLoc::new(6, 5));
+/// assert_eq!(group.to_string(), "(NH3)");
+///
+/// // the syntax tree made from it loses the whitespace and square brackets
+/// assert_eq!(SyntaxNode::from(&cst).to_string(), "2(Cu(NH3)4)SO4^2-(aq)");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+pub fn assemble_cst(formula: &str) -> Result<CstNode, StoikError> {
+    let mut root = build(TokenStream::new(formula).collect())?;
+    let chars = formula.chars().collect::<Vec<_>>();
+    root.span = TokenLoc::locate(formula, 1, chars.len());
+    root.fill(&chars, &mut Locator::new(formula));
+    Ok(root)
+}
+
+/// Parses a formula into a [`CstNode`] like [`assemble_cst`], but carries on after
+/// [`InvalidToken`](StoikError::InvalidToken), [`NumberFirst`](StoikError::NumberFirst),
+/// unpaired bracket and [`Overflow`](StoikError::Overflow) errors, returning every one found
+///
+/// The tokens that caused an error are kept in [`CstKind::Error`] nodes, and an unclosed
+/// group is closed at the end of the formula, so the tree still prints back to the formula
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{assemble_cst_recovering, TokenLoc};
+/// use stoik::StoikError;
+///
+/// let (cst, errors) = assemble_cst_recovering("H2$O(SO4");
+/// assert_eq!(cst.to_string(), "H2$O(SO4");
+/// assert!(matches!(&errors[0], StoikError::InvalidToken(loc) if *loc == TokenLoc::new(3, 1)));
+/// assert!(matches!(&errors[1], StoikError::UnpairedParenthesis(loc) if *loc == TokenLoc::new(5, 1)));
+/// ```
+pub fn assemble_cst_recovering(formula: &str) -> (CstNode, Vec<StoikError>) {
+    let (mut root, errors) = build_recovering(TokenStream::new(formula).collect());
+    let chars = formula.chars().collect::<Vec<_>>();
+    root.span = TokenLoc::locate(formula, 1, chars.len());
+    root.fill(&chars, &mut Locator::new(formula));
+    (root, errors)
+}
+
+impl CstNode {
+    fn new(kind: CstKind, children: Vec<CstElement>) -> Self {
+        let locs = children.iter().filter_map(|x| match x {
+            CstElement::Node(node) if node.span.is_empty() => None,
+            CstElement::Node(node) => Some(&node.span),
+            CstElement::Token(token, _) => Some(token.get_loc()),
+            CstElement::Whitespace(_, loc) => Some(loc),
+        });
+        let span = locs
+            .cloned()
+            .reduce(|span, loc| span.join(&loc))
+            .unwrap_or_default();
+        Self {
+            kind,
+            span,
+            children,
+        }
+    }
+
+    fn leaf(kind: CstKind, token: Token) -> Self {
+        Self::new(kind, vec![token_element(token)])
+    }
+
+    /// Gets what the node is
+    pub fn kind(&self) -> &CstKind {
+        &self.kind
+    }
+
+    /// Gets the location of the node in the formula, which is empty if the node has no tokens
+    pub fn span(&self) -> &TokenLoc {
+        &self.span
+    }
+
+    /// Gets the tokens, whitespace and nodes in the node, in the order they are written
+    pub fn children(&self) -> &[CstElement] {
+        &self.children
+    }
+
+    /// Gets the innermost node that covers the character at `pos`, counting from 1
+    pub fn node_at(&self, pos: usize) -> Option<&CstNode> {
+        let covers = |span: &TokenLoc| span.start() <= pos && pos < span.start() + span.len();
+        if !covers(&self.span) {
+            return None;
+        }
+        self.children
+            .iter()
+            .find_map(|x| match x {
+                CstElement::Node(node) => node.node_at(pos),
+                _ => None,
+            })
+            .or(Some(self))
+    }
+
+    /// Copies the text of each token from the formula, and adds the whitespace between them
+    fn fill(&mut self, chars: &[char], locator: &mut Locator) {
+        let text = |start: usize, end: usize| {
+            chars
+                .get(start.saturating_sub(1)..end.saturating_sub(1))
+                .unwrap_or_default()
+                .iter()
+                .collect::<String>()
+        };
+
+        let mut children = Vec::new();
+        let mut pos = self.span.start();
+        for mut child in std::mem::take(&mut self.children) {
+            let span = match &mut child {
+                CstElement::Node(node) => {
+                    node.fill(chars, locator);
+                    node.span.clone()
+                }
+                CstElement::Token(token, token_text) => {
+                    let loc = token.get_loc();
+                    *token_text = text(loc.start(), loc.start() + loc.len());
+                    loc.clone()
+                }
+                CstElement::Whitespace(_, loc) => loc.clone(),
+            };
+            if !span.is_empty() {
+                if span.start() > pos {
+                    let loc = locator.locate(pos, span.start() - pos);
+                    children.push(CstElement::Whitespace(text(pos, span.start()), loc));
+                }
+                pos = span.start() + span.len();
+            }
+            children.push(child);
+        }
+        let end = self.span.start() + self.span.len();
+        if end > pos {
+            let loc = locator.locate(pos, end - pos);
+            children.push(CstElement::Whitespace(text(pos, end), loc));
+        }
+        self.children = children;
+    }
+
+    /// Finds where a formula's counts overflow when a molecule is made from it, which is
+    /// the number a count grows too big at, the atom that makes a total too big, or the charge
+    /// if it is too big once multiplied by the moles. Abbreviations are not expanded, so
+    /// this gives [`None`] if only their atoms overflow
+    pub(crate) fn overflow_loc(&self) -> Option<TokenLoc> {
+        let moles = self
+            .nodes()
+            .find_map(|x| match x.kind {
+                CstKind::Moles(n) => Some(n),
+                _ => None,
+            })
+            .unwrap_or(1);
+        let mut totals = HashMap::new();
+        self.nodes().find_map(|child| match child.kind {
+            CstKind::Charge(charge) => charge
+                .checked_mul(moles)
+                .is_none()
+                .then(|| child.span.clone()),
+            CstKind::Moles(_) | CstKind::State(_) => None,
+            _ => child.find_overflow(moles.into(), &mut totals),
+        })
+    }
+
+    /// Adds up the atoms in the node like [`Molecule`](super::Molecule) does,
+    /// giving where the first count that does not fit in an [`i64`] comes from
+    fn find_overflow(
+        &self,
+        mul: Rational,
+        totals: &mut HashMap<String, Rational>,
+    ) -> Option<TokenLoc> {
+        let scale = |by: Rational, loc: &TokenLoc| match mul.checked_mul(by) {
+            Some(mul) if mul.fits_i64() => Ok(mul),
+            _ => Err(loc.clone()),
+        };
+        let symbol = match &self.kind {
+            CstKind::Multiplier(by) => {
+                let number = self.children.iter().rev().find_map(|x| match x {
+                    CstElement::Token(token, _) => Some(token.get_loc()),
+                    _ => None,
+                });
+                return match scale(*by, number.unwrap_or(&self.span)) {
+                    Ok(mul) => self.nodes().find_map(|x| x.find_overflow(mul, totals)),
+                    Err(loc) => Some(loc),
+                };
+            }
+            CstKind::Part => {
+                let mut mul = mul;
+                for child in self.nodes() {
+                    match child.kind {
+                        CstKind::Moles(n) => match scale(n.into(), &child.span) {
+                            Ok(new) => mul = new,
+                            Err(loc) => return Some(loc),
+                        },
+                        _ => return child.find_overflow(mul, totals),
+                    }
+                }
+                return None;
+            }
+            CstKind::Atom(atom) => Isotope::from_symbol(atom).symbol,
+            CstKind::Isotope { symbol, .. } => symbol.clone(),
+            _ => return self.nodes().find_map(|x| x.find_overflow(mul, totals)),
+        };
+
+        let total = totals.entry(symbol).or_insert(Rational::ZERO);
+        match total.checked_add(mul) {
+            Some(new) if new.fits_i64() => {
+                *total = new;
+                None
+            }
+            _ => Some(self.span.clone()),
+        }
+    }
+
+    /// Gets the nodes in the node, skipping tokens and whitespace
+    pub(crate) fn nodes(&self) -> impl Iterator<Item = &CstNode> {
+        self.children.iter().filter_map(|x| match x {
+            CstElement::Node(node) => Some(node),
+            _ => None,
+        })
+    }
+}
+
+impl Display for CstNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => write!(f, "{node}")?,
+                CstElement::Token(_, text) | CstElement::Whitespace(text, _) => {
+                    write!(f, "{text}")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&CstNode> for SyntaxNode {
+    fn from(node: &CstNode) -> Self {
+        let mut nodes = node.nodes();
+        match &node.kind {
+            CstKind::Formula => {
+                let mut root = SyntaxNode::Empty;
+                let (mut moles, mut charge, mut state) = (None, None, None);
+                for child in node.nodes() {
+                    match child.kind {
+                        CstKind::Moles(mul) => moles = Some(mul),
+                        CstKind::Charge(value) => charge = Some(value),
+                        CstKind::State(value) => state = Some(value),
+                        _ => root = child.into(),
+                    }
+                }
+
+                if let Some(charge) = charge {
+                    root = SyntaxNode::Ion {
+                        node: Box::new(root),
+                        charge,
+                    };
+                }
+                if let Some(state) = state {
+                    root = SyntaxNode::State {
+                        node: Box::new(root),
+                        state,
+                    };
+                }
+                if let Some(mul) = moles {
+                    root = SyntaxNode::Mole {
+                        node: Box::new(root),
+                        mul,
+                    };
+                }
+                root
+            }
+            CstKind::Adduct => SyntaxNode::Adduct(nodes.map(SyntaxNode::from).collect()),
+            CstKind::Part => {
+                let mut moles = None;
+                let mut root = SyntaxNode::Empty;
+                for child in nodes {
+                    match child.kind {
+                        CstKind::Moles(mul) => moles = Some(mul),
+                        _ => root = child.into(),
+                    }
+                }
+                match moles {
+                    Some(mul) => SyntaxNode::Mole {
+                        node: Box::new(root),
+                        mul,
+                    },
+                    None => root,
+                }
+            }
+            CstKind::Sequence => {
+                let mut items = nodes
+                    .filter(|x| x.kind != CstKind::Error)
+                    .map(SyntaxNode::from)
+                    .collect::<Vec<_>>();
+                match items.len() {
+                    0 => SyntaxNode::Empty,
+                    1 => items.pop().unwrap_or(SyntaxNode::Empty),
+                    _ => SyntaxNode::Subcompound(items),
+                }
+            }
+            CstKind::Group(_) => nodes.next().map_or(SyntaxNode::Empty, SyntaxNode::from),
+            CstKind::Multiplier(mul) => SyntaxNode::Multiplier {
+                node: Box::new(nodes.next().map_or(SyntaxNode::Empty, SyntaxNode::from)),
+                mul: *mul,
+            },
+            CstKind::Atom(atom) => SyntaxNode::Atom(atom.clone()),
+            CstKind::Isotope {
+                symbol,
+                mass_number,
+            } => SyntaxNode::Isotope {
+                symbol: symbol.clone(),
+                mass_number: *mass_number,
+            },
+            CstKind::Electron => SyntaxNode::Electron,
+            CstKind::Moles(_) | CstKind::Charge(_) | CstKind::State(_) | CstKind::Error => {
+                SyntaxNode::Empty
+            }
+        }
+    }
+}
+
+/// Builds the tree of a whole formula from its tokens
+pub(crate) fn build(tokens: Vec<Token>) -> Result<CstNode, StoikError> {
+    Builder { errors: None }.formula(tokens)
+}
+
+/// Builds the tree of a whole formula from its tokens, carrying on past errors
+pub(crate) fn build_recovering(tokens: Vec<Token>) -> (CstNode, Vec<StoikError>) {
+    let mut builder = Builder {
+        errors: Some(Vec::new()),
+    };
+    // a recovering builder never returns an error
+    let root = builder
+        .formula(tokens)
+        .unwrap_or_else(|_| CstNode::new(CstKind::Formula, Vec::new()));
+    (root, builder.errors.unwrap_or_default())
+}
+
+/// Builds trees from tokens, either stopping at the first error or recording each
+/// one and skipping over the tokens that caused it
+struct Builder {
+    errors: Option<Vec<StoikError>>,
+}
+
+impl Builder {
+    /// Records an error when recovering, otherwise returns it
+    fn fail(&mut self, err: StoikError) -> Result<(), StoikError> {
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    fn formula(&mut self, tokens: Vec<Token>) -> Result<CstNode, StoikError> {
+        let mut tokens = VecDeque::from(tokens);
+        let moles = match tokens.front() {
+            Some(Token::Number(n, _)) => {
+                let n = *n;
+                tokens
+                    .pop_front()
+                    .map(|token| CstNode::leaf(CstKind::Moles(n), token))
+            }
+            Some(_) => None,
+            None => {
+                self.fail(StoikError::InvalidInput(
+                    "Empty iter cannot build a valid tree".to_string(),
+                ))?;
+                return Ok(CstNode::new(CstKind::Formula, Vec::new()));
+            }
+        };
+
+        let state = take_state(&mut tokens);
+        let charge = self.take_charge(&mut tokens)?;
+        let body = match tokens.make_contiguous() {
+            [Token::Other(e, _)] if e == "e" && charge.is_some() => tokens
+                .pop_front()
+                .map(|token| CstNode::leaf(CstKind::Electron, token)),
+            _ => Some(self.adduct(tokens.into_iter().collect())?),
+        };
+
+        let children = [moles, body, charge, state]
+            .into_iter()
+            .flatten()
+            .map(CstElement::Node)
+            .collect();
+        Ok(CstNode::new(CstKind::Formula, children))
+    }
+
+    /// Reports a charge that was too big for a number token, which is left as a
+    /// charge of zero when recovering
+    fn charge_overflow(&mut self, token: &Token) -> Result<(), StoikError> {
+        match overflowed(token) {
+            Some(loc) => self.fail(StoikError::Overflow(loc.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes the charge from the end of a formula's tokens, if there is one
+    fn take_charge(&mut self, tokens: &mut VecDeque<Token>) -> Result<Option<CstNode>, StoikError> {
+        let sign_of = |token: Option<&Token>| match token {
+            Some(Token::Plus(_)) => Some(1),
+            Some(Token::Minus(_)) => Some(-1),
+            _ => None,
+        };
+
+        let (charge, taken) = if let Some(Token::Charge(n, _)) = tokens.back() {
+            // `Fe³⁺`, `SO₄²⁻`
+            (*n, 1)
+        } else if let Some(sign) = sign_of(tokens.back()) {
+            // `Ca++`, `Fe3+`, `MnO4-`, `SO4^2-`
+            let mut count = 0;
+            for token in tokens.iter().rev() {
+                match sign_of(Some(token)) {
+                    Some(next) if next != sign => {
+                        // when recovering, the mixed sign is left for the body to report
+                        self.fail(StoikError::InvalidToken(token.get_loc().clone()))?;
+                        break;
+                    }
+                    Some(_) => count += 1,
+                    None => break,
+                }
+            }
+
+            let len = tokens.len() - count;
+            match tokens.range(..len).rev().take(2).collect::<Vec<_>>()[..] {
+                [Token::Number(n, _), Token::Caret(_)] if count == 1 => (sign * n, count + 2),
+                [big, Token::Caret(_)] if count == 1 && overflowed(big).is_some() => {
+                    self.charge_overflow(big)?;
+                    (0, count + 2)
+                }
+                [big, before]
+                    if count == 1 && overflowed(big).is_some() && is_charge_number(before, len) =>
+                {
+                    self.charge_overflow(big)?;
+                    (0, count + 1)
+                }
+                [Token::Number(n, _), before] if count == 1 && is_charge_number(before, len) => {
+                    (sign * n, count + 1)
+                }
+                [Token::Caret(_), ..] => (sign * count as i64, count + 1),
+                _ => (sign * count as i64, count),
+            }
+        } else if let (Some(Token::Number(n, _)), Some(sign)) = (
+            tokens.back(),
+            sign_of(tokens.len().checked_sub(2).and_then(|i| tokens.get(i))),
+        ) {
+            // `Fe+3`, `Fe^+3`
+            let caret = tokens
+                .len()
+                .checked_sub(3)
+                .and_then(|i| tokens.get(i))
+                .is_some_and(|x| matches!(x, Token::Caret(_)));
+            (sign * n, if caret { 3 } else { 2 })
+        } else if let (Some(big), Some(_)) = (
+            tokens.back().filter(|x| overflowed(x).is_some()),
+            sign_of(tokens.len().checked_sub(2).and_then(|i| tokens.get(i))),
+        ) {
+            // `Fe+99999999999999999999`
+            self.charge_overflow(big)?;
+            let caret = tokens
+                .len()
+                .checked_sub(3)
+                .and_then(|i| tokens.get(i))
+                .is_some_and(|x| matches!(x, Token::Caret(_)));
+            (0, if caret { 3 } else { 2 })
+        } else {
+            return Ok(None);
+        };
+
+        let children = tokens
+            .drain(tokens.len() - taken..)
+            .map(token_element)
+            .collect();
+        Ok(Some(CstNode::new(CstKind::Charge(charge), children)))
+    }
+
+    /// Builds the tree of a formula that may be an adduct, with no mole count or charge
+    fn adduct(&mut self, tokens: Vec<Token>) -> Result<CstNode, StoikError> {
+        let mut parts = vec![Vec::new()];
+        let mut separators = Vec::new();
+        let mut level = 0;
+        for token in tokens {
+            match token {
+                Token::OpenBracket(_) | Token::OpenParen(_) => level += 1,
+                Token::CloseBracket(_) | Token::CloseParen(_) => level -= 1,
+                Token::Adduct(_) if level == 0 => {
+                    separators.push(token);
+                    parts.push(Vec::new());
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some(part) = parts.last_mut() {
+                part.push(token);
+            }
+        }
+
+        if parts.len() == 1 {
+            return self.sequence(parts.concat());
+        }
+
+        let locs = separators
+            .iter()
+            .map(|x| x.get_loc().clone())
+            .collect::<Vec<_>>();
+        let mut children = Vec::new();
+        let mut separators = separators.into_iter();
+        for (i, part) in parts.into_iter().enumerate() {
+            let mut part = VecDeque::from(part);
+            let moles = match part.front() {
+                Some(Token::Number(n, _)) => {
+                    let n = *n;
+                    part.pop_front()
+                        .map(|token| CstNode::leaf(CstKind::Moles(n), token))
+                }
+                _ => None,
+            };
+            let node = self.sequence(part.into_iter().collect())?;
+            if SyntaxNode::from(&node) == SyntaxNode::Empty {
+                // report the separator next to the missing part
+                self.fail(StoikError::InvalidToken(locs[i.saturating_sub(1)].clone()))?;
+            }
+
+            let part_children = moles.into_iter().chain([node]).map(CstElement::Node);
+            children.push(CstElement::Node(CstNode::new(
+                CstKind::Part,
+                part_children.collect(),
+            )));
+            if let Some(separator) = separators.next() {
+                children.push(token_element(separator));
+            }
+        }
+        Ok(CstNode::new(CstKind::Adduct, children))
+    }
+
+    /// Builds the tree of a run of atoms and groups
+    fn sequence(&mut self, tokens: Vec<Token>) -> Result<CstNode, StoikError> {
+        let mut bracket_level = 0;
+        let mut paren_level = 0;
+        let mut open = None;
+        let mut nested = Vec::new();
+
+        let mut items: Vec<CstNode> = Vec::new();
+        for token in tokens {
+            if paren_level == 0 && bracket_level == 0 {
+                let err = match token {
+                    Token::OpenBracket(_) => {
+                        bracket_level += 1;
+                        open = Some(token);
+                        continue;
+                    }
+                    Token::OpenParen(_) => {
+                        paren_level += 1;
+                        open = Some(token);
+                        continue;
+                    }
+                    Token::CloseBracket(ref loc) => StoikError::UnpairedBracket(loc.clone()),
+                    Token::CloseParen(ref loc) => StoikError::UnpairedParenthesis(loc.clone()),
+
+                    Token::Number(_, ref loc) | Token::Decimal(_, ref loc) => {
+                        let mul = match token {
+                            Token::Number(n, _) => n.into(),
+                            Token::Decimal(n, _) => n,
+                            _ => Rational::ONE,
+                        };
+                        match items.pop() {
+                            // a number after skipped tokens is skipped along with them
+                            Some(last) if last.kind == CstKind::Error => {
+                                let mut children = last.children;
+                                children.push(token_element(token));
+                                items.push(CstNode::new(CstKind::Error, children));
+                                continue;
+                            }
+                            Some(last) => {
+                                items.push(multiplier(last, mul, token));
+                                continue;
+                            }
+                            None => StoikError::NumberFirst(loc.clone()),
+                        }
+                    }
+                    Token::Atom(ref atom, _) => {
+                        items.push(CstNode::leaf(CstKind::Atom(atom.clone()), token));
+                        continue;
+                    }
+                    Token::Isotope(ref symbol, mass_number, _) => {
+                        let kind = CstKind::Isotope {
+                            symbol: symbol.clone(),
+                            mass_number,
+                        };
+                        items.push(CstNode::leaf(kind, token));
+                        continue;
+                    }
+                    Token::Other(..) if overflowed(&token).is_some() => {
+                        StoikError::Overflow(token.get_loc().clone())
+                    }
+                    Token::Other(_, ref loc)
+                    | Token::Plus(ref loc)
+                    | Token::Minus(ref loc)
+                    | Token::Caret(ref loc)
+                    | Token::Charge(_, ref loc)
+                    | Token::Adduct(ref loc) => StoikError::InvalidToken(loc.clone()),
+                };
+                self.fail(err)?;
+                items.push(CstNode::leaf(CstKind::Error, token));
+            } else {
+                match token {
+                    Token::OpenBracket(_) => bracket_level += 1,
+                    Token::CloseBracket(_) => bracket_level -= 1,
+                    Token::OpenParen(_) => paren_level += 1,
+                    Token::CloseParen(_) => paren_level -= 1,
+                    _ => {}
+                }
+                if bracket_level == 0 && paren_level == 0 {
+                    let Some(open) = open.take() else {
+                        continue;
+                    };
+                    let inner = self.sequence(std::mem::take(&mut nested))?;
+                    items.push(group(open, inner, Some(token)));
+                } else {
+                    nested.push(token);
+                }
+            }
+        }
+
+        if bracket_level != 0 || paren_level != 0 {
+            let loc = open
+                .as_ref()
+                .map(|x| x.get_loc().clone())
+                .unwrap_or_default();
+            self.fail(StoikError::UnpairedParenthesis(loc))?;
+            // carry on as if the group was closed at the end
+            let inner = self.sequence(nested)?;
+            if let Some(open) = open {
+                items.push(group(open, inner, None));
+            }
+        }
+
+        let children = items.into_iter().map(CstElement::Node).collect();
+        Ok(CstNode::new(CstKind::Sequence, children))
+    }
+}
+
+/// Removes the state symbol from the end of a formula's tokens, if there is one
+fn take_state(tokens: &mut VecDeque<Token>) -> Option<CstNode> {
+    let len = tokens.len();
+    let state = match tokens.range(len.checked_sub(3)?..).collect::<Vec<_>>()[..] {
+        [Token::OpenParen(_), Token::Other(symbol, _), Token::CloseParen(_)] => {
+            State::from_symbol(symbol)?
+        }
+        _ => return None,
+    };
+    let children = tokens.drain(len - 3..).map(token_element).collect();
+    Some(CstNode::new(CstKind::State(state), children))
+}
+
+/// Whether a number before a sign is the size of the charge, instead of a multiplier.
+/// `before` is the token before the number, and `len` is the number of tokens left
+/// Gets the location of a number that was too big for a number token, if `token` is one
+fn overflowed(token: &Token) -> Option<&TokenLoc> {
+    match token {
+        Token::Other(s, loc) if s.starts_with(|c: char| c.is_ascii_digit()) => Some(loc),
+        _ => None,
+    }
+}
+
+fn is_charge_number(before: &Token, len: usize) -> bool {
+    // `[Fe(CN)6]4-` or a single atom like `Fe3+`
+    matches!(before, Token::CloseBracket(_))
+        || (len == 2 && matches!(before, Token::Atom(..) | Token::Isotope(..)))
+}
+
+/// Makes a group from its brackets and the sequence inside them, which may be
+/// missing its closing bracket after recovering from an error
+fn group(open: Token, inner: CstNode, close: Option<Token>) -> CstNode {
+    let bracket = match open {
+        Token::OpenBracket(_) => Bracket::Square,
+        _ => Bracket::Paren,
+    };
+    let children = [token_element(open), CstElement::Node(inner)]
+        .into_iter()
+        .chain(close.map(token_element))
+        .collect();
+    CstNode::new(CstKind::Group(bracket), children)
+}
+
+fn multiplier(node: CstNode, mul: Rational, token: Token) -> CstNode {
+    CstNode::new(
+        CstKind::Multiplier(mul),
+        vec![CstElement::Node(node), token_element(token)],
+    )
+}
+
+/// Makes a token element, with text that is filled in from the formula by [`assemble_cst`]
+fn token_element(token: Token) -> CstElement {
+    let text = match &token {
+        Token::OpenBracket(_) => "[".to_string(),
+        Token::CloseBracket(_) => "]".to_string(),
+        Token::OpenParen(_) => "(".to_string(),
+        Token::CloseParen(_) => ")".to_string(),
+        Token::Plus(_) => "+".to_string(),
+        Token::Minus(_) => "-".to_string(),
+        Token::Caret(_) => "^".to_string(),
+        Token::Adduct(_) => "·".to_string(),
+        Token::Number(n, _) => n.to_string(),
+        Token::Decimal(n, _) => n.to_string(),
+        Token::Atom(s, _) | Token::Other(s, _) => s.clone(),
+        Token::Isotope(s, n, _) => format!("[{n}{s}]"),
+        Token::Charge(n, _) => super::charge_string(*n),
+    };
+    CstElement::Token(token, text)
+}
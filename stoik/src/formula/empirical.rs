@@ -0,0 +1,143 @@
+use crate::{err::StoikError, rational::Rational};
+
+use super::{Isotope, MolarMass, Molecule};
+
+/// The largest number the mole ratios are multiplied by to find whole numbers
+const MAX_MULTIPLIER: i64 = 10;
+/// How much closer to whole numbers a larger multiplier has to bring the mole ratios,
+/// relative to their size, to be picked over a smaller one
+const TIE_MARGIN: f64 = 0.001;
+/// How far the molar mass divided by that of the empirical formula can be from a whole
+/// number, relative to its size, and still be rounded to it
+const TOLERANCE: f64 = 0.02;
+
+impl Molecule {
+    /// Finds the empirical formula from the mass of each element, given as `(symbol, mass)` pairs
+    ///
+    /// The masses can be in grams or mass percentages, as only their ratio matters.
+    /// The mole ratios are multiplied by the whole number up to 10 that brings them closest
+    /// to whole numbers, relative to their size, so a ratio of 1.5 is doubled and 1.33 is
+    /// tripled. A smaller multiplier is picked when it is about as close.
+    ///
+    /// # Errors
+    ///
+    /// - [`StoikError::InvalidInput`] if there are no masses, or a mass is not positive
+    /// - [`StoikError::NoAtomicWeight`] if an element is not in the periodic table
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// // glucose
+    /// let empirical = Molecule::empirical_formula(&[("C", 40.0), ("H", 6.71), ("O", 53.29)])?;
+    /// assert_eq!(empirical, Molecule::from_formula("CH2O")?);
+    ///
+    /// // iron(III) oxide has a ratio of 1:1.5
+    /// let empirical = Molecule::empirical_formula(&[("Fe", 69.94), ("O", 30.06)])?;
+    /// assert_eq!(empirical, Molecule::from_formula("Fe2O3")?);
+    ///
+    /// // a ratio of 1:2.1 is not rounded down to 1:2
+    /// let empirical = Molecule::empirical_formula(&[("C", 120.11), ("H", 21.168)])?;
+    /// assert_eq!(empirical, Molecule::from_formula("C10H21")?);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn empirical_formula(masses: &[(&str, f64)]) -> Result<Self, StoikError> {
+        if masses.is_empty() {
+            return Err(StoikError::InvalidInput(
+                "At least one element is needed to find a formula".to_string(),
+            ));
+        }
+
+        let moles = masses
+            .iter()
+            .map(|(symbol, mass)| {
+                if !mass.is_finite() || *mass <= 0.0 {
+                    return Err(StoikError::InvalidInput(format!(
+                        "The mass of `{symbol}` has to be more than zero"
+                    )));
+                }
+                let isotope = Isotope::from_symbol(symbol);
+                let molar_mass = MolarMass::of_isotope(&isotope)
+                    .ok_or_else(|| StoikError::NoAtomicWeight(symbol.to_string()))?;
+                Ok(mass / molar_mass.value)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let smallest = moles.iter().copied().fold(f64::INFINITY, f64::min);
+        let ratios = moles.iter().map(|x| x / smallest).collect::<Vec<_>>();
+
+        // the furthest any ratio is from a whole number when multiplied by `mul`,
+        // relative to the scaled ratio
+        let error = |mul: i64| {
+            ratios
+                .iter()
+                .map(|x| {
+                    let scaled = x * mul as f64;
+                    (scaled - scaled.round()).abs() / scaled
+                })
+                .fold(0.0, f64::max)
+        };
+        let mut multiplier = 1;
+        for mul in 2..=MAX_MULTIPLIER {
+            if error(mul) < error(multiplier) - TIE_MARGIN {
+                multiplier = mul;
+            }
+        }
+
+        let mut new = Self::default();
+        for ((symbol, _), ratio) in masses.iter().zip(ratios) {
+            new.increase_atom(symbol, (ratio * multiplier as f64).round() as i64);
+        }
+        Ok(new)
+    }
+
+    /// Finds the molecular formula from the mass of each element and the molar mass of the
+    /// compound in g/mol, by scaling up the [empirical formula](Self::empirical_formula)
+    ///
+    /// # Errors
+    ///
+    /// As well as the errors from [`empirical_formula`](Self::empirical_formula), this gives
+    /// [`StoikError::InvalidInput`] if the molar mass is less than that of the empirical formula,
+    /// not close to a whole number times it, or so big that the counts would overflow
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// let masses = [("C", 40.0), ("H", 6.71), ("O", 53.29)];
+    /// let glucose = Molecule::molecular_formula(&masses, 180.16)?;
+    /// assert_eq!(glucose, Molecule::from_formula("C6H12O6")?);
+    ///
+    /// // 4.5 times the mass of CH2O
+    /// assert!(Molecule::molecular_formula(&masses, 135.12).is_err());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn molecular_formula(masses: &[(&str, f64)], molar_mass: f64) -> Result<Self, StoikError> {
+        let empirical = Self::empirical_formula(masses)?;
+        let ratio = molar_mass / empirical.molar_mass()?.value;
+        let factor = ratio.round() as i64;
+        if factor < 1 {
+            return Err(StoikError::InvalidInput(format!(
+                "A molar mass of {molar_mass} g/mol is less than that of {empirical}"
+            )));
+        }
+        if (ratio - ratio.round()).abs() > TOLERANCE * ratio {
+            return Err(StoikError::InvalidInput(format!(
+                "A molar mass of {molar_mass} g/mol is not a whole number times that of {empirical}"
+            )));
+        }
+
+        let mut new = Self::default();
+        for (isotope, count) in empirical.get_isotope_map() {
+            let count = count
+                .checked_mul(factor.into())
+                .filter(Rational::fits_i64)
+                .ok_or_else(|| {
+                    StoikError::InvalidInput(format!(
+                        "A molar mass of {molar_mass} g/mol is too big"
+                    ))
+                })?;
+            new.increase_isotope(isotope, count);
+        }
+        Ok(new)
+    }
+}
@@ -0,0 +1,418 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use crate::{err::StoikError, rational::Rational};
+
+use super::{FormulaOrder, Isotope, Molecule, ParseOptions, TokenLoc};
+
+/// The formula, charge and protonation layers of an InChI, like the
+/// `C2H4O2.Na/q;+1/p-1` of sodium acetate's `InChI=1S/C2H4O2.Na/c1-2(3)4;/h1H3,(H,3,4);/q;+1/p-1`
+///
+/// Each disconnected component is a [`Molecule`], with its [`moles`](Molecule::moles) being
+/// how many of it there are, and the formula layer is written in Hill order with the biggest
+/// component first. Isotopes are left out, as InChI gives them in a layer of their own
+///
+/// When an InChI is read the other layers are skipped, so the formula of any InChI, like one
+/// from PubChem, can be compared with stoik's through [`to_molecule`](Self::to_molecule). The
+/// charge of each component is written in the `/q` layer, as moving it into the `/p` layer like
+/// standard InChI does needs the structure of the molecule
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{InchiFormula, Molecule};
+///
+/// let acetate = Molecule::from_formula("CH3COO-")?;
+/// let sodium = Molecule::from_formula("Na+")?;
+/// let formula = InchiFormula::new(vec![sodium, acetate.clone()])?;
+/// assert_eq!(formula.to_string(), "C2H3O2.Na/q-1;+1");
+///
+/// let read: InchiFormula = "InChI=1S/C2H4O2/c1-2(3)4/h1H3,(H,3,4)/p-1".parse()?;
+/// assert_eq!(read.protons, -1);
+/// assert_eq!(read.to_molecule()?, acetate);
+/// assert_eq!(Molecule::from_inchi("InChI=1S/ClH.Na/h1H;/q;+1/p-1")?, Molecule::from_formula("NaCl")?);
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InchiFormula {
+    /// The disconnected components, in the order they are written
+    pub components: Vec<Molecule>,
+    /// The number of protons added to the whole formula by the `/p` layer, which is
+    /// negative if they are taken away
+    pub protons: i64,
+}
+
+impl InchiFormula {
+    /// Makes the formula layers from the components of a mixture or salt, sorting them
+    /// into the order InChI writes them in and joining any that are the same
+    ///
+    /// # Errors
+    ///
+    /// [`StoikError::InvalidInput`] if a component is an electron, has no atoms, has a
+    /// count that is not a whole number, or has a [`moles`](Molecule::moles) of less than one
+    pub fn new(components: Vec<Molecule>) -> Result<Self, StoikError> {
+        let mut sorted: Vec<(Molecule, String)> = Vec::new();
+        for component in components {
+            let formula = hill_formula(&component);
+            if component.is_electron() || formula.is_empty() {
+                return Err(StoikError::InvalidInput(
+                    "an InChI component needs to have atoms".to_string(),
+                ));
+            }
+            if component.get_map().values().any(|x| !x.is_integer()) {
+                return Err(StoikError::InvalidInput(format!(
+                    "`{formula}` cannot be written in an InChI, as it has a count that is not a whole number"
+                )));
+            }
+            if component.moles < 1 {
+                return Err(StoikError::InvalidInput(format!(
+                    "there has to be at least one `{formula}` in an InChI"
+                )));
+            }
+            sorted.push((component, formula));
+        }
+        sorted.sort_by(|(a, a_formula), (b, b_formula)| {
+            heavy_atoms(b)
+                .cmp(&heavy_atoms(a))
+                .then(a_formula.cmp(b_formula))
+                .then(b.charge.cmp(&a.charge))
+        });
+
+        let mut joined: Vec<(Molecule, String)> = Vec::new();
+        for (mut component, formula) in sorted {
+            component.state = None;
+            match joined.last_mut() {
+                Some((last, last_formula))
+                    if *last_formula == formula && last.charge == component.charge =>
+                {
+                    last.moles = last
+                        .moles
+                        .checked_add(component.moles)
+                        .ok_or(StoikError::Overflow(TokenLoc::default()))?;
+                }
+                _ => joined.push((component, formula)),
+            }
+        }
+        Ok(Self {
+            components: joined.into_iter().map(|(x, _)| x).collect(),
+            protons: 0,
+        })
+    }
+
+    /// Reads the formula, `/q` and `/p` layers of an InChI. The `InChI=1S/` at the start can be
+    /// left out, and every other layer is skipped
+    ///
+    /// # Errors
+    ///
+    /// The errors for [`Molecule::from_formula`] if a component is not a formula, with its
+    /// location in the whole InChI. [`StoikError::InvalidToken`] is given for a component with
+    /// a charge or a count that is not a whole number, or a `/q` or `/p` layer that is not
+    /// a list of charges that matches the components.
+    /// [`StoikError::EmptyMolecule`] is given if there is nothing in the InChI
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::{InchiFormula, TokenLoc};
+    /// use stoik::StoikError;
+    ///
+    /// let calcium_chloride: InchiFormula = "InChI=1S/Ca.2ClH/h;2*1H/q+2;;/p-2".parse()?;
+    /// assert_eq!(calcium_chloride.components[1].moles, 2);
+    /// assert_eq!(calcium_chloride.to_string(), "Ca.2ClH/q+2;;/p-2");
+    ///
+    /// let err = "InChI=1S/C2H6Oo".parse::<InchiFormula>().unwrap_err();
+    /// assert!(matches!(err, StoikError::UnknownElement(loc) if loc == TokenLoc::new(14, 2)));
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn parse(inchi: &str) -> Result<Self, StoikError> {
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let body = match inchi.strip_prefix("InChI=") {
+            Some(rest) => rest.find('/').map_or(inchi.len(), |x| x + 7),
+            None => usize::from(inchi.starts_with('/')),
+        };
+        let locate = |start: usize, text: &str| {
+            let start = inchi[..start].chars().count() + 1;
+            TokenLoc::locate(inchi, start, text.chars().count().max(1))
+        };
+
+        let mut new = Self::default();
+        let mut charges = None;
+        let mut start = body;
+        for (i, layer) in inchi[body..].split('/').enumerate() {
+            match layer.chars().next() {
+                Some('q') => charges = Some((start, layer)),
+                Some('p') => {
+                    new.protons = layer[1..]
+                        .parse()
+                        .map_err(|_| StoikError::InvalidToken(locate(start, layer)))?;
+                }
+                // the layers after these are for a different form of the molecule
+                Some('r' | 'f') => break,
+                Some(c) if i == 0 && !c.is_ascii_lowercase() => {
+                    let mut part_start = start;
+                    for part in layer.split('.') {
+                        let component = Molecule::from_formula_with(part, &strict)
+                            .map_err(|e| e.offset(&inchi[..part_start]))?;
+                        let whole = component.get_map().values().all(Rational::is_integer);
+                        if component.charge != 0
+                            || component.state.is_some()
+                            || component.moles < 1
+                            || !whole
+                            || hill_formula(&component).is_empty()
+                        {
+                            return Err(StoikError::InvalidToken(locate(part_start, part)));
+                        }
+                        new.components.push(component);
+                        part_start += part.len() + 1;
+                    }
+                }
+                _ => {}
+            }
+            start += layer.len() + 1;
+        }
+
+        if let Some((start, layer)) = charges {
+            new.read_charges(&layer[1..])
+                .ok_or_else(|| StoikError::InvalidToken(locate(start, layer)))?;
+        }
+        if new.components.is_empty() && new.protons == 0 {
+            return Err(StoikError::EmptyMolecule);
+        }
+        Ok(new)
+    }
+
+    /// Gives each component its charge from the `/q` layer, which has the charge of each
+    /// one of every component seperated by `;`. Components with different charges are split up
+    fn read_charges(&mut self, layer: &str) -> Option<()> {
+        // the charges as (how many, charge), where `2*+1` is two +1s
+        let mut charges = Vec::new();
+        for entry in layer.split(';') {
+            let (count, charge) = match entry.split_once('*') {
+                Some((count, charge)) => (count.parse::<i64>().ok().filter(|x| *x > 0)?, charge),
+                None => (1, entry),
+            };
+            let charge = match charge {
+                "" => 0,
+                charge => charge.parse().ok()?,
+            };
+            charges.push((count, charge));
+        }
+
+        let mut charges = charges.into_iter();
+        let mut next = charges.next();
+        let mut components: Vec<Molecule> = Vec::new();
+        for component in &self.components {
+            let first = components.len();
+            let mut left = component.moles;
+            while left > 0 {
+                let (count, charge) = next.as_mut()?;
+                let taken = left.min(*count);
+                match components[first..].last_mut() {
+                    Some(last) if last.charge == *charge => last.moles += taken,
+                    _ => {
+                        let mut split = component.clone();
+                        split.moles = taken;
+                        split.charge = *charge;
+                        components.push(split);
+                    }
+                }
+                left -= taken;
+                *count -= taken;
+                if *count == 0 {
+                    next = charges.next();
+                }
+            }
+        }
+        // every charge has to be used
+        if next.is_some() {
+            return None;
+        }
+        self.components = components;
+        Some(())
+    }
+
+    /// Makes the whole formula as one molecule, with the protons from the `/p` layer
+    /// added to it. The elements are written in Hill order
+    ///
+    /// # Errors
+    ///
+    /// [`StoikError::InvalidInput`] if the `/p` layer takes away more hydrogens than there are,
+    /// and [`StoikError::Overflow`] if a count or the charge is too big
+    pub fn to_molecule(&self) -> Result<Molecule, StoikError> {
+        let overflow = || StoikError::Overflow(TokenLoc::default());
+        let mut counts: HashMap<String, Rational> = HashMap::new();
+        let mut charge = self.protons;
+        for component in &self.components {
+            for (symbol, count) in component.get_map() {
+                let total = counts.entry(symbol).or_default();
+                *total = total.checked_add(count).ok_or_else(overflow)?;
+            }
+            charge = component
+                .charge
+                .checked_mul(component.moles)
+                .and_then(|x| x.checked_add(charge))
+                .ok_or_else(overflow)?;
+        }
+        let hydrogens = counts.entry("H".to_string()).or_default();
+        *hydrogens = hydrogens
+            .checked_add(self.protons.into())
+            .ok_or_else(overflow)?;
+        if hydrogens.num() < 0 {
+            return Err(StoikError::InvalidInput(
+                "the `/p` layer takes away more hydrogens than there are".to_string(),
+            ));
+        }
+
+        let isotopes = counts
+            .iter()
+            .filter(|(_, count)| !count.is_zero())
+            .map(|(symbol, _)| Isotope::new(symbol, None))
+            .collect::<Vec<_>>();
+        let mut sorted = isotopes.iter().collect::<Vec<_>>();
+        FormulaOrder::Hill.sort(&mut sorted, &[]);
+        let mut molecule = Molecule {
+            charge,
+            ..Default::default()
+        };
+        for isotope in sorted {
+            molecule.increase_isotope(isotope.clone(), counts[&isotope.symbol]);
+        }
+        Ok(molecule)
+    }
+}
+
+impl FromStr for InchiFormula {
+    type Err = StoikError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Display for InchiFormula {
+    /// Writes the layers without the `InChI=1S/` at the start, as they are not a whole InChI
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // components with the same formula but different charges are written together
+        let mut formulas: Vec<(String, i64)> = Vec::new();
+        for component in &self.components {
+            let formula = hill_formula(component);
+            match formulas.last_mut() {
+                Some((last, count)) if *last == formula => *count += component.moles,
+                _ => formulas.push((formula, component.moles)),
+            }
+        }
+        let formulas = formulas
+            .into_iter()
+            .map(|(formula, count)| match count {
+                1 => formula,
+                count => format!("{count}{formula}"),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", formulas.join("."))?;
+
+        if self.components.iter().any(|x| x.charge != 0) {
+            let charges = self
+                .components
+                .iter()
+                .map(|x| match (x.moles, x.charge) {
+                    (1, charge) if charge != 0 => format!("{charge:+}"),
+                    // InChI leaves each neutral one empty, like the `;;` of `2ClH` in `+2;;`
+                    (moles, 0) => vec![""; moles.max(1) as usize].join(";"),
+                    // `3*+1` is three +1s
+                    (moles, charge) => format!("{moles}*{charge:+}"),
+                })
+                .collect::<Vec<_>>();
+            write!(f, "/q{}", charges.join(";"))?;
+        }
+        if self.protons != 0 {
+            write!(f, "/p{:+}", self.protons)?;
+        }
+        Ok(())
+    }
+}
+
+impl Molecule {
+    /// Writes one of the molecule as the formula layers of an InChI. Each part of an adduct
+    /// like `CuSO4·5H2O` is its own component, with the charge given to the first part, and
+    /// anything else is one component. See [`InchiFormula`] for mixtures and salts
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// assert_eq!(Molecule::from_formula("CH3CH2OH")?.to_inchi()?, "C2H6O");
+    /// assert_eq!(Molecule::from_formula("2[13C]H3OH")?.to_inchi()?, "CH4O");
+    /// assert_eq!(Molecule::from_formula("SO4^2-")?.to_inchi()?, "O4S/q-2");
+    /// assert_eq!(Molecule::from_formula("CuSO4·5H2O")?.to_inchi()?, "CuO4S.5H2O");
+    /// assert!(Molecule::from_formula("Fe0.95O")?.to_inchi().is_err());
+    /// assert!(Molecule::from_formula("0H2O")?.to_inchi().is_err());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The same as [`InchiFormula::new`], including if [`moles`](Molecule::moles) is less than one
+    pub fn to_inchi(&self) -> Result<String, StoikError> {
+        if self.moles < 1 {
+            return Err(StoikError::InvalidInput(format!(
+                "there has to be at least one `{}` in an InChI",
+                hill_formula(&unit(self))
+            )));
+        }
+        let mut components = match self.adduct_parts() {
+            Some(parts) => parts.to_vec(),
+            None => vec![unit(self)],
+        };
+        components[0].charge = self.charge;
+        components.iter_mut().for_each(|x| x.state = None);
+        Ok(InchiFormula::new(components)?.to_string())
+    }
+
+    /// Reads the formula layers of an InChI as one molecule, see [`InchiFormula::parse`]
+    /// and [`InchiFormula::to_molecule`]
+    pub fn from_inchi(inchi: &str) -> Result<Self, StoikError> {
+        InchiFormula::parse(inchi)?.to_molecule()
+    }
+}
+
+/// Writes one of a molecule in Hill order, without isotopes or a charge
+fn hill_formula(molecule: &Molecule) -> String {
+    let unit = unit(molecule);
+    let mut formula = String::new();
+    let mut last = None;
+    for isotope in molecule.isotopes(FormulaOrder::Hill) {
+        // isotopes of the same element are next to each other, so are only written once
+        if last == Some(&isotope.symbol) {
+            continue;
+        }
+        last = Some(&isotope.symbol);
+        let count = unit.get_count(&isotope.symbol);
+        if count.is_zero() {
+            continue;
+        }
+        formula += &isotope.symbol;
+        if count != 1 {
+            formula += &count.to_string();
+        }
+    }
+    formula
+}
+
+/// Gets how many atoms of one of a molecule are not hydrogen
+fn heavy_atoms(molecule: &Molecule) -> Rational {
+    unit(molecule)
+        .get_map()
+        .into_iter()
+        .filter(|(symbol, _)| symbol != "H")
+        .map(|(_, count)| count)
+        .sum()
+}
+
+/// Gets a copy of the molecule with one mole
+fn unit(molecule: &Molecule) -> Molecule {
+    let mut unit = molecule.clone();
+    unit.moles = 1;
+    unit
+}
@@ -0,0 +1,61 @@
+use std::fmt::Display;
+
+/// Symbols that are shorthand for an isotope, as `(alias, symbol, mass number)`
+pub const ISOTOPE_ALIASES: [(&str, &str, u16); 2] = [("D", "H", 2), ("T", "H", 3)];
+
+/// An element, optionally labelled with the mass number of one of its isotopes
+///
+/// This is what a [`Molecule`](super::Molecule) counts its atoms by, so that
+/// labelled compounds like `[13C]H4` can be told apart from `CH4`
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::Isotope;
+///
+/// let deuterium = Isotope::from_symbol("D");
+/// assert_eq!(deuterium, Isotope::new("H", Some(2)));
+/// assert_eq!(deuterium.to_string(), "[2H]");
+/// assert_eq!(Isotope::from_symbol("C").to_string(), "C");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Isotope {
+    /// The symbol of the element
+    pub symbol: String,
+    /// The mass number of the isotope, or [`None`] for the natural mix of isotopes
+    pub mass_number: Option<u16>,
+}
+
+impl Isotope {
+    /// Constructs a new isotope from an element symbol and mass number
+    pub fn new(symbol: &str, mass_number: Option<u16>) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            mass_number,
+        }
+    }
+
+    /// Gets the isotope for a symbol as written in a formula, resolving the
+    /// [aliases](ISOTOPE_ALIASES) like `D` and `T`
+    pub fn from_symbol(symbol: &str) -> Self {
+        match ISOTOPE_ALIASES.iter().find(|(alias, ..)| *alias == symbol) {
+            Some((_, element, mass_number)) => Self::new(element, Some(*mass_number)),
+            None => Self::new(symbol, None),
+        }
+    }
+
+    /// Checks if a symbol is an [alias](ISOTOPE_ALIASES) for an isotope
+    pub fn is_alias(symbol: &str) -> bool {
+        ISOTOPE_ALIASES.iter().any(|(alias, ..)| *alias == symbol)
+    }
+}
+
+impl Display for Isotope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mass_number {
+            Some(mass_number) => write!(f, "[{mass_number}{}]", self.symbol),
+            None => write!(f, "{}", self.symbol),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    elements::{self, AtomicWeight},
+    err::StoikError,
+    nuclides::{self, ELECTRON_MASS},
+    rational::Rational,
+};
+
+use super::{Isotope, Molecule};
+
+/// A molar mass in g/mol, with its standard uncertainty
+///
+/// Elements with an interval atomic weight, like hydrogen, use the middle of the
+/// interval with the uncertainty of a uniform distribution over it
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::Molecule;
+///
+/// let mass = Molecule::from_formula("NaCl")?.molar_mass()?;
+/// assert!((mass.value - 58.44).abs() < 0.01);
+/// assert_eq!(mass.to_string(), "58.4413 ± 0.0032 g/mol");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MolarMass {
+    /// The molar mass in g/mol
+    pub value: f64,
+    /// The standard uncertainty of the molar mass in g/mol
+    pub uncertainty: f64,
+}
+
+impl MolarMass {
+    /// Gets the molar mass of one mole of an element
+    ///
+    /// Elements without a standard atomic weight use the mass number of
+    /// their longest lived isotope, with no uncertainty
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::MolarMass;
+    ///
+    /// let sodium = MolarMass::of_element("Na").unwrap();
+    /// assert_eq!(sodium.value, 22.98976928);
+    /// assert_eq!(MolarMass::of_element("Tc").unwrap().value, 97.0);
+    /// assert!(MolarMass::of_element("Xy").is_none());
+    /// ```
+    pub fn of_element(symbol: &str) -> Option<Self> {
+        Some(match elements::get(symbol)?.atomic_weight {
+            AtomicWeight::Value { value, uncertainty } => Self { value, uncertainty },
+            AtomicWeight::Interval { low, high } => Self {
+                value: (low + high) / 2.0,
+                uncertainty: (high - low) / (2.0 * 3.0_f64.sqrt()),
+            },
+            AtomicWeight::MassNumber(n) => Self {
+                value: n as f64,
+                uncertainty: 0.0,
+            },
+        })
+    }
+
+    /// Gets the molar mass of one mole of an isotope
+    ///
+    /// A labelled isotope uses its exact mass from [`nuclides::NUCLIDES`], or its mass number
+    /// if it is not listed there, which is within about 0.1 g/mol of its true mass
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::{Isotope, MolarMass};
+    ///
+    /// let deuterium = MolarMass::of_isotope(&Isotope::from_symbol("D")).unwrap();
+    /// assert_eq!(deuterium.value, 2.01410177812);
+    /// let fluorine = MolarMass::of_isotope(&Isotope::new("F", Some(18))).unwrap();
+    /// assert_eq!(fluorine.value, 18.0);
+    /// assert_eq!(
+    ///     MolarMass::of_isotope(&Isotope::from_symbol("Na")),
+    ///     MolarMass::of_element("Na")
+    /// );
+    /// ```
+    pub fn of_isotope(isotope: &Isotope) -> Option<Self> {
+        let element = Self::of_element(&isotope.symbol)?;
+        Some(match isotope.mass_number {
+            Some(mass_number) => Self {
+                value: nuclides::get(&isotope.symbol, mass_number)
+                    .map_or(mass_number as f64, |x| x.mass),
+                uncertainty: 0.0,
+            },
+            None => element,
+        })
+    }
+}
+
+impl Display for MolarMass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.uncertainty > 0.0 {
+            // show two significant figures of the uncertainty
+            let places = (1 - self.uncertainty.log10().floor() as i32).max(0) as usize;
+            write!(
+                f,
+                "{:.places$} ± {:.places$} g/mol",
+                self.value, self.uncertainty
+            )
+        } else {
+            write!(f, "{} g/mol", self.value)
+        }
+    }
+}
+
+/// How much of a molecule is made up of one element
+///
+/// See [`Molecule::mass_composition`]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Composition {
+    /// The symbol of the element
+    pub element: String,
+    /// The number of atoms of the element in one formula unit
+    pub count: Rational,
+    /// The percentage of the molar mass that comes from the element
+    pub mass_percent: f64,
+    /// The fraction of all the atoms that are the element, from 0 to 1
+    pub atom_fraction: f64,
+}
+
+impl Molecule {
+    /// Calculates the molar mass of the molecule from the IUPAC standard atomic weights,
+    /// taking into account `moles`
+    ///
+    /// The uncertainty of atoms of the same element add together, as they all
+    /// share the same atomic weight, while different elements are combined in quadrature.
+    /// Labelled isotopes use [`MolarMass::of_isotope`]. The mass of the electrons gained or lost
+    /// by an ion is taken into account like in [`monoisotopic_mass`](Self::monoisotopic_mass),
+    /// so an electron has a mass
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoikError::NoAtomicWeight`] if an atom is not in the periodic table
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// let water = Molecule::from_formula("2H2O")?.molar_mass()?;
+    /// assert!((water.value - 36.030).abs() < 0.001);
+    ///
+    /// let electron = Molecule::from_formula("e-")?.molar_mass()?;
+    /// assert!((electron.value - 0.000548580).abs() < 1e-9);
+    ///
+    /// assert!(Molecule::from_formula("Xy")?.molar_mass().is_err());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn molar_mass(&self) -> Result<MolarMass, StoikError> {
+        let mut total = MolarMass {
+            value: self.electron_mass(),
+            uncertainty: 0.0,
+        };
+        let mut variance = 0.0;
+        for (isotope, count) in self.get_isotope_map() {
+            let mass = MolarMass::of_isotope(&isotope)
+                .ok_or_else(|| StoikError::NoAtomicWeight(isotope.to_string()))?;
+            total.value += mass.value * count.to_f64();
+            variance += (mass.uncertainty * count.to_f64()).powi(2);
+        }
+        total.uncertainty = variance.sqrt();
+        Ok(total)
+    }
+
+    /// Gets the mass of the electrons gained or lost by the molecule in daltons,
+    /// taking into account `moles`
+    pub(super) fn electron_mass(&self) -> f64 {
+        -(self.charge as f64) * self.moles as f64 * ELECTRON_MASS
+    }
+
+    /// Gets the mass percentage and atom fraction of each element in one formula unit
+    /// of the molecule, sorted by element symbol
+    ///
+    /// Isotopes are counted as part of their element
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoikError::NoAtomicWeight`] if an atom is not in the periodic table,
+    /// [`StoikError::EmptyMolecule`] if the molecule has no atoms, like an electron,
+    /// or [`StoikError::Overflow`] if there are too many atoms to count
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// let water = Molecule::from_formula("H2O")?.mass_composition()?;
+    /// assert_eq!(water[0].element, "H");
+    /// assert_eq!(water[0].count, 2);
+    /// assert!((water[0].mass_percent - 11.19).abs() < 0.01);
+    /// assert!((water[1].mass_percent - 88.81).abs() < 0.01);
+    /// assert!((water[1].atom_fraction - 1.0 / 3.0).abs() < 1e-12);
+    ///
+    /// assert!(Molecule::from_formula("e-")?.mass_composition().is_err());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn mass_composition(&self) -> Result<Vec<Composition>, StoikError> {
+        let mut elements = BTreeMap::new();
+        for (isotope, &count) in self.map.iter().filter(|(_, count)| **count != 0) {
+            let mass = MolarMass::of_isotope(isotope)
+                .ok_or_else(|| StoikError::NoAtomicWeight(isotope.to_string()))?;
+            let (total_count, total_mass) = elements
+                .entry(isotope.symbol.clone())
+                .or_insert((Rational::ZERO, 0.0));
+            *total_count = total_count
+                .checked_add(count)
+                .ok_or_else(|| self.overflow_at(&isotope.symbol))?;
+            *total_mass += mass.value * count.to_f64();
+        }
+        if elements.is_empty() {
+            return Err(StoikError::EmptyMolecule);
+        }
+
+        let mass = elements.values().map(|(_, mass)| mass).sum::<f64>();
+        let atoms = elements
+            .iter()
+            .try_fold(Rational::ZERO, |acc, (symbol, (count, _))| {
+                acc.checked_add(*count)
+                    .ok_or_else(|| self.overflow_at(symbol))
+            })?;
+        Ok(elements
+            .into_iter()
+            .map(|(element, (count, element_mass))| Composition {
+                element,
+                count,
+                mass_percent: element_mass / mass * 100.0,
+                atom_fraction: count.to_f64() / atoms.to_f64(),
+            })
+            .collect())
+    }
+}
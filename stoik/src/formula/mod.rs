@@ -1,6 +1,6 @@
 //! This module is the main module for parsing chemical equations
 //!
-//! See the documentaion for [`Molecule`], [`assemble_tree`], [`TokenStream`] for more info
+//! See the documentaion for [`Molecule`], [`assemble_tree`], [`assemble_cst`], [`TokenStream`] for more info
 //! ```
 //! use stoik::formula::*;
 //!
@@ -14,16 +14,62 @@
 //! assert_eq!(Molecule::from_formula("2(SO5)3")?, mol);
 //! # Ok::<(), stoik::StoikError>(())
 //! ```
+mod abbreviation;
+mod cst;
+mod empirical;
+mod inchi;
+mod isotope;
+mod mass;
+mod order;
+mod spectrum;
+mod state;
 mod tokenstream;
+mod valence;
 
 use std::{
     collections::{HashMap, VecDeque},
     fmt::{Debug, Display},
 };
 
+pub use abbreviation::*;
+pub use cst::*;
+pub use inchi::*;
+pub use isotope::*;
+pub use mass::*;
+pub use order::*;
+pub use spectrum::*;
+pub use state::*;
 pub use tokenstream::*;
 
-use crate::err::StoikError;
+use crate::{diagnostic::Diagnostic, elements, err::StoikError, rational::Rational};
+
+/// Options to change how a formula is parsed
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{Molecule, ParseOptions};
+/// use stoik::StoikError;
+///
+/// let strict = ParseOptions {
+///     strict: true,
+///     ..Default::default()
+/// };
+/// assert!(Molecule::from_formula_with("Hh2O", &strict).is_err());
+/// assert!(Molecule::from_formula_with("H2O", &strict).is_ok());
+/// // without strict mode anything that looks like an element is allowed
+/// assert!(Molecule::from_formula("Hh2O").is_ok());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseOptions {
+    /// Only allow elements from the periodic table, see [`elements`](crate::elements)
+    ///
+    /// Unknown elements cause a [`StoikError::UnknownElement`]
+    pub strict: bool,
+    /// The abbreviations to expand, like `Me` and `Ph`, see [`Abbreviations`]. None are used by default
+    pub abbreviations: Option<Abbreviations>,
+}
 
 /// A node in a parsed chemical equation syntax tree
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,12 +77,13 @@ use crate::err::StoikError;
 pub enum SyntaxNode {
     /// A subcompund, like the `(SO4)` in `Rh2(SO4)3`
     Subcompound(Vec<SyntaxNode>),
-    /// A node that has a multiplier. e.g. `O2` would become `{ node: Atom("O"), mul: 2 }`
+    /// A node that has a multiplier. e.g. `O2` would become `{ node: Atom("O"), mul: 2 }`.
+    /// The multiplier can be a decimal for non-stoichiometric compounds like `Fe0.95O`
     Multiplier {
         /// The contained sytntax node
         node: Box<SyntaxNode>,
         /// The multiplier for the node
-        mul: i64,
+        mul: Rational,
     },
     /// A node that has contains a whole molecule. e.g. `2Fe` would become `{ node: Atom("Fe"), mul: 2 }`
     Mole {
@@ -45,8 +92,34 @@ pub enum SyntaxNode {
         /// The multiplier for the node
         mul: i64,
     },
+    /// A node with a net charge, e.g. `SO4^2-` would become `{ node: Subcompound([S, O4]), charge: -2 }`
+    Ion {
+        /// The contained sytntax node
+        node: Box<SyntaxNode>,
+        /// The net charge of the node
+        charge: i64,
+    },
     /// An atom in a compound, like the `Rh` in `Rh2(SO4)3`
     Atom(String),
+    /// An atom with a mass number, like the `[13C]` in `[13C]H4`
+    Isotope {
+        /// The symbol of the element
+        symbol: String,
+        /// The mass number of the isotope
+        mass_number: u16,
+    },
+    /// A node with a state symbol, e.g. `H2O(l)` would become `{ node: Subcompound([H2, O]), state: Liquid }`
+    State {
+        /// The contained sytntax node
+        node: Box<SyntaxNode>,
+        /// The state of matter of the node
+        state: State,
+    },
+    /// An adduct or hydrate, like `CuSO4·5H2O`. A leading multiplier on a part is kept as a
+    /// [`Mole`](Self::Mole) node, so `CuSO4·5H2O` becomes `[Subcompound([Cu, S, O4]), Mole { H2O, 5 }]`
+    Adduct(Vec<SyntaxNode>),
+    /// An electron, written as `e-`
+    Electron,
     /// Nothing
     Empty,
 }
@@ -54,7 +127,8 @@ pub enum SyntaxNode {
 /// This assebles a tree of [`SyntaxNode`] from a token stream
 ///
 /// It requires an iterator of [`Token`] passed to it, idealy to be
-/// based off of [`TokenStream`]
+/// based off of [`TokenStream`]. The tree is derived from a [`CstNode`], use
+/// [`assemble_cst`] to keep the whitespace and brackets of the formula
 ///
 /// # Examples
 ///
@@ -64,98 +138,50 @@ pub enum SyntaxNode {
 ///
 /// let ts = TokenStream::new("O2");
 /// let tree = assemble_tree(ts)?;
-/// assert_eq!(tree, SyntaxNode::Multiplier { node: Box::new(SyntaxNode::Atom("O".to_string())), mul: 2 });
+/// assert_eq!(tree, SyntaxNode::Multiplier { node: Box::new(SyntaxNode::Atom("O".to_string())), mul: 2.into() });
 /// # Ok::<(), stoik::StoikError>(())
 /// ```
-pub fn assemble_tree(mut stream: impl Iterator<Item = Token>) -> Result<SyntaxNode, StoikError> {
-    match stream.next() {
-        Some(token) => match token {
-            Token::Number(i, _) => Ok(SyntaxNode::Mole {
-                node: Box::new(internal_tree(stream, None)?),
-                mul: i,
-            }),
-            _ => Ok(internal_tree(stream, Some(token))?),
-        },
-        None => Err(StoikError::InvalidInput(
-            "Empty iter cannot build a valid tree".to_string(),
-        )),
-    }
-}
-
-fn internal_tree(
-    mut stream: impl Iterator<Item = Token>,
-    start: Option<Token>,
-) -> Result<SyntaxNode, StoikError> {
-    let mut start = start.or_else(|| stream.next());
-    if start.is_none() {
-        return Ok(SyntaxNode::Empty);
-    }
-
-    let mut bracket_level = 0;
-    let mut paren_level = 0;
-    let mut nested_start = TokenLoc::new(0, 0);
-    let mut nested_stack = Vec::new();
-
-    let mut tree: Vec<SyntaxNode> = Vec::new();
-    while let Some(token) = start {
-        if paren_level == 0 && bracket_level == 0 {
-            match token {
-                Token::OpenBracket(loc) => {
-                    bracket_level += 1;
-                    nested_start = loc
-                }
-                Token::OpenParen(loc) => {
-                    paren_level += 1;
-                    nested_start = loc
-                }
-                Token::CloseBracket(loc) => return Err(StoikError::UnpairedBracket(loc)),
-                Token::CloseParen(loc) => return Err(StoikError::UnpairedParenthesis(loc)),
-
-                Token::Number(n, loc) => {
-                    let last = tree.pop();
-                    if let Some(last) = last {
-                        tree.push(SyntaxNode::Multiplier {
-                            node: Box::new(last),
-                            mul: n,
-                        })
-                    } else {
-                        return Err(StoikError::NumberFirst(loc));
-                    }
-                }
-                Token::Atom(s, _) => tree.push(SyntaxNode::Atom(s)),
-                Token::Other(_, loc) => return Err(StoikError::InvalidToken(loc)),
-            }
-        } else {
-            match token {
-                Token::OpenBracket(_) => bracket_level += 1,
-                Token::CloseBracket(_) => bracket_level -= 1,
-                Token::OpenParen(_) => paren_level += 1,
-                Token::CloseParen(_) => paren_level -= 1,
-                _ => {}
-            }
-            if bracket_level == 0 && paren_level == 0 {
-                tree.push(internal_tree(nested_stack.into_iter(), None)?);
-                nested_stack = Vec::new();
-            } else {
-                nested_stack.push(token);
-            }
-        }
-
-        start = stream.next();
-    }
-
-    if bracket_level != 0 || paren_level != 0 {
-        return Err(StoikError::UnpairedParenthesis(nested_start));
-    }
-
-    match tree.len() {
-        0 => Ok(SyntaxNode::Empty),
-        1 => Ok(tree[0].clone()),
-        _ => Ok(SyntaxNode::Subcompound(tree)),
-    }
+///
+/// A charge can be given at the end of the formula, either after a `^`, or as
+/// a number and sign. The number is only read as part of the charge for single
+/// atoms and after square brackets, so `Fe3+` has a charge of `3+` while `MnO4-`
+/// has a charge of `-`. Electrons are written as `e-`.
+/// ```
+/// use stoik::formula::*;
+///
+/// for (formula, charge) in [("SO4^2-", -2), ("Fe+3", 3), ("Fe3+", 3), ("Ca++", 2), ("MnO4-", -1), ("[Fe(CN)6]4-", -4), ("e-", -1)] {
+///     let tree = assemble_tree(TokenStream::new(formula))?;
+///     assert!(matches!(tree, SyntaxNode::Ion { charge: c, .. } if c == charge));
+/// }
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+///
+/// The parts of an adduct or hydrate are seperated by a `·`, `.` or `*`, and
+/// each part can have its own multiplier.
+/// ```
+/// use stoik::formula::*;
+///
+/// let tree = assemble_tree(TokenStream::new("CuSO4.5H2O"))?;
+/// assert!(matches!(&tree, SyntaxNode::Adduct(parts) if parts.len() == 2));
+/// assert_eq!(tree.to_string(), "CuSO4·5H2O");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+///
+/// A state symbol of `(s)`, `(l)`, `(g)` or `(aq)` can be given at the very end,
+/// after any charge.
+/// ```
+/// use stoik::formula::*;
+///
+/// let tree = assemble_tree(TokenStream::new("Fe3+(aq)"))?;
+/// assert!(matches!(tree, SyntaxNode::State { state: State::Aqueous, .. }));
+/// assert_eq!(tree.to_string(), "Fe^3+(aq)");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+pub fn assemble_tree(stream: impl Iterator<Item = Token>) -> Result<SyntaxNode, StoikError> {
+    Ok(SyntaxNode::from(&cst::build(stream.collect())?))
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A repesentaion of a molecule used for stoichiometric puroposies
 ///
@@ -173,7 +199,26 @@ fn internal_tree(
 pub struct Molecule {
     /// The mole count of the molecule
     pub moles: i64,
-    map: HashMap<String, i64>,
+    /// The net charge of one unit of the molecule
+    pub charge: i64,
+    /// The state of matter of the molecule, if it was given
+    pub state: Option<State>,
+    map: HashMap<Isotope, Rational>,
+    /// Each isotope in the order it was first added, for [`FormulaOrder::Written`]
+    written: Vec<Isotope>,
+    /// The abbreviations that are kept when the molecule is written out, see [`Abbreviations::keep`]
+    abbreviated: Vec<(String, Rational)>,
+    /// The atoms the kept abbreviations stand for, which are left out when the molecule is written out
+    abbreviated_atoms: HashMap<Isotope, Rational>,
+    /// Each part of an adduct like `CuSO4·5H2O`, with its own moles, so they can be written
+    /// as separate components of an InChI
+    adducts: Vec<Molecule>,
+    /// Where the formula the molecule was parsed from is, which is empty if it was not parsed
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: TokenLoc,
+    /// Where each element is first written in the formula, so errors about it can point there
+    #[cfg_attr(feature = "serde", serde(skip))]
+    element_locs: HashMap<String, TokenLoc>,
 }
 
 #[allow(dead_code)]
@@ -181,11 +226,14 @@ impl Molecule {
     /// Increases the count of an atom in the molecule.
     ///
     /// Adds the count instead if the atom is not already present in the molecule.
+    /// Aliases like `D` are counted as their [`Isotope`].
+    /// The count saturates like the [`Rational`] operators if it overflows.
     ///
     /// # Examples
     ///
     /// ```
     /// use stoik::formula::Molecule;
+    /// use stoik::Rational;
     ///
     /// let mut oxygen = Molecule::from_formula("O3")?;
     /// oxygen.increase_atom("O", -1);
@@ -196,24 +244,72 @@ impl Molecule {
     /// let mut mol = Molecule::from_formula("2 H2O")?;
     /// mol.increase_atom("O", 1);
     /// assert_eq!(mol.get_count("O"), 4);
+    ///
+    /// let mut wustite = Molecule::from_formula("FeO")?;
+    /// wustite.increase_atom("Fe", Rational::new(-1, 20));
+    /// assert_eq!(wustite, Molecule::from_formula("Fe0.95O")?);
     /// # Ok::<(), stoik::StoikError>(())
     /// ```
-    pub fn increase_atom(&mut self, atom: &str, n: i64) {
-        if let Some(count) = self.map.get_mut(atom) {
-            *count += n;
-        } else {
-            self.map.insert(atom.to_string(), n);
+    pub fn increase_atom(&mut self, atom: &str, n: impl Into<Rational>) {
+        self.increase_isotope(Isotope::from_symbol(atom), n);
+    }
+
+    /// Increases the count of an isotope in the molecule.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stoik::formula::{Isotope, Molecule};
+    ///
+    /// let mut methane = Molecule::from_formula("CH4")?;
+    /// methane.increase_isotope(Isotope::new("C", Some(13)), 1);
+    /// assert_eq!(methane.get_count("C"), 2);
+    /// assert_eq!(methane.get_isotope_count(&Isotope::new("C", Some(13))), 1);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn increase_isotope(&mut self, isotope: Isotope, n: impl Into<Rational>) {
+        if !self.map.contains_key(&isotope) {
+            self.written.push(isotope.clone());
         }
+        *self.map.entry(isotope).or_default() += n.into();
     }
 
     /// Construts a molecule from a sytnax tree
     ///
     /// See docs for [`from_formula`](Self::from_formula) to get usage
-    pub fn construct_from_tree(mut root: SyntaxNode) -> Result<Self, StoikError> {
-        let mut new = Self {
-            moles: 1,
-            map: HashMap::new(),
-        };
+    ///
+    /// # Errors
+    ///
+    /// As well as the errors for a malformed tree, this gives [`StoikError::Overflow`] if a
+    /// count is too big. The tree has no locations, so the location of the overflow is empty
+    pub fn construct_from_tree(root: SyntaxNode) -> Result<Self, StoikError> {
+        Self::construct_from_tree_with(root, &ParseOptions::default())
+    }
+
+    /// Construts a molecule from a sytnax tree like [`construct_from_tree`](Self::construct_from_tree),
+    /// expanding the [`Abbreviations`] in the given [`ParseOptions`]
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::*;
+    ///
+    /// let options = ParseOptions {
+    ///     abbreviations: Some(Abbreviations::default()),
+    ///     ..Default::default()
+    /// };
+    /// let tree = assemble_tree(TokenStream::new("PhMe"))?;
+    /// let toluene = Molecule::construct_from_tree_with(tree, &options)?;
+    /// assert_eq!(toluene.to_string_with(FormulaOrder::Hill), "C7H8");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn construct_from_tree_with(
+        mut root: SyntaxNode,
+        options: &ParseOptions,
+    ) -> Result<Self, StoikError> {
+        let abbreviations = options.abbreviations.as_ref();
+        let overflow = || StoikError::Overflow(TokenLoc::default());
+
+        let mut new = Self::default();
 
         if let SyntaxNode::Mole { node, mul } = root {
             root = *node;
@@ -222,30 +318,199 @@ impl Molecule {
             return Err(StoikError::EmptyMolecule);
         }
 
+        if let SyntaxNode::State { node, state } = root {
+            root = *node;
+            new.state = Some(state);
+            if root == SyntaxNode::Empty {
+                return Err(StoikError::EmptyMolecule);
+            }
+        }
+
+        if let SyntaxNode::Ion { node, charge } = root {
+            root = *node;
+            new.charge = charge;
+            if root == SyntaxNode::Electron {
+                if !new.fits_i64() {
+                    return Err(overflow());
+                }
+                return Ok(new);
+            } else if root == SyntaxNode::Empty {
+                return Err(StoikError::EmptyMolecule);
+            }
+        }
+
+        if let SyntaxNode::Adduct(parts) = &root {
+            // errors are given when the whole molecule is made below
+            new.adducts = parts
+                .iter()
+                .map(|x| Self::construct_from_tree_with(x.clone(), options))
+                .collect::<Result<_, _>>()
+                .unwrap_or_default();
+        }
+
         let mut stack = VecDeque::new();
-        stack.push_back(MoleculeStackItem::new(root, 1));
+        stack.push_back(MoleculeStackItem::new(root, Rational::ONE));
 
         while !stack.is_empty() {
             let MoleculeStackItem { node, mul } = stack.pop_front().unwrap();
             match node {
-                SyntaxNode::Subcompound(nodes) => {
+                // children go on the front in reverse, so atoms are added in the order they are written
+                SyntaxNode::Subcompound(mut nodes) => {
+                    if let Some(abbreviations) = abbreviations {
+                        nodes = abbreviations.join_atoms(nodes);
+                    }
                     nodes
                         .into_iter()
-                        .for_each(|x| stack.push_back(MoleculeStackItem::new(x, mul)));
+                        .rev()
+                        .for_each(|x| stack.push_front(MoleculeStackItem::new(x, mul)));
                 }
                 SyntaxNode::Multiplier { node, mul: new_mul } => {
-                    stack.push_front(MoleculeStackItem::new(*node, new_mul * mul));
+                    let mul = mul.checked_mul(new_mul).ok_or_else(overflow)?;
+                    stack.push_front(MoleculeStackItem::new(*node, mul));
+                }
+                SyntaxNode::Adduct(parts) => {
+                    for part in parts.into_iter().rev() {
+                        match part {
+                            SyntaxNode::Mole { node, mul: new_mul } => {
+                                let mul = mul.checked_mul(new_mul.into()).ok_or_else(overflow)?;
+                                stack.push_front(MoleculeStackItem::new(*node, mul));
+                            }
+                            part => stack.push_front(MoleculeStackItem::new(part, mul)),
+                        }
+                    }
                 }
-                SyntaxNode::Atom(atom) => new.increase_atom(&atom, mul),
+                SyntaxNode::Atom(atom) => {
+                    match abbreviations.and_then(|x| Some((x, x.get(&atom)?))) {
+                        Some((abbreviations, formula)) => new
+                            .expand(&atom, formula, mul, abbreviations.keep)?
+                            .ok_or_else(overflow)?,
+                        None => new
+                            .checked_increase(Isotope::from_symbol(&atom), mul)
+                            .ok_or_else(overflow)?,
+                    }
+                }
+                SyntaxNode::Isotope {
+                    symbol,
+                    mass_number,
+                } => new
+                    .checked_increase(Isotope::new(&symbol, Some(mass_number)), mul)
+                    .ok_or_else(overflow)?,
                 SyntaxNode::Empty => continue,
-                SyntaxNode::Mole { .. } => return Err(StoikError::InvalidNode(node, new)),
+                SyntaxNode::Mole { .. }
+                | SyntaxNode::State { .. }
+                | SyntaxNode::Ion { .. }
+                | SyntaxNode::Electron => return Err(StoikError::InvalidNode(node, Box::new(new))),
             }
         }
 
+        if !new.fits_i64() {
+            return Err(overflow());
+        }
         Ok(new)
     }
 
-    /// Gets the count of an atom with respect to mole count
+    /// Makes an overflow error about an element, pointing at where it is first written,
+    /// or the whole formula if it is not written, or the molecule was not parsed
+    pub(crate) fn overflow_at(&self, symbol: &str) -> StoikError {
+        let loc = self.element_locs.get(symbol).unwrap_or(&self.span);
+        StoikError::Overflow(loc.clone())
+    }
+
+    /// Moves where the molecule is written right past `before`, like [`TokenLoc::offset`]
+    pub(crate) fn offset_locs(&mut self, before: &str) {
+        if !self.span.is_empty() {
+            self.span.offset(before);
+        }
+        self.element_locs
+            .values_mut()
+            .for_each(|x| x.offset(before));
+    }
+
+    /// Forgets where the molecule is written, for when it is written out somewhere new
+    pub(crate) fn relocate(&mut self, span: TokenLoc) {
+        self.span = span;
+        self.element_locs.clear();
+    }
+
+    /// Increases the count of an isotope like [`increase_isotope`](Self::increase_isotope),
+    /// giving `None` instead of overflowing
+    fn checked_increase(&mut self, isotope: Isotope, n: Rational) -> Option<()> {
+        let count = self
+            .map
+            .get(&isotope)
+            .copied()
+            .unwrap_or_default()
+            .checked_add(n)?;
+        if !self.map.contains_key(&isotope) {
+            self.written.push(isotope.clone());
+        }
+        self.map.insert(isotope, count);
+        Some(())
+    }
+
+    /// Adds the atoms of an abbreviation `mul` times, giving `None` if a count overflows
+    fn expand(
+        &mut self,
+        name: &str,
+        formula: &str,
+        mul: Rational,
+        keep: bool,
+    ) -> Result<Option<()>, StoikError> {
+        let expansion = Self::from_formula(formula).map_err(|_| {
+            StoikError::InvalidInput(format!(
+                "The abbreviation `{name}` stands for `{formula}`, which is not a formula"
+            ))
+        })?;
+        for isotope in &expansion.written {
+            let Some(count) = expansion.map[isotope].checked_mul(mul) else {
+                return Ok(None);
+            };
+            if self.checked_increase(isotope.clone(), count).is_none() {
+                return Ok(None);
+            }
+            if keep {
+                let kept = self.abbreviated_atoms.entry(isotope.clone()).or_default();
+                let Some(total) = kept.checked_add(count) else {
+                    return Ok(None);
+                };
+                *kept = total;
+            }
+        }
+        if keep {
+            match self.abbreviated.iter_mut().find(|(x, _)| x == name) {
+                Some((_, count)) => {
+                    let Some(total) = count.checked_add(mul) else {
+                        return Ok(None);
+                    };
+                    *count = total;
+                }
+                None => self.abbreviated.push((name.to_string(), mul)),
+            }
+        }
+        Ok(Some(()))
+    }
+
+    /// Checks that every count, the total of each element and the charge fit in an [`i64`] once
+    /// multiplied by `moles`, so that adding them up never overflows
+    pub(crate) fn fits_i64(&self) -> bool {
+        if self.charge.checked_mul(self.moles).is_none() {
+            return false;
+        }
+        let mut totals = HashMap::new();
+        for (isotope, count) in &self.map {
+            let Some(count) = count.checked_mul(self.moles.into()) else {
+                return false;
+            };
+            let total = totals.entry(&isotope.symbol).or_insert(Rational::ZERO);
+            match total.checked_add(count) {
+                Some(new) if count.fits_i64() && new.fits_i64() => *total = new,
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Gets the count of an element with respect to mole count, including all of its isotopes
     ///
     /// # Examples
     /// ```
@@ -256,10 +521,72 @@ impl Molecule {
     ///
     /// let water = Molecule::from_formula("2 H2O")?;
     /// assert_eq!(water.get_count("H"), 4);
+    ///
+    /// let heavy_water = Molecule::from_formula("HDO")?;
+    /// assert_eq!(heavy_water.get_count("H"), 2);
+    ///
+    /// let cuprate = Molecule::from_formula("YBa2Cu3O6.9")?;
+    /// assert_eq!(cuprate.get_count("O").to_string(), "6.9");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn get_count(&self, atom: &str) -> Rational {
+        self.map
+            .iter()
+            .filter(|(isotope, _)| isotope.symbol == atom)
+            .map(|(_, count)| *count)
+            .sum::<Rational>()
+            * self.moles.into()
+    }
+
+    /// Gets the count of one isotope with respect to mole count
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::{Isotope, Molecule};
+    ///
+    /// let heavy_water = Molecule::from_formula("HDO")?;
+    /// assert_eq!(heavy_water.get_isotope_count(&Isotope::from_symbol("D")), 1);
+    /// assert_eq!(heavy_water.get_isotope_count(&Isotope::from_symbol("H")), 1);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn get_isotope_count(&self, isotope: &Isotope) -> Rational {
+        self.map.get(isotope).copied().unwrap_or_default() * self.moles.into()
+    }
+
+    /// Gets the net charge with respect to mole count
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// let sulfate = Molecule::from_formula("3SO4^2-")?;
+    /// assert_eq!(sulfate.charge, -2);
+    /// assert_eq!(sulfate.total_charge(), -6);
     /// # Ok::<(), stoik::StoikError>(())
     /// ```
-    pub fn get_count(&self, atom: &str) -> i64 {
-        *self.map.get(atom).unwrap_or(&0) * self.moles
+    pub fn total_charge(&self) -> i128 {
+        i128::from(self.charge) * i128::from(self.moles)
+    }
+
+    /// Gets the parts of an adduct like `CuSO4·5H2O`, or [`None`] if the molecule is not one,
+    /// or it was changed after it was parsed so the parts no longer add up to it
+    pub(crate) fn adduct_parts(&self) -> Option<&[Molecule]> {
+        let mut total: HashMap<Isotope, Rational> = HashMap::new();
+        for part in &self.adducts {
+            for (isotope, count) in part.get_isotope_map() {
+                let entry = total.entry(isotope).or_default();
+                *entry = entry.checked_add(count)?;
+            }
+        }
+        total.retain(|_, count| !count.is_zero());
+        let mut map = self.map.clone();
+        map.retain(|_, count| !count.is_zero());
+        (!self.adducts.is_empty() && total == map).then_some(&self.adducts[..])
+    }
+
+    /// Checks if the molecule is an electron
+    pub fn is_electron(&self) -> bool {
+        self.map.is_empty() && self.charge == -1
     }
 
     /// Convenience function for construct a molicule direcly from a [`&str`]
@@ -276,54 +603,359 @@ impl Molecule {
     /// # Ok::<(), stoik::StoikError>(())
     /// ```
     pub fn from_formula(formula: &str) -> Result<Self, StoikError> {
-        Self::construct_from_tree(assemble_tree(TokenStream::new(formula))?)
+        Self::from_formula_with(formula, &ParseOptions::default())
+    }
+
+    /// Construct a molecule from a [`&str`] using the given [`ParseOptions`]
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::*;
+    /// use stoik::StoikError;
+    ///
+    /// let options = ParseOptions {
+    ///     strict: true,
+    ///     ..Default::default()
+    /// };
+    /// let err = Molecule::from_formula_with("CuXy2", &options).unwrap_err();
+    /// assert!(matches!(err, StoikError::UnknownElement(loc) if loc == TokenLoc::new(3, 2)));
+    /// ```
+    pub fn from_formula_with(formula: &str, options: &ParseOptions) -> Result<Self, StoikError> {
+        let tokens = TokenStream::new(formula).collect::<Vec<_>>();
+        if options.strict {
+            if let Some(loc) = unknown_elements(&tokens, options).next() {
+                return Err(StoikError::UnknownElement(loc));
+            }
+        }
+        let mut element_locs = HashMap::new();
+        for token in &tokens {
+            let symbol = match token {
+                Token::Atom(atom, _) => Isotope::from_symbol(atom).symbol,
+                Token::Isotope(symbol, _, _) => symbol.clone(),
+                _ => continue,
+            };
+            element_locs
+                .entry(symbol)
+                .or_insert_with(|| token.get_loc().clone());
+        }
+
+        let mut new = Self::construct_from_tree_with(assemble_tree(tokens.into_iter())?, options)
+            .map_err(|e| locate_overflow(e, formula))?;
+        new.span = TokenLoc::locate(formula, 1, formula.chars().count());
+        new.element_locs = element_locs;
+        Ok(new)
+    }
+
+    /// Finds every problem in a formula, instead of stopping at the first one like
+    /// [`from_formula_with`](Self::from_formula_with) does
+    ///
+    /// The diagnostics are sorted by where they are in the formula, and there are none if
+    /// the formula can be parsed. See [`assemble_cst_recovering`] for which errors are recovered from
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::{Molecule, ParseOptions, TokenLoc};
+    ///
+    /// let options = ParseOptions {
+    ///     strict: true,
+    ///     ..Default::default()
+    /// };
+    /// let diagnostics = Molecule::diagnose("Xy2(SO4))3", &options);
+    /// assert_eq!(diagnostics.len(), 2);
+    /// assert_eq!(diagnostics[0].message, "Unknown element");
+    /// assert_eq!(diagnostics[0].suggestion.as_deref(), Some("Did you mean Xe, Dy"));
+    /// assert_eq!(diagnostics[1].span, Some(TokenLoc::new(9, 1)));
+    ///
+    /// assert!(Molecule::diagnose("H2O", &options).is_empty());
+    /// ```
+    pub fn diagnose(formula: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+        let tokens = TokenStream::new(formula).collect::<Vec<_>>();
+        let mut errors = Vec::new();
+        if options.strict {
+            errors.extend(unknown_elements(&tokens, options).map(StoikError::UnknownElement));
+        }
+        let (root, parse_errors) = cst::build_recovering(tokens);
+        if parse_errors.is_empty() {
+            // the counts can only be worked out once the whole tree is known
+            if let Err(e) = Self::construct_from_tree_with(SyntaxNode::from(&root), options) {
+                errors.push(locate_overflow(e, formula));
+            }
+        }
+        errors.extend(parse_errors);
+
+        let mut diagnostics = errors
+            .iter()
+            .map(|e| Diagnostic::from_error(e, formula))
+            .collect::<Vec<_>>();
+        diagnostics.sort_by_key(|x| x.span.as_ref().map(|loc| loc.start()));
+        diagnostics.dedup();
+        diagnostics
     }
 
-    /// Gets the molecule in map form, taking into account `moles`
+    /// Gets the molecule in map form by element, taking into account `moles`
+    ///
+    /// The isotopes of each element are counted together, so `D2O` has 2 `H`
     ///
     /// # Examples
     /// ```
     /// use stoik::formula::Molecule;
     /// let mol = Molecule::from_formula("2 H2O")?;
     /// let map = mol.get_map();
-    /// assert_eq!(Some(&4), map.get("H"));
+    /// assert_eq!(map["H"], 4);
     /// assert_eq!(None, map.get("S"));
+    ///
+    /// let mol = Molecule::from_formula("CH3D")?;
+    /// assert_eq!(mol.get_map()["H"], 4);
+    /// # Ok::<(), stoik::StoikError>(())
+    pub fn get_map(&self) -> HashMap<String, Rational> {
+        let mut map = HashMap::new();
+        for (isotope, count) in &self.map {
+            *map.entry(isotope.symbol.clone()).or_default() += *count * self.moles.into();
+        }
+        map
+    }
+
+    /// Gets the molecule in map form like [`get_map`](Self::get_map), giving `None`
+    /// instead of saturating if a count overflows
+    pub(crate) fn checked_map(&self) -> Option<HashMap<String, Rational>> {
+        let mut map = HashMap::<_, Rational>::new();
+        for (isotope, count) in &self.map {
+            let total = map.entry(isotope.symbol.clone()).or_default();
+            *total = total.checked_add(count.checked_mul(self.moles.into())?)?;
+        }
+        Some(map)
+    }
+
+    /// Gets the molecule in map form by isotope, taking into account `moles`
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::{Isotope, Molecule};
+    /// let mol = Molecule::from_formula("^13CH3D")?;
+    /// let map = mol.get_isotope_map();
+    /// assert_eq!(map[&Isotope::new("C", Some(13))], 1);
+    /// assert_eq!(map[&Isotope::new("H", None)], 3);
+    /// assert_eq!(map[&Isotope::new("H", Some(2))], 1);
     /// # Ok::<(), stoik::StoikError>(())
-    pub fn get_map(&self) -> HashMap<String, i64> {
+    pub fn get_isotope_map(&self) -> HashMap<Isotope, Rational> {
         self.map
             .iter()
-            .map(|(s, x)| (s.clone(), x * self.moles))
+            .map(|(s, x)| (s.clone(), *x * self.moles.into()))
             .collect()
     }
 }
 
-impl Display for Molecule {
+impl Display for SyntaxNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // nested groups need parentheses so their multipliers apply to the whole group
+        let grouped = |node: &SyntaxNode| {
+            matches!(
+                node,
+                SyntaxNode::Subcompound(_) | SyntaxNode::Multiplier { .. } | SyntaxNode::Adduct(_)
+            )
+        };
+        match self {
+            SyntaxNode::Subcompound(nodes) => {
+                for node in nodes {
+                    if matches!(node, SyntaxNode::Subcompound(_) | SyntaxNode::Adduct(_)) {
+                        write!(f, "({node})")?;
+                    } else {
+                        write!(f, "{node}")?;
+                    }
+                }
+                Ok(())
+            }
+            SyntaxNode::Multiplier { node, mul } if grouped(node) => write!(f, "({node}){mul}"),
+            SyntaxNode::Multiplier { node, mul } => write!(f, "{node}{mul}"),
+            SyntaxNode::Mole { node, mul } => write!(f, "{mul}{node}"),
+            SyntaxNode::Ion { node, charge } => write!(f, "{node}{}", charge_string(*charge)),
+            SyntaxNode::State { node, state } => write!(f, "{node}{state}"),
+            SyntaxNode::Adduct(parts) => {
+                let parts = parts.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+                write!(f, "{}", parts.join("·"))
+            }
+            SyntaxNode::Atom(atom) => write!(f, "{atom}"),
+            SyntaxNode::Isotope {
+                symbol,
+                mass_number,
+            } => write!(f, "[{mass_number}{symbol}]"),
+            SyntaxNode::Electron => write!(f, "e"),
+            SyntaxNode::Empty => Ok(()),
+        }
+    }
+}
+
+/// Gets the location of every atom that is not in the periodic table, for [`ParseOptions::strict`].
+/// Atoms that are part of an abbreviation are allowed
+fn unknown_elements<'a>(
+    tokens: &'a [Token],
+    options: &ParseOptions,
+) -> impl Iterator<Item = TokenLoc> + 'a {
+    let mut abbreviated = vec![false; tokens.len()];
+    if let Some(abbreviations) = &options.abbreviations {
+        let mut i = 0;
+        while i < tokens.len() {
+            let symbols = tokens[i..]
+                .iter()
+                .map_while(|x| match x {
+                    Token::Atom(atom, _) => Some(atom.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let len = abbreviations.longest_match(&symbols).unwrap_or(0);
+            abbreviated[i..i + len].fill(true);
+            i += len.max(1);
+        }
+    }
+
+    tokens
+        .iter()
+        .zip(abbreviated)
+        .filter_map(|(token, abbreviated)| {
+            let unknown = match token {
+                Token::Atom(atom, _) => {
+                    !abbreviated && elements::get(atom).is_none() && !Isotope::is_alias(atom)
+                }
+                Token::Isotope(symbol, _, _) => elements::get(symbol).is_none(),
+                _ => false,
+            };
+            unknown.then(|| token.get_loc().clone())
+        })
+}
+
+/// Points an overflow from making a molecule at the token that caused it, or the whole
+/// formula if it cannot be found, as the syntax tree has no locations
+fn locate_overflow(err: StoikError, formula: &str) -> StoikError {
+    match err {
+        StoikError::Overflow(loc) if loc.is_empty() => {
+            let loc = cst::build(TokenStream::new(formula).collect())
+                .ok()
+                .and_then(|root| root.overflow_loc());
+            StoikError::Overflow(
+                loc.unwrap_or_else(|| TokenLoc::locate(formula, 1, formula.chars().count())),
+            )
+        }
+        e => e,
+    }
+}
+
+/// Formats a charge in the form `^2-`, or nothing for a neutral charge
+pub(crate) fn charge_string(charge: i64) -> String {
+    match charge {
+        0 => String::new(),
+        1 => "^+".to_string(),
+        -1 => "^-".to_string(),
+        _ => format!("^{}{}", charge.abs(), if charge > 0 { "+" } else { "-" }),
+    }
+}
+
+impl Molecule {
+    /// Gets every isotope in the molecule in the given order
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::{FormulaOrder, Molecule};
+    ///
+    /// let mol = Molecule::from_formula("NaHCO3")?;
+    /// let isotopes = mol.isotopes(FormulaOrder::Hill);
+    /// let symbols = isotopes.iter().map(|x| x.symbol.as_str()).collect::<Vec<_>>();
+    /// assert_eq!(symbols, ["C", "H", "Na", "O"]);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn isotopes(&self, order: FormulaOrder) -> Vec<&Isotope> {
+        let mut isotopes = self.map.keys().collect::<Vec<_>>();
+        order.sort(&mut isotopes, &self.written);
+        isotopes
+    }
+
+    /// Formats the molecule like [`Display`], with the elements in the given order
+    ///
+    /// See [`FormulaOrder`] for examples
+    pub fn to_string_with(&self, order: FormulaOrder) -> String {
+        let mut new = String::new();
         if self.moles != 1 {
-            write!(f, "{} ", self.moles)?;
+            new += &format!("{} ", self.moles);
         }
-        let pairs = &mut self.map.iter().collect::<Vec<_>>();
-        pairs.sort_by(|(ak, _), (bk, _)| ak.cmp(bk));
-        for (k, v) in pairs {
-            if **v != 1 {
-                write!(f, "{k}{v}")?;
-            } else {
-                write!(f, "{k}")?;
+        for (name, count) in &self.abbreviated {
+            match *count {
+                count if count == 1 => new += name,
+                // `OTf2` would be read as `OTf` with two `Tf`
+                count if name.chars().filter(|c| c.is_uppercase()).count() > 1 => {
+                    new += &format!("({name}){count}")
+                }
+                count => new += &format!("{name}{count}"),
             }
         }
+        for isotope in self.isotopes(order) {
+            let count = self.map[isotope]
+                - self
+                    .abbreviated_atoms
+                    .get(isotope)
+                    .copied()
+                    .unwrap_or_default();
+            if count == 0 && self.abbreviated_atoms.contains_key(isotope) {
+                continue;
+            }
+            new += &isotope.to_string();
+            if count != 1 {
+                new += &count.to_string();
+            }
+        }
+
+        if self.is_electron() {
+            new += "e";
+        }
+        new += &charge_string(self.charge);
+        if let Some(state) = self.state {
+            new += &state.to_string();
+        }
+        new
+    }
+}
 
-        Ok(())
+impl PartialEq for Molecule {
+    fn eq(&self, other: &Self) -> bool {
+        // the written order does not change what the molecule is
+        self.moles == other.moles
+            && self.charge == other.charge
+            && self.state == other.state
+            && self.map == other.map
+    }
+}
+impl Eq for Molecule {}
+
+impl Default for Molecule {
+    /// An empty molecule, with one mole
+    fn default() -> Self {
+        Self {
+            moles: 1,
+            charge: 0,
+            state: None,
+            map: HashMap::new(),
+            written: Vec::new(),
+            abbreviated: Vec::new(),
+            abbreviated_atoms: HashMap::new(),
+            adducts: Vec::new(),
+            span: TokenLoc::default(),
+            element_locs: HashMap::new(),
+        }
+    }
+}
+
+impl Display for Molecule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_string_with(FormulaOrder::Alphabetical))
     }
 }
 
 #[derive(Debug)]
 struct MoleculeStackItem {
     node: SyntaxNode,
-    mul: i64,
+    mul: Rational,
 }
 
 impl MoleculeStackItem {
-    pub fn new(node: SyntaxNode, mul: i64) -> Self {
+    pub fn new(node: SyntaxNode, mul: Rational) -> Self {
         Self { node, mul }
     }
 }
@@ -0,0 +1,114 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{elements, err::StoikError};
+
+use super::Isotope;
+
+/// The order the elements of a [`Molecule`](super::Molecule) are written in
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{FormulaOrder, Molecule};
+///
+/// let ethanol = Molecule::from_formula("CH3CH2OH")?;
+/// assert_eq!(ethanol.to_string_with(FormulaOrder::Hill), "C2H6O");
+/// assert_eq!(ethanol.to_string_with(FormulaOrder::Written), "C2H6O");
+///
+/// let salt = Molecule::from_formula("NaCl")?;
+/// assert_eq!(salt.to_string_with(FormulaOrder::Alphabetical), "ClNa");
+/// assert_eq!(salt.to_string_with(FormulaOrder::Electronegativity), "NaCl");
+///
+/// let sulfuric = Molecule::from_formula("H2SO4")?;
+/// assert_eq!(sulfuric.to_string_with(FormulaOrder::Hill), "H2O4S");
+/// assert_eq!(sulfuric.to_string_with(FormulaOrder::Written), "H2SO4");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormulaOrder {
+    /// Sorted by symbol, this is what [`Display`] uses
+    #[default]
+    Alphabetical,
+    /// Carbon, then hydrogen, then everything else by symbol. Without carbon everything is by symbol
+    Hill,
+    /// The least electronegative element first, as is normal for inorganic compounds
+    Electronegativity,
+    /// The order the elements first appear in the formula
+    Written,
+}
+
+impl FormulaOrder {
+    /// Every order, in the order they are declared
+    pub const ALL: [FormulaOrder; 4] = [
+        FormulaOrder::Alphabetical,
+        FormulaOrder::Hill,
+        FormulaOrder::Electronegativity,
+        FormulaOrder::Written,
+    ];
+
+    /// Sorts isotopes into this order, where `written` is the order they were written in.
+    /// Isotopes of the same element are kept together, with the natural mix first
+    pub(crate) fn sort(&self, isotopes: &mut [&Isotope], written: &[Isotope]) {
+        match self {
+            FormulaOrder::Alphabetical => isotopes.sort(),
+            FormulaOrder::Hill => {
+                let has_carbon = isotopes.iter().any(|x| x.symbol == "C");
+                let rank = |isotope: &Isotope| match isotope.symbol.as_str() {
+                    "C" if has_carbon => 0,
+                    "H" if has_carbon => 1,
+                    _ => 2,
+                };
+                isotopes.sort_by(|a, b| rank(a).cmp(&rank(b)).then(a.cmp(b)));
+            }
+            FormulaOrder::Electronegativity => {
+                let electronegativity = |isotope: &Isotope| {
+                    elements::get(&isotope.symbol)
+                        .and_then(|x| x.electronegativity)
+                        .unwrap_or(f64::INFINITY)
+                };
+                isotopes.sort_by(|a, b| {
+                    electronegativity(a)
+                        .total_cmp(&electronegativity(b))
+                        .then(a.cmp(b))
+                });
+            }
+            FormulaOrder::Written => {
+                // the first time any isotope of the element was written
+                let position = |isotope: &Isotope| {
+                    written
+                        .iter()
+                        .position(|x| x.symbol == isotope.symbol)
+                        .unwrap_or(usize::MAX)
+                };
+                isotopes.sort_by(|a, b| position(a).cmp(&position(b)).then(a.cmp(b)));
+            }
+        }
+    }
+}
+
+impl FromStr for FormulaOrder {
+    type Err = StoikError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                StoikError::InvalidInput(format!(
+                    "`{s}` is not an order, use alphabetical, hill, electronegativity or written"
+                ))
+            })
+    }
+}
+
+impl Display for FormulaOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormulaOrder::Alphabetical => write!(f, "Alphabetical"),
+            FormulaOrder::Hill => write!(f, "Hill"),
+            FormulaOrder::Electronegativity => write!(f, "Electronegativity"),
+            FormulaOrder::Written => write!(f, "Written"),
+        }
+    }
+}
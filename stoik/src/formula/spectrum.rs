@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+
+use crate::{err::StoikError, nuclides};
+
+use super::{Isotope, Molecule};
+
+/// The most peaks a partly built envelope can have before giving up, which stops very
+/// large molecules from taking forever
+const MAX_PEAKS: usize = 4096;
+
+/// The fraction of the tallest peak that peaks are pruned below while an envelope is built,
+/// which is small enough that the peaks that are left barely move
+const PRUNE_THRESHOLD: f64 = 1e-9;
+
+/// One peak in an isotopic envelope, see [`Molecule::isotopic_envelope`]
+///
+/// Isotopologues with the same nominal mass are grouped into one peak, as they
+/// cannot be told apart by most mass spectrometers
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Peak {
+    /// The mass in daltons, which is the mean mass of the isotopologues in the peak
+    /// weighted by their abundance
+    pub mass: f64,
+    /// The abundance relative to the tallest peak, from 0 to 1
+    pub abundance: f64,
+}
+
+/// The peaks of an envelope while it is being built, with one for each nominal mass from `start`
+/// as its relative abundance and mean mass
+#[derive(Debug, Clone)]
+struct Distribution {
+    start: i128,
+    peaks: Vec<(f64, f64)>,
+}
+
+impl Molecule {
+    /// Calculates the monoisotopic mass of the molecule in daltons, taking into account `moles`
+    ///
+    /// This is the mass with only the most abundant isotope of each element, while labelled
+    /// isotopes use their own mass. The mass of the electrons gained or lost by an ion is
+    /// taken into account
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoikError::NoAtomicWeight`] if an element has no isotopes found in nature,
+    /// or a labelled isotope is not in [`nuclides::NUCLIDES`]
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// let water = Molecule::from_formula("H2O")?.monoisotopic_mass()?;
+    /// assert!((water - 18.010565).abs() < 1e-6);
+    ///
+    /// let chloride = Molecule::from_formula("Cl-")?.monoisotopic_mass()?;
+    /// assert!((chloride - 34.969401).abs() < 1e-6);
+    ///
+    /// let labelled = Molecule::from_formula("[13C]O2")?.monoisotopic_mass()?;
+    /// assert!((labelled - 44.993185).abs() < 1e-6);
+    ///
+    /// assert!(Molecule::from_formula("TcO4-")?.monoisotopic_mass().is_err());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn monoisotopic_mass(&self) -> Result<f64, StoikError> {
+        let mut mass = self.electron_mass();
+        for (isotope, count) in self.get_isotope_map() {
+            let nuclide = match isotope.mass_number {
+                Some(mass_number) => nuclides::get(&isotope.symbol, mass_number),
+                None => nuclides::most_abundant(&isotope.symbol),
+            };
+            let nuclide = nuclide.ok_or_else(|| StoikError::NoAtomicWeight(isotope.to_string()))?;
+            mass += nuclide.mass * count.to_f64();
+        }
+        Ok(mass)
+    }
+
+    /// Calculates the mass of the tallest peak in the [isotopic envelope](Self::isotopic_envelope)
+    /// of the molecule in daltons
+    ///
+    /// This is the same as the monoisotopic mass for small molecules, but moves away from it
+    /// for larger ones, as it becomes likely that at least one atom is a heavier isotope
+    ///
+    /// # Errors
+    ///
+    /// The same as [`Molecule::isotopic_envelope`]
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// let methane = Molecule::from_formula("CH4")?;
+    /// assert!((methane.most_abundant_mass()? - methane.monoisotopic_mass()?).abs() < 1e-9);
+    ///
+    /// // one of the hundred carbon atoms is most likely to be carbon-13
+    /// let big = Molecule::from_formula("C100H202")?;
+    /// assert!((big.most_abundant_mass()? - big.monoisotopic_mass()? - 1.0).abs() < 0.01);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn most_abundant_mass(&self) -> Result<f64, StoikError> {
+        // a threshold of 1 only leaves the tallest peak
+        let envelope = self.isotopic_envelope(1.0)?;
+        let tallest = envelope.first().ok_or(StoikError::EmptyMolecule)?;
+        Ok(tallest.mass)
+    }
+
+    /// Calculates the isotopic envelope of the molecule, which is the pattern of peaks it gives
+    /// in a mass spectrum, sorted by mass. This takes into account `moles`, so `2H2O` is
+    /// treated as one `H4O2` molecule
+    ///
+    /// The envelope of each element is the polynomial of its isotope abundances raised to the
+    /// power of its count, which is multiplied out by convolution and repeated squaring, pruning
+    /// the tiniest peaks after each step. Peaks less abundant than `threshold` times the tallest
+    /// peak are then left out, so a threshold of `0.001` drops the peaks under 0.1% of the tallest
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StoikError::NoAtomicWeight`] if an element has no isotopes found in nature,
+    /// or a labelled isotope is not in [`nuclides::NUCLIDES`],
+    /// [`StoikError::InvalidInput`] if the count of an element is not a whole number,
+    /// and [`StoikError::Overflow`] if the molecule is so large the envelope has too many peaks,
+    /// pointing at where the element that made it too large is first written
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// let envelope = Molecule::from_formula("CH2Cl2")?.isotopic_envelope(0.001)?;
+    /// let masses = envelope.iter().map(|x| x.mass.round()).collect::<Vec<_>>();
+    /// assert_eq!(masses, [84.0, 85.0, 86.0, 87.0, 88.0, 89.0]);
+    /// assert_eq!(envelope[0].abundance, 1.0);
+    /// // two chlorines give peaks two apart, in the ratio of about 9:6:1
+    /// assert!((envelope[2].abundance - 0.64).abs() < 0.01);
+    /// assert!((envelope[4].abundance - 0.10).abs() < 0.01);
+    ///
+    /// assert!(Molecule::from_formula("Fe0.95O")?.isotopic_envelope(0.001).is_err());
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn isotopic_envelope(&self, threshold: f64) -> Result<Vec<Peak>, StoikError> {
+        let mut elements: BTreeMap<Isotope, i128> = BTreeMap::new();
+        for (isotope, count) in self.get_isotope_map() {
+            if !count.is_integer() || count.num() < 0 {
+                return Err(StoikError::InvalidInput(format!(
+                    "The count of `{isotope}` has to be a positive whole number to find its isotopes"
+                )));
+            }
+            if !count.is_zero() {
+                elements.insert(isotope, count.num());
+            }
+        }
+
+        let mut envelope = Distribution::single(0, self.electron_mass());
+        for (isotope, count) in elements {
+            let base = match isotope.mass_number {
+                Some(mass_number) => nuclides::get(&isotope.symbol, mass_number)
+                    .map(|x| Distribution::single(x.mass_number.into(), x.mass)),
+                None => Distribution::of_element(&isotope.symbol),
+            };
+            let base = base.ok_or_else(|| StoikError::NoAtomicWeight(isotope.to_string()))?;
+            envelope = base
+                .power(count, PRUNE_THRESHOLD)
+                .and_then(|x| envelope.convolve(&x, PRUNE_THRESHOLD))
+                .ok_or_else(|| self.overflow_at(&isotope.symbol))?;
+        }
+
+        // the tallest peak is never left out
+        envelope.normalise(threshold.min(1.0));
+        Ok(envelope
+            .peaks
+            .into_iter()
+            .filter(|(abundance, _)| *abundance > 0.0)
+            .map(|(abundance, mass)| Peak { mass, abundance })
+            .collect())
+    }
+}
+
+impl Distribution {
+    /// Makes an envelope with one peak
+    fn single(nominal: i128, mass: f64) -> Self {
+        Self {
+            start: nominal,
+            peaks: vec![(1.0, mass)],
+        }
+    }
+
+    /// Makes the envelope of one atom of an element from its natural isotopes,
+    /// or [`None`] if it has none
+    fn of_element(symbol: &str) -> Option<Self> {
+        let nuclides = nuclides::of(symbol)
+            .iter()
+            .filter(|x| x.abundance > 0.0)
+            .collect::<Vec<_>>();
+        let start = nuclides.first()?.mass_number;
+        let end = nuclides.last()?.mass_number;
+        let mut peaks = vec![(0.0, 0.0); usize::from(end - start) + 1];
+        for nuclide in nuclides {
+            peaks[usize::from(nuclide.mass_number - start)] = (nuclide.abundance, nuclide.mass);
+        }
+        Some(Self {
+            start: start.into(),
+            peaks,
+        })
+    }
+
+    /// Raises the envelope to the power of `count` by repeated squaring,
+    /// giving the envelope of `count` atoms, or [`None`] if it has too many peaks
+    fn power(mut self, mut count: i128, threshold: f64) -> Option<Self> {
+        let mut result = Self::single(0, 0.0);
+        while count > 0 {
+            if count % 2 == 1 {
+                result = result.convolve(&self, threshold)?;
+            }
+            count /= 2;
+            if count > 0 {
+                self = self.convolve(&self, threshold)?;
+            }
+        }
+        Some(result)
+    }
+
+    /// Multiplies two envelopes together, as if they were polynomials of the nominal mass,
+    /// giving [`None`] if the product has too many peaks
+    fn convolve(&self, other: &Self, threshold: f64) -> Option<Self> {
+        let start = self.start.checked_add(other.start)?;
+        let len = self.peaks.len() + other.peaks.len() - 1;
+        if len > 2 * MAX_PEAKS || start.checked_add(len as i128).is_none() {
+            return None;
+        }
+
+        // the masses are totalled up first, then divided by the abundance
+        let mut peaks = vec![(0.0, 0.0); len];
+        for (i, (abundance_a, mass_a)) in self.peaks.iter().enumerate() {
+            if *abundance_a == 0.0 {
+                continue;
+            }
+            for (j, (abundance_b, mass_b)) in other.peaks.iter().enumerate() {
+                let abundance = abundance_a * abundance_b;
+                peaks[i + j].0 += abundance;
+                peaks[i + j].1 += abundance * (mass_a + mass_b);
+            }
+        }
+        for (abundance, mass) in &mut peaks {
+            if *abundance > 0.0 {
+                *mass /= *abundance;
+            }
+        }
+
+        let mut product = Self { start, peaks };
+        product.normalise(threshold);
+        (product.peaks.len() <= MAX_PEAKS).then_some(product)
+    }
+
+    /// Scales the envelope so the tallest peak has an abundance of 1, then prunes the
+    /// peaks below `threshold`
+    fn normalise(&mut self, threshold: f64) {
+        let tallest = self.peaks.iter().map(|x| x.0).fold(0.0, f64::max);
+        if tallest <= 0.0 {
+            return;
+        }
+        for peak in &mut self.peaks {
+            peak.0 /= tallest;
+            if peak.0 < threshold {
+                *peak = (0.0, 0.0);
+            }
+        }
+
+        let first = self.peaks.iter().position(|x| x.0 > 0.0).unwrap_or(0);
+        let last = self.peaks.iter().rposition(|x| x.0 > 0.0).unwrap_or(0);
+        self.peaks.truncate(last + 1);
+        self.peaks.drain(..first);
+        self.start += first as i128;
+    }
+}
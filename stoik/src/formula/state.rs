@@ -0,0 +1,61 @@
+use std::fmt::Display;
+
+/// The state of matter of a species, written at the end of a formula like the `(aq)` in `NaCl(aq)`
+///
+/// # Examples
+///
+/// ```
+/// use stoik::formula::{Molecule, State};
+///
+/// let water = Molecule::from_formula("2H2O(l)")?;
+/// assert_eq!(water.state, Some(State::Liquid));
+/// assert_eq!(water.get_count("H"), 4);
+/// assert_eq!(water.to_string(), "2 H2O(l)");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum State {
+    /// A solid - `(s)`
+    Solid,
+    /// A liquid - `(l)`
+    Liquid,
+    /// A gas - `(g)`
+    Gas,
+    /// Dissolved in water - `(aq)`
+    Aqueous,
+}
+
+impl State {
+    /// Every state, in the order they are declared
+    pub const ALL: [State; 4] = [State::Solid, State::Liquid, State::Gas, State::Aqueous];
+
+    /// Gets the symbol of the state without parentheses, e.g. `aq`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            State::Solid => "s",
+            State::Liquid => "l",
+            State::Gas => "g",
+            State::Aqueous => "aq",
+        }
+    }
+
+    /// Gets the state from its symbol without parentheses
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::State;
+    ///
+    /// assert_eq!(State::from_symbol("g"), Some(State::Gas));
+    /// assert_eq!(State::from_symbol("x"), None);
+    /// ```
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.symbol() == symbol)
+    }
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({})", self.symbol())
+    }
+}
@@ -1,9 +1,16 @@
-use std::{fmt::Display, iter::Peekable, str::Chars};
+use std::{fmt::Display, iter::Peekable, ops::Range, str::Chars};
 
-const NOT_OTHER: [char; 14] = [
-    '(', '[', ')', ']', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::rational::Rational;
+
+const NOT_OTHER: [char; 17] = [
+    '(', '[', ')', ']', '+', '-', '^', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
 ];
 
+/// The characters that can seperate the parts of an adduct, like the `·` in `CuSO4·5H2O`
+pub const ADDUCT_SEPARATORS: [char; 5] = ['·', '.', '*', '•', '∙'];
+
 /// This is an iterator over [`Token`] that is constructed from a given formula
 ///
 /// # Examples
@@ -18,6 +25,42 @@ const NOT_OTHER: [char; 14] = [
 /// assert_eq!(None, ts.next());
 /// ```
 ///
+/// Atoms with a mass number are written as `[13C]` or `^13C`
+///
+/// ```
+/// use stoik::formula::{TokenStream, Token, TokenLoc};
+///
+/// let mut ts = TokenStream::new("[13C]H4");
+/// assert_eq!(Some(Token::Isotope("C".to_string(), 13, TokenLoc::new(1, 5))), ts.next());
+/// ```
+///
+/// A `.` between digits is a decimal point, for non-stoichiometric compounds like `Fe0.95O`.
+/// As `.` also seperates adducts, it is only read as a decimal point if the number before it is
+/// `0`, or if the number after it is not followed by an atom, so `CuSO4.5H2O` is still a hydrate
+///
+/// ```
+/// use stoik::formula::{TokenStream, Token, TokenLoc};
+/// use stoik::Rational;
+///
+/// let mut ts = TokenStream::new("Fe0.95O");
+/// assert_eq!(Some(Token::Atom("Fe".to_string(), TokenLoc::new(1, 2))), ts.next());
+/// assert_eq!(Some(Token::Decimal(Rational::new(19, 20), TokenLoc::new(3, 4))), ts.next());
+///
+/// let ts = TokenStream::new("CuSO4.5H2O");
+/// assert!(ts.map(|x| x.to_string()).any(|x| x == "·"));
+/// ```
+///
+/// Subscripts are read as numbers and superscripts as charges, so formulas can be pasted
+/// as they are written, like `SO₄²⁻`
+///
+/// ```
+/// use stoik::formula::{TokenStream, Token, TokenLoc};
+///
+/// let mut ts = TokenStream::new("SO₄²⁻");
+/// assert_eq!(Some(Token::Number(4, TokenLoc::new(3, 1))), ts.nth(2));
+/// assert_eq!(Some(Token::Charge(-2, TokenLoc::new(4, 2))), ts.next());
+/// ```
+///
 /// You can also use normal [`Iterator`] functions on a token stream
 ///
 /// ```
@@ -30,6 +73,7 @@ const NOT_OTHER: [char; 14] = [
 pub struct TokenStream<'a> {
     iter: Peekable<Chars<'a>>,
     pos: usize,
+    locator: Locator<'a>,
 }
 
 impl Iterator for TokenStream<'_> {
@@ -51,14 +95,41 @@ impl Iterator for TokenStream<'_> {
             // handle parens and brackets
             self.pos += 1;
 
-            if c == '(' {
-                Some(Token::OpenParen(TokenLoc::new(self.pos, 1)))
+            if let Some(isotope) = self.isotope(c) {
+                Some(isotope)
+            } else if c == '(' {
+                Some(Token::OpenParen(self.locator.locate(self.pos, 1)))
             } else if c == '[' {
-                Some(Token::OpenBracket(TokenLoc::new(self.pos, 1)))
+                Some(Token::OpenBracket(self.locator.locate(self.pos, 1)))
             } else if c == ')' {
-                Some(Token::CloseParen(TokenLoc::new(self.pos, 1)))
+                Some(Token::CloseParen(self.locator.locate(self.pos, 1)))
             } else if c == ']' {
-                Some(Token::CloseBracket(TokenLoc::new(self.pos, 1)))
+                Some(Token::CloseBracket(self.locator.locate(self.pos, 1)))
+            } else if c == '+' {
+                Some(Token::Plus(self.locator.locate(self.pos, 1)))
+            } else if c == '-' {
+                Some(Token::Minus(self.locator.locate(self.pos, 1)))
+            } else if c == '^' {
+                Some(Token::Caret(self.locator.locate(self.pos, 1)))
+            } else if ADDUCT_SEPARATORS.contains(&c) {
+                Some(Token::Adduct(self.locator.locate(self.pos, 1)))
+            } else if let Some(digit) = subscript(c) {
+                // handle subscript numbers, which are always multipliers
+
+                let mut count = digit.to_string();
+                while let Some(digit) = self.iter.peek().copied().and_then(subscript) {
+                    let _ = self.iter.next();
+                    count.push(digit);
+                }
+                let pos = self.pos;
+                self.pos += count.len() - 1;
+                let loc = self.locator.locate(pos, count.len());
+                match count.parse() {
+                    Ok(n) => Some(Token::Number(n, loc)),
+                    Err(_) => Some(Token::Other(count, loc)),
+                }
+            } else if superscript(c).is_some() {
+                Some(self.superscript(c))
             } else if let '0'..='9' = c {
                 // handle numbers
 
@@ -70,12 +141,19 @@ impl Iterator for TokenStream<'_> {
                         break;
                     }
                 }
+                if let Some(fraction) = self.fraction(&count) {
+                    count = format!("{count}.{fraction}");
+                }
                 let pos = self.pos;
                 self.pos += count.len() - 1;
-                Some(Token::Number(
-                    count.parse::<i64>().unwrap(),
-                    TokenLoc::new(pos, count.len()),
-                ))
+                let loc = self.locator.locate(pos, count.len());
+                // numbers too big to store are left as other, and become an overflow error
+                let token = if count.contains('.') {
+                    count.parse().ok().map(|n| Token::Decimal(n, loc.clone()))
+                } else {
+                    count.parse().ok().map(|n| Token::Number(n, loc.clone()))
+                };
+                Some(token.unwrap_or(Token::Other(count, loc)))
             } else if c.is_uppercase() {
                 // handle alphabetic atoms
 
@@ -88,21 +166,33 @@ impl Iterator for TokenStream<'_> {
                     }
                 }
                 let pos = self.pos;
-                self.pos += atom.len() - 1;
-                Some(Token::Atom(atom, TokenLoc::new(pos, self.pos - pos + 1)))
+                self.pos += atom.chars().count() - 1;
+                Some(Token::Atom(
+                    atom,
+                    self.locator.locate(pos, self.pos - pos + 1),
+                ))
             } else {
                 // idk how to handle other chars, get yeeted here lmao
                 let mut output = c.to_string();
                 while let Some(c) = self.iter.peek() {
-                    if !NOT_OTHER.contains(c) && !c.is_uppercase() && !c.is_whitespace() {
+                    if !NOT_OTHER.contains(c)
+                        && !ADDUCT_SEPARATORS.contains(c)
+                        && !c.is_uppercase()
+                        && !c.is_whitespace()
+                        && subscript(*c).is_none()
+                        && superscript(*c).is_none()
+                    {
                         output += &self.iter.next().unwrap().to_string();
                     } else {
                         break;
                     }
                 }
                 let pos = self.pos;
-                self.pos += output.len() - 1;
-                Some(Token::Other(output, TokenLoc::new(pos, self.pos - pos + 1)))
+                self.pos += output.chars().count() - 1;
+                Some(Token::Other(
+                    output,
+                    self.locator.locate(pos, self.pos - pos + 1),
+                ))
             }
         } else {
             None
@@ -117,11 +207,133 @@ impl<'a> TokenStream<'a> {
         Self {
             iter: formula.chars().peekable(),
             pos: 0,
+            locator: Locator::new(formula),
         }
     }
+
+    /// Reads an isotope like `[13C]` or `^13C`, where `c` is the `[` or `^` that has
+    /// already been taken. Nothing more is taken if it is not an isotope
+    fn isotope(&mut self, c: char) -> Option<Token> {
+        if c != '[' && c != '^' {
+            return None;
+        }
+
+        let mut ahead = self.iter.clone();
+        let mut digits = String::new();
+        while let Some(digit) = ahead.next_if(char::is_ascii_digit) {
+            digits.push(digit);
+        }
+        let mass_number = digits.parse::<u16>().ok()?;
+        let mut symbol = ahead.next_if(|x| x.is_uppercase())?.to_string();
+        while let Some(lower) = ahead.next_if(|x| x.is_lowercase()) {
+            symbol.push(lower);
+        }
+        if c == '[' {
+            ahead.next_if_eq(&']')?;
+        }
+
+        let len = digits.len() + symbol.chars().count() + if c == '[' { 2 } else { 1 };
+        let token = Token::Isotope(symbol, mass_number, self.locator.locate(self.pos, len));
+        self.iter = ahead;
+        self.pos += len - 1;
+        Some(token)
+    }
+
+    /// Reads a run of superscripts, where `c` is the first one and has already been taken.
+    /// This is either a charge like the `³⁺` in `Fe³⁺`, or the mass number of an isotope like `¹³C`
+    fn superscript(&mut self, c: char) -> Token {
+        let mut text = c.to_string();
+        while let Some(c) = self.iter.next_if(|c| superscript(*c).is_some()) {
+            text.push(c);
+        }
+        let ascii = text.chars().filter_map(superscript).collect::<String>();
+        let (digits, signs): (String, String) = ascii.chars().partition(char::is_ascii_digit);
+        let pos = self.pos;
+        let len = text.chars().count();
+        self.pos += len - 1;
+
+        if signs.is_empty() {
+            if let (Ok(mass_number), Some(upper)) = (
+                digits.parse::<u16>(),
+                self.iter.next_if(|c| c.is_uppercase()),
+            ) {
+                let mut symbol = upper.to_string();
+                while let Some(lower) = self.iter.next_if(|c| c.is_lowercase()) {
+                    symbol.push(lower);
+                }
+                self.pos += symbol.chars().count();
+                let loc = self.locator.locate(pos, len + symbol.chars().count());
+                return Token::Isotope(symbol, mass_number, loc);
+            }
+        }
+
+        // `⁺`, `⁺⁺`, `³⁺` and `⁺³` are charges, but not `³`, `⁺⁻` or `³⁺⁺`
+        let loc = self.locator.locate(pos, len);
+        let one_sign = signs.chars().all(|c| signs.starts_with(c));
+        let in_order = ascii.starts_with(&digits) || ascii.ends_with(&digits);
+        if signs.is_empty() || !one_sign || (!digits.is_empty() && (signs.len() > 1 || !in_order)) {
+            return Token::Other(text, loc);
+        }
+        let sign = if signs.starts_with('+') { 1 } else { -1 };
+        if digits.is_empty() {
+            return Token::Charge(sign * signs.len() as i64, loc);
+        }
+        match digits.parse::<i64>() {
+            Ok(n) => Token::Charge(sign * n, loc),
+            // numbers too big to store are left as other, and become an overflow error
+            Err(_) => Token::Other(digits, loc),
+        }
+    }
+
+    /// Reads the digits after a decimal point, where `whole` is the number before it.
+    /// Nothing is taken if the `.` is an adduct seperator instead
+    fn fraction(&mut self, whole: &str) -> Option<String> {
+        let mut ahead = self.iter.clone();
+        ahead.next_if_eq(&'.')?;
+        let mut fraction = String::new();
+        while let Some(digit) = ahead.next_if(char::is_ascii_digit) {
+            fraction.push(digit);
+        }
+        if fraction.is_empty() {
+            return None;
+        }
+
+        // `CuSO4.5H2O` is a hydrate, but `Fe0.95O` and `YBa2Cu3O6.9` are not
+        let before_atom = ahead
+            .peek()
+            .is_some_and(|c| c.is_uppercase() || *c == '(' || *c == '[');
+        if whole.chars().all(|c| c == '0') || !before_atom {
+            self.iter = ahead;
+            Some(fraction)
+        } else {
+            None
+        }
+    }
+}
+
+/// Gets the digit a subscript like the `₂` in `H₂O` stands for
+fn subscript(c: char) -> Option<char> {
+    match c {
+        '₀'..='₉' => char::from_digit(c as u32 - '₀' as u32, 10),
+        _ => None,
+    }
+}
+
+/// Gets the digit or sign a superscript like the `³` or `⁺` in `Fe³⁺` stands for
+fn superscript(c: char) -> Option<char> {
+    match c {
+        '⁰' => Some('0'),
+        '¹' => Some('1'),
+        '²' => Some('2'),
+        '³' => Some('3'),
+        '⁴'..='⁹' => char::from_digit(c as u32 - '⁰' as u32, 10),
+        '⁺' => Some('+'),
+        '⁻' => Some('-'),
+        _ => None,
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// One "lexical" token in a formula. It carries along its location in a formula using [`TokenLoc`]
 /// This is intended to be generated with [`TokenStream`]
@@ -134,10 +346,24 @@ pub enum Token {
     OpenParen(TokenLoc),
     /// A closing parenthesis - `)`
     CloseParen(TokenLoc),
-    /// A i64 number
+    /// A plus sign, used for charges - `+`
+    Plus(TokenLoc),
+    /// A minus sign, used for charges - `-`
+    Minus(TokenLoc),
+    /// A caret, used to explicitly mark a charge - `^`
+    Caret(TokenLoc),
+    /// A seperator between the parts of an adduct or hydrate, see [`ADDUCT_SEPARATORS`] - `·`
+    Adduct(TokenLoc),
+    /// A i64 number, written with digits or subscripts
     Number(i64, TokenLoc),
+    /// A number with a decimal point, like the `0.95` in `Fe0.95O`
+    Decimal(Rational, TokenLoc),
     /// Any capital followed by 0 or more lowercase
     Atom(String, TokenLoc),
+    /// An atom with a mass number, written as `[13C]`, `^13C` or `¹³C`
+    Isotope(String, u16, TokenLoc),
+    /// A charge written in superscripts, like the `³⁺` in `Fe³⁺`
+    Charge(i64, TokenLoc),
     /// Anything else that could not fit above, normally erronious
     Other(String, TokenLoc),
 }
@@ -159,8 +385,15 @@ impl Token {
             Token::CloseBracket(loc) => loc,
             Token::OpenParen(loc) => loc,
             Token::CloseParen(loc) => loc,
+            Token::Plus(loc) => loc,
+            Token::Minus(loc) => loc,
+            Token::Caret(loc) => loc,
+            Token::Adduct(loc) => loc,
             Token::Number(_, loc) => loc,
+            Token::Decimal(_, loc) => loc,
             Token::Atom(_, loc) => loc,
+            Token::Isotope(_, _, loc) => loc,
+            Token::Charge(_, loc) => loc,
             Token::Other(_, loc) => loc,
         }
     }
@@ -174,8 +407,17 @@ impl PartialEq for Token {
             (Self::CloseBracket(_), Self::CloseBracket(_)) => true,
             (Self::OpenParen(_), Self::OpenParen(_)) => true,
             (Self::CloseParen(_), Self::CloseParen(_)) => true,
+            (Self::Plus(_), Self::Plus(_)) => true,
+            (Self::Minus(_), Self::Minus(_)) => true,
+            (Self::Caret(_), Self::Caret(_)) => true,
+            (Self::Adduct(_), Self::Adduct(_)) => true,
             (Self::Number(lhs, _), Self::Number(rhs, _)) => lhs == rhs,
+            (Self::Decimal(lhs, _), Self::Decimal(rhs, _)) => lhs == rhs,
             (Self::Atom(lhs, _), Self::Atom(rhs, _)) => lhs == rhs,
+            (Self::Isotope(lhs, lhs_mass, _), Self::Isotope(rhs, rhs_mass, _)) => {
+                lhs == rhs && lhs_mass == rhs_mass
+            }
+            (Self::Charge(lhs, _), Self::Charge(rhs, _)) => lhs == rhs,
             (Self::Other(lhs, _), Self::Other(rhs, _)) => lhs == rhs,
             _ => false,
         }
@@ -190,17 +432,27 @@ impl Display for Token {
             Token::CloseBracket(_) => write!(f, "]"),
             Token::OpenParen(_) => write!(f, "("),
             Token::CloseParen(_) => write!(f, ")"),
+            Token::Plus(_) => write!(f, "+"),
+            Token::Minus(_) => write!(f, "-"),
+            Token::Caret(_) => write!(f, "^"),
+            Token::Adduct(_) => write!(f, "·"),
             Token::Number(n, _) => write!(f, "#{n}"),
+            Token::Decimal(n, _) => write!(f, "#{n}"),
             Token::Atom(s, _) => write!(f, "a{s}"),
+            Token::Isotope(s, n, _) => write!(f, "i{n}{s}"),
+            Token::Charge(n, _) => write!(f, "c{n}"),
             Token::Other(s, _) => write!(f, "o{s}"),
         }
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The location of one token in a formula. Used for debugging and error reporting
 ///
+/// The location is kept as characters, bytes and a line and column, so it can be used
+/// to index into the formula as a [`str`] as well as to point at it
+///
 /// # Examples
 ///
 /// ```
@@ -210,20 +462,128 @@ impl Display for Token {
 /// let paren = ts.find(|x| x.to_string() == "(").unwrap();
 /// // Token::get_loc returns a poiner so we pass a referance.
 /// assert_eq!(&TokenLoc::new(4, 1), paren.get_loc());
+///
+/// let formula = "H₂O\n+ Xy";
+/// let unknown = TokenStream::new(formula).last().unwrap();
+/// let loc = unknown.get_loc();
+/// assert_eq!((loc.start(), loc.len()), (7, 2));
+/// assert_eq!(&formula[loc.byte_range()], "Xy");
+/// assert_eq!((loc.line(), loc.col()), (2, 3));
 /// ```
 pub struct TokenLoc {
     start: usize,
     len: usize,
+    byte_start: usize,
+    byte_len: usize,
+    line: usize,
+    col: usize,
 }
 
 impl TokenLoc {
     /// Constructs a new tokenloc from a start pos and a length
+    ///
+    /// This assumes everything before the token is on one line and one byte per character,
+    /// use [`locate`](Self::locate) to get the location in any text
     pub fn new(start: usize, len: usize) -> Self {
-        Self { start, len }
+        Self {
+            start,
+            len,
+            byte_start: start.saturating_sub(1),
+            byte_len: len,
+            line: 1,
+            col: start,
+        }
+    }
+
+    /// Gets the location of `len` characters in `text`, starting at the `start`th character
+    /// counting from 1
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::TokenLoc;
+    ///
+    /// let formula = "CuSO₄·5H₂O";
+    /// let loc = TokenLoc::locate(formula, 6, 2);
+    /// assert_eq!(loc.byte_range(), 7..10);
+    /// assert_eq!(&formula[loc.byte_range()], "·5");
+    /// // `new` counts the `₄` as one byte
+    /// assert_ne!(loc, TokenLoc::new(6, 2));
+    /// ```
+    pub fn locate(text: &str, start: usize, len: usize) -> Self {
+        Locator::new(text).locate(start, len)
+    }
+
+    /// Gets the start of the token in characters, counting from 1
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Gets the length of the token in characters
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the location covers no characters
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gets the bytes the token covers, for slicing the text it came from
+    pub fn byte_range(&self) -> Range<usize> {
+        self.byte_start..self.byte_start + self.byte_len
+    }
+
+    /// Gets the line the token starts on, counting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Gets the column the token starts at in characters, counting from 1
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    /// Gets the location covering both this location and `other`, and everything between them
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::TokenLoc;
+    ///
+    /// assert_eq!(TokenLoc::new(5, 2).join(&TokenLoc::new(2, 1)), TokenLoc::new(2, 5));
+    /// ```
+    pub fn join(&self, other: &TokenLoc) -> TokenLoc {
+        let (first, last) = if other.start < self.start {
+            (other, self)
+        } else {
+            (self, other)
+        };
+        let end = (last.start + last.len).max(first.start + first.len);
+        let byte_end = last.byte_range().end.max(first.byte_range().end);
+        TokenLoc {
+            len: end - first.start,
+            byte_len: byte_end - first.byte_start,
+            ..first.clone()
+        }
+    }
+
+    /// Moves the location right past `before`, used when a formula is
+    /// part of a larger string such as an equation
+    pub(crate) fn offset(&mut self, before: &str) {
+        let newlines = before.matches('\n').count();
+        if self.line <= 1 {
+            let last_line = before.rsplit('\n').next().unwrap_or_default();
+            self.col += last_line.chars().count();
+        }
+        self.start += before.chars().count();
+        self.byte_start += before.len();
+        self.line += newlines;
     }
 
     /// Prints a message with the relevant bit of the formula highlighted
     ///
+    /// The line of the formula the token is on is printed, lined up by the width the
+    /// characters take up in a terminal, so wide and combining characters are pointed at correctly
+    ///
     /// # Examples
     ///
     /// ```
@@ -240,17 +600,101 @@ impl TokenLoc {
     /// //            ^^
     /// //            hey look
     /// //            3+4=7
+    ///
+    /// let msg2 = TokenLoc::new(3, 2).format_msg("水H$", "wide", "here");
+    /// assert_eq!("wide: 水H$
+    ///          ^
+    ///          here", msg2);
+    ///
+    /// let msg3 = TokenLoc::new(5, 1).format_msg("H2O\nXy", "lines", "here");
+    /// assert_eq!("lines (line 2): Xy
+    ///                 ^
+    ///                 here", msg3);
+    /// ```
     pub fn format_msg(&self, formula: &str, msg: &str, diag: &str) -> String {
-        let lines = diag.lines().collect::<Vec<_>>();
-        let mut return_msg = format!("{msg}: {formula}");
-        return_msg += &format!(
-            "\n{}{}",
-            " ".repeat(msg.len() + 1 + self.start),
-            "^".repeat(self.len)
-        );
-        for s in lines {
-            return_msg += &format!("\n{}{}", " ".repeat(msg.len() + 1 + self.start), s);
+        let chars = formula.chars().collect::<Vec<_>>();
+        let start = self.start.saturating_sub(1).min(chars.len());
+        let end = (start + self.len).min(chars.len());
+        let line_start = chars[..start]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |i| i + 1);
+        let line_end = chars[start..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(chars.len(), |i| start + i);
+
+        let prefix = if formula.contains('\n') {
+            let line = chars[..start].iter().filter(|c| **c == '\n').count() + 1;
+            format!("{msg} (line {line}): ")
+        } else {
+            format!("{msg}: ")
+        };
+        let width = |chars: &[char]| chars.iter().filter_map(|c| c.width()).sum::<usize>();
+        let indent = " ".repeat(prefix.width() + width(&chars[line_start..start]));
+        // anything is shown with at least one caret, even if it takes up no space
+        let carets =
+            width(&chars[start..end.min(line_end).max(start)]).max(usize::from(self.len > 0));
+
+        let mut return_msg = prefix + &chars[line_start..line_end].iter().collect::<String>();
+        return_msg += &format!("\n{indent}{}", "^".repeat(carets));
+        for s in diag.lines() {
+            return_msg += &format!("\n{indent}{s}");
         }
         return_msg
     }
 }
+
+/// Works out the full [`TokenLoc`] of character locations in a text, which is quickest
+/// when the locations are asked for in order
+pub(crate) struct Locator<'a> {
+    text: &'a str,
+    /// The number of characters before `byte`
+    chars: usize,
+    byte: usize,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Locator<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            chars: 0,
+            byte: 0,
+            line: 1,
+            col: 1,
+        }
+    }
+
+    /// Gets the location of `len` characters starting at the `start`th character
+    pub(crate) fn locate(&mut self, start: usize, len: usize) -> TokenLoc {
+        let before = start.saturating_sub(1);
+        if before < self.chars {
+            *self = Self::new(self.text);
+        }
+        for c in self.text[self.byte..].chars().take(before - self.chars) {
+            self.chars += 1;
+            self.byte += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+
+        // past the end of the text counts as one byte per character
+        let missing = before.saturating_sub(self.chars);
+        let found = self.text[self.byte..].chars().take(len).collect::<Vec<_>>();
+        let byte_len = found.iter().map(|c| c.len_utf8()).sum::<usize>() + len - found.len();
+        TokenLoc {
+            start,
+            len,
+            byte_start: self.byte + missing,
+            byte_len,
+            line: self.line,
+            col: self.col + missing,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    elements::{self, Element},
+    err::StoikError,
+    rational::Rational,
+};
+
+use super::Molecule;
+
+impl Molecule {
+    /// Gets the degree of unsaturation of one of the molecule, which is its number of rings
+    /// plus its number of double bonds, with a triple bond counting as two
+    ///
+    /// This uses the lowest standard valence of each element, see [`Element::valences`].
+    /// The charge is not taken into account, so ions can give a half, like `1.5` for acetate
+    ///
+    /// # Errors
+    ///
+    /// [`StoikError::NoValence`] if an element has no standard valence, like a transition metal,
+    /// [`StoikError::EmptyMolecule`] if there are no atoms, like in an electron,
+    /// and [`StoikError::Overflow`] if the counts are too big, pointing at where the
+    /// element is first written
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    /// use stoik::StoikError;
+    ///
+    /// assert_eq!(Molecule::from_formula("C6H6")?.degree_of_unsaturation()?, 4);
+    /// assert_eq!(Molecule::from_formula("CH3CH2OH")?.degree_of_unsaturation()?, 0);
+    /// assert_eq!(Molecule::from_formula("C5H5N")?.degree_of_unsaturation()?, 4);
+    /// assert_eq!(Molecule::from_formula("2C2H2")?.degree_of_unsaturation()?, 2);
+    ///
+    /// let acetate = Molecule::from_formula("CH3COO-")?;
+    /// assert_eq!(acetate.degree_of_unsaturation()?.to_string(), "1.5");
+    ///
+    /// let err = Molecule::from_formula("Fe2O3")?.degree_of_unsaturation().unwrap_err();
+    /// assert!(matches!(err, StoikError::NoValence(atom) if atom == "Fe"));
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn degree_of_unsaturation(&self) -> Result<Rational, StoikError> {
+        let mut degree = Rational::ONE;
+        for (element, count) in self.valence_counts()? {
+            let valence = i128::from(element.valences()[0]);
+            degree = count
+                .checked_mul(Rational::new(valence - 2, 2))
+                .and_then(|x| x.checked_add(degree))
+                .ok_or_else(|| self.overflow_at(element.symbol))?;
+        }
+        Ok(degree)
+    }
+
+    /// Checks if one of the molecule could be a closed-shell molecule, or ion, with the
+    /// standard valences of its elements, giving a warning if it cannot
+    ///
+    /// A molecule cannot be closed-shell if it has an odd number of bonding electrons, like `CH5`,
+    /// or if there are too few bonds to join every atom together, like `C2H8`. Radicals like `NO2`
+    /// are warned about too. Lone atoms, molecules with elements that have no standard valence
+    /// and molecules with counts that are not whole numbers are not checked
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    /// use stoik::Severity;
+    ///
+    /// let warning = Molecule::from_formula("C2H7")?.check_valence().unwrap();
+    /// assert_eq!(warning.severity, Severity::Warning);
+    /// assert!(Molecule::from_formula("C2H8")?.check_valence().is_some());
+    ///
+    /// for formula in ["CH4", "C2H6", "NH4+", "SO4^2-", "SF6", "Na", "Fe2O3"] {
+    ///     assert!(Molecule::from_formula(formula)?.check_valence().is_none());
+    /// }
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn check_valence(&self) -> Option<Diagnostic> {
+        let (mut atoms, mut lowest, mut highest) = (0i128, 0i128, 0i128);
+        for (element, count) in self.valence_counts().ok()? {
+            if !count.is_integer() {
+                return None;
+            }
+            let valences = element.valences();
+            let bonding = |valence: &u8| count.num().checked_mul(i128::from(*valence));
+            atoms = atoms.checked_add(count.num())?;
+            lowest = lowest.checked_add(bonding(valences.first()?)?)?;
+            highest = highest.checked_add(bonding(valences.last()?)?)?;
+        }
+        if atoms < 2 {
+            return None;
+        }
+
+        // each charge can take one from or give one to the valence of an atom
+        let charge = i128::from(self.charge);
+        let (message, suggestion) = if lowest.checked_add(charge)? % 2 != 0 {
+            (
+                "Odd number of bonding electrons, so this cannot be a closed-shell molecule",
+                "Check the count of each element, or add a charge",
+            )
+        } else if highest.checked_add(charge.abs())? < (atoms - 1).checked_mul(2)? {
+            (
+                "Too few bonds to join every atom together",
+                "Check the count of each element",
+            )
+        } else {
+            return None;
+        };
+        Some(Diagnostic {
+            severity: Severity::Warning,
+            span: None,
+            message: message.to_string(),
+            suggestion: Some(suggestion.to_string()),
+        })
+    }
+
+    /// Gets the count of each element in one of the molecule, with its periodic table data
+    fn valence_counts(&self) -> Result<Vec<(&'static Element, Rational)>, StoikError> {
+        let mut counts: BTreeMap<&str, Rational> = BTreeMap::new();
+        for (isotope, count) in &self.map {
+            let total = counts.entry(&isotope.symbol).or_default();
+            *total = total
+                .checked_add(*count)
+                .ok_or_else(|| self.overflow_at(&isotope.symbol))?;
+        }
+        counts.retain(|_, count| !count.is_zero());
+        if counts.is_empty() {
+            return Err(StoikError::EmptyMolecule);
+        }
+
+        counts
+            .into_iter()
+            .map(|(symbol, count)| {
+                let element = elements::get(symbol)
+                    .filter(|x| !x.valences().is_empty())
+                    .ok_or_else(|| StoikError::NoValence(symbol.to_string()))?;
+                Ok((element, count))
+            })
+            .collect()
+    }
+}
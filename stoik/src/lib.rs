@@ -44,16 +44,37 @@
 //! ```
 #![warn(missing_docs)]
 
+mod diagnostic;
+pub mod elements;
+pub mod equation;
 mod err;
 pub mod formula;
+pub mod nuclides;
+mod rational;
+pub mod render;
+pub mod smiles;
 
+pub use diagnostic::{Diagnostic, Severity};
 pub use err::StoikError;
+pub use rational::Rational;
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
-    use crate::formula::Molecule;
+    use crate::{
+        elements,
+        equation::{Amount, Equation, Medium, Species, ARROWS},
+        formula::{
+            assemble_cst, assemble_cst_recovering, assemble_tree, Abbreviations, Bracket, CstKind,
+            FormulaOrder, InchiFormula, Isotope, MolarMass, Molecule, ParseOptions, State,
+            SyntaxNode, TokenLoc, TokenStream,
+        },
+        nuclides,
+        render::OutputFormat,
+        smiles::{BondOrder, Graph},
+        Diagnostic, Rational, Severity, StoikError,
+    };
 
     #[test]
     fn overall_test() {
@@ -64,17 +85,1184 @@ mod tests {
         assert_eq!(
             mol.get_map(),
             HashMap::from([
-                ("V".to_string(), 30,),
-                ("Fe".to_string(), 150,),
-                ("Mo".to_string(), 60,),
-                ("Cr".to_string(), 30,),
-                ("Mn".to_string(), 5,),
-                ("Co".to_string(), 5,),
-                ("Si".to_string(), 5,),
-                ("O".to_string(), 15,),
-                ("H".to_string(), 30,),
-                ("W".to_string(), 150,),
+                ("V".to_string(), 30.into()),
+                ("Fe".to_string(), 150.into()),
+                ("Mo".to_string(), 60.into()),
+                ("Cr".to_string(), 30.into()),
+                ("Mn".to_string(), 5.into()),
+                ("Co".to_string(), 5.into()),
+                ("Si".to_string(), 5.into()),
+                ("O".to_string(), 15.into()),
+                ("H".to_string(), 30.into()),
+                ("W".to_string(), 150.into()),
             ])
         );
     }
+
+    #[test]
+    fn equation_test() {
+        let equation = "2H2 + O2 -> 2H2O".parse::<Equation>().unwrap();
+        assert_eq!(equation.reactants.len(), 2);
+        assert_eq!(equation.reactants[0].molecule.moles, 2);
+        assert_eq!(equation.products[0].formula, "2H2O");
+        assert_eq!(equation.to_string(), "2H2 + O2 -> 2H2O");
+        assert_eq!(equation.to_string().parse::<Equation>().unwrap(), equation);
+        assert_eq!(equation.reactants[1].loc, TokenLoc::new(7, 2));
+        assert_eq!(equation.products[0].loc, TokenLoc::new(13, 4));
+
+        // errors point at where they are in the whole equation, not just their formula
+        let cases = [
+            ("H2 + O2$ -> H2O", TokenLoc::new(8, 1)),
+            ("H2 + (O2 -> H2O", TokenLoc::new(6, 1)),
+            ("H2 + O2 -> H2O]", TokenLoc::new(15, 1)),
+            ("H2 +   (2O)2 -> H2O", TokenLoc::new(9, 1)),
+            (
+                "H2 + O2 => H2O + H99999999999999999999",
+                TokenLoc::new(19, 20),
+            ),
+            ("H2 + O2 -> + H2O", TokenLoc::new(11, 1)),
+        ];
+        for (input, loc) in cases {
+            let err = input.parse::<Equation>().unwrap_err();
+            assert_eq!(err.get_loc(), Some(&loc), "{input}");
+        }
+        assert!(matches!(
+            "H2 + O2 H2O".parse::<Equation>(),
+            Err(StoikError::MissingArrow)
+        ));
+    }
+
+    #[test]
+    fn element_test() {
+        let oxygen = elements::get("O").unwrap();
+        assert_eq!((oxygen.name, oxygen.atomic_number), ("Oxygen", 8));
+        assert!(elements::get("Xy").is_none());
+
+        // unknown elements are only an error in strict mode
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(Molecule::from_formula("Xy3").is_ok());
+        for (formula, loc) in [
+            ("Xy3", TokenLoc::new(1, 2)),
+            ("Hh2O", TokenLoc::new(1, 2)),
+            ("2H2(SO4)3Qq", TokenLoc::new(10, 2)),
+            ("CuSO4·5Hh2O", TokenLoc::locate("CuSO4·5Hh2O", 8, 2)),
+        ] {
+            let err = Molecule::from_formula_with(formula, &strict).unwrap_err();
+            assert!(
+                matches!(&err, StoikError::UnknownElement(x) if *x == loc),
+                "{formula}: {err:?}"
+            );
+        }
+        let err = Equation::parse_with("H2 + Oo2 -> H2O", &strict).unwrap_err();
+        assert!(matches!(err, StoikError::UnknownElement(loc) if loc == TokenLoc::new(6, 2)));
+
+        // the suggestions read the letters as separate elements, or as a close element
+        let diagnostic = Diagnostic::from_error(
+            &Molecule::from_formula_with("Coo2", &strict).unwrap_err(),
+            "Coo2",
+        );
+        assert_eq!(diagnostic.message, "Unknown element");
+        assert_eq!(diagnostic.span, Some(TokenLoc::new(1, 3)));
+        assert_eq!(
+            diagnostic.suggestion.as_deref(),
+            Some("Did you mean COO, Co")
+        );
+        let diagnostics = Molecule::diagnose("Hh2Xy", &strict);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics[0]
+            .suggestion
+            .as_ref()
+            .unwrap()
+            .starts_with("Did you mean HH, H, He,"));
+        assert_eq!(diagnostics[1].span, Some(TokenLoc::new(4, 2)));
+        assert_eq!(
+            diagnostics[1].suggestion.as_deref(),
+            Some("Did you mean Xe, Dy")
+        );
+    }
+
+    #[test]
+    fn charge_test() {
+        let ferrocyanide = Molecule::from_formula("[Fe(CN)6]4-").unwrap();
+        assert_eq!(ferrocyanide.charge, -4);
+        assert_eq!(ferrocyanide.get_count("C"), 6);
+        assert_eq!(ferrocyanide.get_count("N"), 6);
+        assert_eq!(ferrocyanide.get_count("Fe"), 1);
+
+        for formula in ["Fe+3", "Fe3+", "Fe^3+", "Fe^+3", "Fe+++"] {
+            let iron = Molecule::from_formula(formula).unwrap();
+            assert_eq!(iron.charge, 3, "{formula}");
+            assert_eq!(iron.get_count("Fe"), 1);
+        }
+        assert_eq!(Molecule::from_formula("SO4^2-").unwrap().charge, -2);
+        assert_eq!(Molecule::from_formula("2Na+").unwrap().total_charge(), 2);
+
+        // the `+` of a charge is not taken as a separator
+        let equation = "Fe+3 + e- -> Fe+2".parse::<Equation>().unwrap();
+        assert_eq!(equation.reactants.len(), 2);
+        assert_eq!(equation.reactants[1].molecule.charge, -1);
+        assert!(equation.is_balanced());
+
+        // the charge imbalance is reported apart from the elements
+        let equation = "Fe3+ -> Fe2+".parse::<Equation>().unwrap();
+        assert!(equation.element_balance().values().all(|x| *x));
+        assert_eq!(
+            (equation.reactant_charge(), equation.product_charge()),
+            (3, 2)
+        );
+        assert!(!equation.is_charge_balanced());
+        assert!(!equation.is_balanced());
+        let equation = "Ag+ + Cl- -> AgCl + e-".parse::<Equation>().unwrap();
+        assert!(equation.element_balance().values().all(|x| *x));
+        assert_eq!(
+            (equation.reactant_charge(), equation.product_charge()),
+            (0, -1)
+        );
+        assert!(matches!(equation.balance(), Err(StoikError::Unbalanceable)));
+    }
+
+    #[test]
+    fn adduct_test() {
+        for formula in ["CuSO4·5H2O", "CuSO4.5H2O", "CuSO4*5H2O", "2CuSO4·5H2O"] {
+            let mol = Molecule::from_formula(formula).unwrap();
+            assert_eq!(mol.get_count("Cu"), mol.moles);
+            assert_eq!(mol.get_count("H"), 10 * mol.moles);
+            assert_eq!(mol.get_count("O"), 9 * mol.moles);
+        }
+        let mol = Molecule::from_formula("Na2CO3·(NaHCO3)2·2H2O").unwrap();
+        assert_eq!(mol.get_count("Na"), 4);
+        assert_eq!(mol.get_count("H"), 6);
+
+        assert!(matches!(
+            Molecule::from_formula("CuSO4·"),
+            Err(StoikError::InvalidToken(_))
+        ));
+        assert!(matches!(
+            Molecule::from_formula("CuSO4··H2O"),
+            Err(StoikError::InvalidToken(_))
+        ));
+
+        let equation = "CuSO4·5H2O -> CuSO4 + H2O".parse::<Equation>().unwrap();
+        assert_eq!(
+            equation.balance().unwrap().to_string(),
+            "CuSO4·5H2O -> CuSO4 + 5H2O"
+        );
+    }
+
+    #[test]
+    fn isotope_test() {
+        let labelled = Molecule::from_formula("[13C]H4").unwrap();
+        assert_eq!(labelled, Molecule::from_formula("^13CH4").unwrap());
+        assert_eq!(labelled.get_count("C"), 1);
+        assert_eq!(labelled.get_isotope_count(&Isotope::new("C", None)), 0);
+        assert_eq!(labelled.to_string(), "[13C]H4");
+
+        let heavy = Molecule::from_formula("D2O").unwrap();
+        assert_eq!(heavy, Molecule::from_formula("[2H]2O").unwrap());
+        assert_eq!(
+            heavy.get_map(),
+            Molecule::from_formula("H2O").unwrap().get_map()
+        );
+        assert_ne!(heavy, Molecule::from_formula("H2O").unwrap());
+        assert_eq!(
+            Molecule::from_formula("T2").unwrap().get_isotope_map(),
+            HashMap::from([(Isotope::new("H", Some(3)), 2.into())])
+        );
+
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(Molecule::from_formula_with("D2O", &strict).is_ok());
+        assert!(matches!(
+            Molecule::from_formula_with("[13Xy]", &strict),
+            Err(StoikError::UnknownElement(_))
+        ));
+
+        let equation = "D2 + O2 -> H2O".parse::<Equation>().unwrap();
+        assert!(equation.balance().unwrap().is_balanced());
+    }
+
+    #[test]
+    fn state_test() {
+        let equation = "2H2(g) + O2(g) -> 2H2O(l)".parse::<Equation>().unwrap();
+        assert!(equation.is_balanced());
+        assert_eq!(equation.reactants[0].molecule.state, Some(State::Gas));
+        assert_eq!(equation.products[0].molecule.state, Some(State::Liquid));
+        assert_eq!(equation.product_map().get("l"), None);
+
+        let equation = "Fe3+(aq) + e- -> Fe2+(aq)".parse::<Equation>().unwrap();
+        assert_eq!(equation.reactants.len(), 2);
+        assert!(equation.is_balanced());
+
+        let salt = Molecule::from_formula("CuSO4·5H2O(s)").unwrap();
+        assert_eq!(salt.state, Some(State::Solid));
+        assert_eq!(salt.get_count("H"), 10);
+        assert_eq!(
+            Molecule::from_formula("NaCl(aq)").unwrap().to_string(),
+            "ClNa(aq)"
+        );
+        assert!(Molecule::from_formula("(aq)").is_err());
+
+        let equation = "Na(s) + H2O(l) -> NaOH(aq) + H2(g)"
+            .parse::<Equation>()
+            .unwrap();
+        assert_eq!(
+            equation.balance().unwrap().to_string(),
+            "2Na(s) + 2H2O(l) -> 2NaOH(aq) + H2(g)"
+        );
+    }
+
+    #[test]
+    fn redox_test() {
+        let cases = [
+            (
+                "Cr2O7^2- + Fe2+ -> Cr3+ + Fe3+",
+                Medium::Acidic,
+                "Cr2O7^2- + 6Fe2+ + 14H+ -> 2Cr3+ + 6Fe3+ + 7H2O",
+            ),
+            (
+                "Cu + NO3- -> Cu2+ + NO",
+                Medium::Acidic,
+                "3Cu + 2NO3- + 8H+ -> 3Cu2+ + 2NO + 4H2O",
+            ),
+            (
+                "MnO4- + Fe2+ + H+ -> Mn2+ + Fe3+ + H2O(l)",
+                Medium::Acidic,
+                "MnO4- + 5Fe2+ + 8H+ -> Mn2+ + 5Fe3+ + 4H2O",
+            ),
+            (
+                "Cr(OH)3 + ClO3- -> CrO4^2- + Cl-",
+                Medium::Basic,
+                "2Cr(OH)3 + ClO3- + 4OH- -> 2CrO4^2- + Cl- + 5H2O",
+            ),
+            // disproportionation and comproportionation
+            (
+                "Cl2 -> Cl- + ClO3-",
+                Medium::Acidic,
+                "3Cl2 + 3H2O -> 5Cl- + ClO3- + 6H+",
+            ),
+            ("Ag + Ag2+ -> Ag+", Medium::Acidic, "Ag + Ag2+ -> 2Ag+"),
+            (
+                "MnO4- + Mn2+ -> MnO2",
+                Medium::Basic,
+                "2MnO4- + 3Mn2+ + 4OH- -> 5MnO2 + 2H2O",
+            ),
+        ];
+        for (input, medium, output) in cases {
+            let redox = input
+                .parse::<Equation>()
+                .unwrap()
+                .balance_redox(medium)
+                .unwrap();
+            assert_eq!(redox.overall.to_string(), output);
+            assert!(redox.overall.is_balanced());
+            assert!(redox.oxidation.is_balanced());
+            assert!(redox.reduction.is_balanced());
+        }
+
+        let not_redox = "NaOH + HCl -> NaCl + H2O".parse::<Equation>().unwrap();
+        assert!(matches!(
+            not_redox.balance_redox(Medium::Acidic),
+            Err(StoikError::NotRedox)
+        ));
+        let peroxide = "H2O2 -> H2O + O2".parse::<Equation>().unwrap();
+        assert!(matches!(
+            peroxide.balance_redox(Medium::Acidic),
+            Err(StoikError::Disproportionation)
+        ));
+    }
+
+    #[test]
+    fn yield_test() {
+        let equation = "Fe2O3 + 3CO -> 2Fe + 3CO2".parse::<Equation>().unwrap();
+        let yields = equation
+            .reaction_yield(&[Amount::Grams(1000.0), Amount::Moles(30.0)])
+            .unwrap();
+        assert_eq!(yields.limiting, 0);
+        let iron = yields.products[0];
+        assert!((iron.theoretical.moles - 12.524).abs() < 0.001);
+        assert!((iron.theoretical.grams - 699.4).abs() < 0.1);
+        assert!((iron.percent_yield(Amount::Grams(600.0)).unwrap() - 85.79).abs() < 0.01);
+        assert!((yields.reactants[1].left.moles - 11.214).abs() < 0.001);
+        assert_eq!(yields.reactants[0].left.moles, 0.0);
+
+        let unbalanced = "Fe2O3 + CO -> Fe + CO2".parse::<Equation>().unwrap();
+        assert!(matches!(
+            unbalanced.reaction_yield(&[Amount::Moles(1.0), Amount::Moles(1.0)]),
+            Err(StoikError::NotBalanced)
+        ));
+        assert!(matches!(
+            equation.reaction_yield(&[Amount::Moles(1.0)]),
+            Err(StoikError::InvalidInput(_))
+        ));
+
+        // nothing can be made without one of the reactants
+        let yields = equation
+            .reaction_yield(&[Amount::Grams(0.0), Amount::Moles(30.0)])
+            .unwrap();
+        assert_eq!(yields.products[0].percent_yield(Amount::Grams(1.0)), None);
+        let electrons = "Fe^3+ + e- -> Fe^2+".parse::<Equation>().unwrap();
+        let yields = electrons
+            .reaction_yield(&[Amount::Moles(1.0), Amount::Moles(1.0)])
+            .unwrap();
+        assert_eq!(
+            yields.products[0].percent_yield(Amount::Moles(0.5)),
+            Some(50.0)
+        );
+        // electrons have a mass, so can be given in grams
+        let yields = electrons
+            .reaction_yield(&[Amount::Moles(1.0), Amount::Grams(nuclides::ELECTRON_MASS)])
+            .unwrap();
+        assert!((yields.products[0].theoretical.moles - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn composition_test() {
+        // the composition is of one formula unit, however many moles there are
+        for formula in ["H2O", "2H2O", "0H2O", "H2^18O"] {
+            let composition = Molecule::from_formula(formula)
+                .unwrap()
+                .mass_composition()
+                .unwrap();
+            assert_eq!(composition.len(), 2);
+            assert_eq!(composition[0].element, "H");
+            assert_eq!(composition[0].count, 2);
+            assert_eq!(composition[1].count, 1);
+            assert!((composition[1].atom_fraction - 1.0 / 3.0).abs() < 1e-12);
+            let total = composition.iter().map(|x| x.mass_percent).sum::<f64>();
+            assert!((total - 100.0).abs() < 1e-9);
+        }
+        let heavy = Molecule::from_formula("H2^18O")
+            .unwrap()
+            .mass_composition()
+            .unwrap();
+        assert!(heavy[1].mass_percent > 89.0);
+
+        for formula in ["e-", "2e-", "H0"] {
+            assert!(matches!(
+                Molecule::from_formula(formula).unwrap().mass_composition(),
+                Err(StoikError::EmptyMolecule)
+            ));
+        }
+        assert!(matches!(
+            Molecule::from_formula("Xy").unwrap().mass_composition(),
+            Err(StoikError::NoAtomicWeight(_))
+        ));
+    }
+
+    #[test]
+    fn empirical_test() {
+        let formula = |masses: &[(&str, f64)]| Molecule::empirical_formula(masses).unwrap();
+        assert_eq!(
+            formula(&[("C", 120.11), ("H", 21.168)]),
+            Molecule::from_formula("C10H21").unwrap()
+        );
+        assert_eq!(
+            formula(&[("C", 85.63), ("H", 14.37)]),
+            Molecule::from_formula("CH2").unwrap()
+        );
+        assert_eq!(
+            formula(&[("Fe", 69.94), ("O", 30.06)]),
+            Molecule::from_formula("Fe2O3").unwrap()
+        );
+        // ethyl acetate, with rounded percentages
+        assert_eq!(
+            formula(&[("C", 54.5), ("H", 9.2), ("O", 36.3)]),
+            Molecule::from_formula("C2H4O").unwrap()
+        );
+
+        // eicosane
+        let masses = [("C", 240.22), ("H", 42.34)];
+        let eicosane = Molecule::molecular_formula(&masses, 282.55).unwrap();
+        assert_eq!(eicosane, Molecule::from_formula("C20H42").unwrap());
+        assert!(matches!(
+            Molecule::molecular_formula(&masses, 200.0),
+            Err(StoikError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            Molecule::molecular_formula(&masses, 100.0),
+            Err(StoikError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            Molecule::empirical_formula(&[("C", 1.0), ("H", 0.0)]),
+            Err(StoikError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn balance_test() {
+        let cases = [
+            ("H2 + O2 -> H2O", "2H2 + O2 -> 2H2O"),
+            (
+                "KNO3 + C12H22O11 -> N2 + CO2 + H2O + K2CO3",
+                "48KNO3 + 5C12H22O11 -> 24N2 + 36CO2 + 55H2O + 24K2CO3",
+            ),
+            (
+                "Cu + HNO3 -> Cu(NO3)2 + NO + H2O",
+                "3Cu + 8HNO3 -> 3Cu(NO3)2 + 2NO + 4H2O",
+            ),
+            ("5 H2O -> H2 + O2", "2H2O -> 2H2 + O2"),
+            (
+                "MnO4- + Fe2+ + H+ -> Mn2+ + Fe3+ + H2O",
+                "MnO4- + 5Fe2+ + 8H+ -> Mn2+ + 5Fe3+ + 4H2O",
+            ),
+            ("Fe3+ + e- -> Fe", "Fe3+ + 3e- -> Fe"),
+        ];
+        for (input, output) in cases {
+            let equation = input.parse::<Equation>().unwrap().balance().unwrap();
+            assert_eq!(equation.to_string(), output);
+            assert!(equation.is_balanced());
+        }
+
+        let ambiguous = "H2 + O2 -> H2O + H2O2".parse::<Equation>().unwrap();
+        assert!(matches!(
+            ambiguous.balance(),
+            Err(StoikError::AmbiguousBalance)
+        ));
+        let impossible = "H2 + O2 -> NaCl".parse::<Equation>().unwrap();
+        assert!(matches!(
+            impossible.balance(),
+            Err(StoikError::Unbalanceable)
+        ));
+    }
+
+    #[test]
+    fn decimal_test() {
+        let mol = Molecule::from_formula("LiNi0.8Co0.15Al0.05O2").unwrap();
+        assert_eq!(mol.get_count("Ni"), Rational::new(4, 5));
+        assert_eq!(mol.get_count("Co"), Rational::new(3, 20));
+        assert_eq!(mol.get_count("Al"), Rational::new(1, 20));
+        assert_eq!(mol.to_string(), "Al0.05Co0.15LiNi0.8O2");
+
+        let mol = Molecule::from_formula("2(Fe0.5)3O").unwrap();
+        assert_eq!(mol.get_count("Fe"), 3);
+
+        // a `.` before an atom is an adduct unless the number before it is 0
+        let hydrate = Molecule::from_formula("CuSO4.5H2O").unwrap();
+        assert_eq!(hydrate.get_count("H"), 10);
+        assert!(matches!(
+            Molecule::from_formula("0.5H2O"),
+            Err(StoikError::NumberFirst(_))
+        ));
+
+        let equation = "2Fe0.95O + 0.05O2 -> Fe2O3".parse::<Equation>();
+        assert!(equation.is_err());
+        let equation = "20Fe0.95O + 7O2 -> 19Fe2O3".parse::<Equation>().unwrap();
+        assert!(!equation.is_balanced());
+        let balanced = "Fe0.95O + O2 -> Fe2O3"
+            .parse::<Equation>()
+            .unwrap()
+            .balance()
+            .unwrap();
+        assert_eq!(balanced.to_string(), "80Fe0.95O + 17O2 -> 38Fe2O3");
+        assert!(balanced.is_balanced());
+    }
+
+    #[test]
+    fn overflow_test() {
+        let err = Molecule::from_formula("H99999999999999999999").unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(2, 20)));
+        let err =
+            Molecule::from_formula("Fe0.000000000000000000000000000000000000001").unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(3, 41)));
+
+        // each multiplier fits, but not once they are multiplied together
+        let formula = "((H9223372036854775807)9223372036854775807)2";
+        let err = Molecule::from_formula(formula).unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(24, 19)));
+        let err = Molecule::from_formula("9223372036854775807H2").unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(21, 1)));
+        // or the total of an element is too big
+        let err = Molecule::from_formula("H9223372036854775807H").unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(21, 1)));
+        let formula = "CuSO4·9223372036854775807H2O";
+        let err = Molecule::diagnose(formula, &ParseOptions::default());
+        assert_eq!(err[0].span, Some(TokenLoc::locate(formula, 27, 1)));
+
+        // a charge that is too big is pointed at, however it is written
+        for (formula, start) in [
+            ("Fe^99999999999999999999+", 4),
+            ("Fe99999999999999999999+", 3),
+            ("Fe+99999999999999999999", 4),
+            ("Fe^+99999999999999999999", 5),
+        ] {
+            let err = Molecule::from_formula(formula).unwrap_err();
+            assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(start, 20)));
+        }
+        let err = "H2 + Fe^99999999999999999999+ -> H2"
+            .parse::<Equation>()
+            .unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(9, 20)));
+
+        // errors from a molecule point at the element that overflowed
+        let formula = "CH4·Sn100000";
+        let molecule = Molecule::from_formula(formula).unwrap();
+        let err = molecule.isotopic_envelope(0.001).unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::locate(formula, 5, 2)));
+        let equation = "H2 + CH4·Sn100000 -> H2".parse::<Equation>().unwrap();
+        let err = equation.reactants[1]
+            .molecule
+            .isotopic_envelope(0.001)
+            .unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc.start() == 10 && loc.len() == 2));
+
+        // the error is moved to where the species is in the equation
+        let err = "H2 + O99999999999999999999 -> H2O"
+            .parse::<Equation>()
+            .unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(7, 20)));
+
+        let equation = "H4611686018427387904 + O4611686018427387903 -> H2O"
+            .parse::<Equation>()
+            .unwrap();
+        let err = equation.balance().unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(1, 50)));
+
+        // the charge fits, but not once it is multiplied by the moles
+        let err = "2Fe^9223372036854775807+ -> 2Fe^9223372036854775807+"
+            .parse::<Equation>()
+            .unwrap_err();
+        assert!(matches!(err, StoikError::Overflow(loc) if loc == TokenLoc::new(4, 21)));
+        let equation = "Fe^9223372036854775807+ + Fe^9223372036854775807+ -> Fe2"
+            .parse::<Equation>()
+            .unwrap();
+        assert_eq!(equation.reactant_charge(), 2 * i128::from(i64::MAX));
+        assert!(!equation.is_balanced());
+        let equation = "Fe^9223372036854775807+ -> Fe^9223372036854775807+"
+            .parse::<Equation>()
+            .unwrap();
+        assert!(equation.is_balanced());
+
+        // the operators saturate instead of panicking, and comparing never overflows
+        let max = Rational::new(i128::MAX, 1);
+        let big = Rational::from(i64::MAX) * Rational::from(i64::MAX);
+        assert_eq!(big * big, max);
+        assert_eq!(-big * big, -max);
+        assert_eq!(max + Rational::ONE, max);
+        assert_eq!(-max - max, -max);
+        assert_eq!(Rational::ONE / Rational::ZERO, max);
+        assert_eq!(Rational::ZERO / Rational::ZERO, Rational::ZERO);
+        let tiny = Rational::new(1, i128::MAX) * Rational::new(1, i128::MAX - 1);
+        assert_eq!(tiny, Rational::ZERO);
+        let third = Rational::new(1, 3) + Rational::new(1, i128::MAX);
+        assert!((third.to_f64() - 1.0 / 3.0).abs() < 1e-15);
+        assert!(
+            Rational::new(i128::MAX, i128::MAX - 1) < Rational::new(i128::MAX - 1, i128::MAX - 2)
+        );
+        assert!(
+            Rational::new(i128::MAX - 1, i128::MAX) > Rational::new(i128::MAX - 2, i128::MAX - 1)
+        );
+        assert!(Rational::new(-i128::MAX, 3) < Rational::new(-i128::MAX + 1, 3));
+        assert!(max > big && -max < -big);
+        let mut total = max;
+        total += max;
+        assert_eq!(total, max);
+        assert_eq!(
+            [max, max, -Rational::ONE].into_iter().sum::<Rational>(),
+            max - Rational::ONE
+        );
+
+        // a molecule built up by hand saturates too
+        let mut molecule = Molecule::from_formula("H").unwrap();
+        molecule.increase_atom("H", i64::MAX);
+        molecule.increase_atom("H", max);
+        assert_eq!(molecule.get_count("H"), max);
+        assert!(matches!(
+            molecule.mass_composition(),
+            Ok(composition) if composition[0].count == max
+        ));
+        molecule.increase_atom("O", max);
+        assert!(matches!(
+            molecule.mass_composition(),
+            Err(StoikError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn cst_test() {
+        for formula in [
+            " 2 (H2O)3·[13C]O2 ",
+            "CuSO4 . 5 H2O",
+            "[Fe(CN)6]4-",
+            "Fe ^+3 (aq)",
+            "e-",
+        ] {
+            let cst = assemble_cst(formula).unwrap();
+            assert_eq!(cst.to_string(), formula);
+            assert_eq!(
+                cst.span(),
+                &TokenLoc::locate(formula, 1, formula.chars().count())
+            );
+            assert_eq!(
+                SyntaxNode::from(&cst),
+                assemble_tree(TokenStream::new(formula)).unwrap()
+            );
+        }
+
+        let cst = assemble_cst("K3[Fe(CN)6]").unwrap();
+        let square = cst.node_at(3).unwrap();
+        assert_eq!(square.kind(), &CstKind::Group(Bracket::Square));
+        assert_eq!(square.span(), &TokenLoc::new(3, 9));
+        let paren = cst.node_at(6).unwrap();
+        assert_eq!(paren.kind(), &CstKind::Group(Bracket::Paren));
+        assert_eq!(paren.to_string(), "(CN)");
+        assert_eq!(cst.node_at(12), None);
+
+        let err = assemble_cst("H2O)").unwrap_err();
+        assert!(matches!(err, StoikError::UnpairedParenthesis(loc) if loc == TokenLoc::new(4, 1)));
+    }
+
+    #[test]
+    fn diagnostic_test() {
+        let options = ParseOptions::default();
+        let formula = "(5H2O$)3·[Fe";
+        let diagnostics = Molecule::diagnose(formula, &options);
+        let spans = diagnostics
+            .iter()
+            .map(|x| x.span.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                TokenLoc::new(2, 1),
+                TokenLoc::new(6, 1),
+                TokenLoc::locate(formula, 10, 1)
+            ]
+        );
+        assert!(diagnostics.iter().all(|x| x.severity == Severity::Error));
+        assert_eq!(
+            diagnostics[2].suggestion.as_deref(),
+            Some("Add a `]` to close it")
+        );
+
+        // the recovered tree still prints back to the formula
+        let (cst, errors) = assemble_cst_recovering(formula);
+        assert_eq!(cst.to_string(), formula);
+        assert_eq!(errors.len(), 3);
+
+        let equation = "H2 + O2 => H2O";
+        assert!(Equation::diagnose(equation, &options).is_empty());
+        let diagnostics = Equation::diagnose("H2 + O2 H2O", &options);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, None);
+
+        // only empty species next to a `+` suggest removing it
+        for (equation, suggestion) in [
+            ("H2 + -> H2", "Add a species, or remove the extra `+`"),
+            ("+ H2 -> H2", "Add a species, or remove the extra `+`"),
+            ("H2 -> H2 +", "Add a species, or remove the extra `+`"),
+            ("Na+ + -> Na+", "Add a species, or remove the extra `+`"),
+            ("-> H2", "Add a reactant before the arrow"),
+            ("  ⇌ H2", "Add a reactant before the arrow"),
+            ("H2 ->", "Add a product after the arrow"),
+            ("Na+ -> ", "Add a product after the arrow"),
+        ] {
+            let diagnostics = Equation::diagnose(equation, &options);
+            assert_eq!(diagnostics.len(), 1, "{equation}");
+            assert_eq!(diagnostics[0].suggestion.as_deref(), Some(suggestion));
+        }
+        let diagnostics = Species::diagnose(" ", &options);
+        assert_eq!(diagnostics[0].suggestion.as_deref(), Some("Add a species"));
+    }
+
+    #[test]
+    fn loc_test() {
+        let equation = "CuSO4·5H2O +\n Fe -> Cu + FeSO4 + Xy";
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let err = Equation::parse_with(equation, &strict).unwrap_err();
+        let loc = err.get_loc().unwrap();
+        assert_eq!(&equation[loc.byte_range()], "Xy");
+        assert_eq!((loc.line(), loc.col()), (2, 21));
+        assert_eq!(loc.start(), 34);
+
+        let equation = "水 + Fe\n -> Fe";
+        let parsed = equation.parse::<Equation>();
+        let loc = parsed.unwrap_err().get_loc().unwrap().clone();
+        assert_eq!(&equation[loc.byte_range()], "水");
+
+        let parsed = "H2 +\n  O2 -> H2O".parse::<Equation>().unwrap();
+        let loc = &parsed.reactants[1].loc;
+        assert_eq!((loc.line(), loc.col(), loc.start()), (2, 3, 8));
+        assert_eq!(
+            loc.format_msg("H2 +\n  O2 -> H2O", "Here", "oxygen"),
+            "Here (line 2):   O2 -> H2O\n                 ^^\n                 oxygen"
+        );
+    }
+
+    #[test]
+    fn unicode_test() {
+        let pasted = Molecule::from_formula("H₂SO₄").unwrap();
+        assert_eq!(pasted, Molecule::from_formula("H2SO4").unwrap());
+
+        let ion = Molecule::from_formula("Fe³⁺").unwrap();
+        assert_eq!(ion.charge, 3);
+        assert_eq!(Molecule::from_formula("SO₄²⁻").unwrap().charge, -2);
+        assert_eq!(Molecule::from_formula("NH₄⁺").unwrap().charge, 1);
+        assert_eq!(Molecule::from_formula("Fe⁺⁺").unwrap().charge, 2);
+        assert_eq!(
+            Molecule::from_formula("¹³CH₄").unwrap(),
+            Molecule::from_formula("[13C]H4").unwrap()
+        );
+
+        for arrow in ARROWS {
+            let equation = format!("2H₂ + O₂ {arrow} 2H₂O");
+            assert!(equation.parse::<Equation>().unwrap().is_balanced());
+        }
+        let redox = "Fe³⁺ + e⁻ ⇌ Fe²⁺".parse::<Equation>().unwrap();
+        assert!(redox.is_charge_balanced());
+        assert_eq!(redox.to_string(), "Fe³⁺ + e⁻ ⇌ Fe²⁺");
+
+        // the kind of arrow is kept when balancing and rendering
+        let equation = "H2 + I2 ⇄ HI".parse::<Equation>().unwrap();
+        let balanced = equation.balance().unwrap();
+        assert_eq!(balanced.to_string(), "H2 + I2 ⇄ 2HI");
+        assert_eq!(
+            balanced.products[0].loc,
+            TokenLoc::locate("H2 + I2 ⇄ 2HI", 11, 3)
+        );
+        assert_eq!(
+            balanced.render(OutputFormat::Mhchem).unwrap(),
+            r"\ce{H2 + I2 <--> 2HI}"
+        );
+        let equation = "2NO2 ⇌ N2O4".parse::<Equation>().unwrap();
+        assert_eq!(
+            equation.render(OutputFormat::Latex).unwrap(),
+            r"$\mathrm{2NO_{2} \rightleftharpoons N_{2}O_{4}}$"
+        );
+        let redox = "MnO4- + Fe2+ ⇌ Mn2+ + Fe3+".parse::<Equation>().unwrap();
+        let redox = redox.balance_redox(Medium::Acidic).unwrap();
+        assert_eq!(redox.oxidation.to_string(), "Fe2+ ⇌ Fe3+ + e-");
+
+        let err = Molecule::from_formula("Fe²⁺⁻").unwrap_err();
+        assert_eq!(err.get_loc(), Some(&TokenLoc::locate("Fe²⁺⁻", 3, 3)));
+    }
+
+    #[test]
+    fn render_test() {
+        // only multipliers are subscripts, not the coefficients of later species
+        let equation = "2Fe + 3 Cl2 -> 2FeCl3".parse::<Equation>().unwrap();
+        assert_eq!(
+            equation.render(OutputFormat::Unicode).unwrap(),
+            "2Fe + 3Cl₂ → 2FeCl₃"
+        );
+        assert_eq!(
+            equation.render(OutputFormat::Latex).unwrap(),
+            r"$\mathrm{2Fe + 3Cl_{2} \rightarrow 2FeCl_{3}}$"
+        );
+
+        assert_eq!(
+            OutputFormat::Mhchem.render("Fe0.95O").unwrap(),
+            r"\ce{Fe_{0.95}O}"
+        );
+        assert_eq!(OutputFormat::Unicode.render("e-").unwrap(), "e⁻");
+        assert_eq!(
+            OutputFormat::Html.render("CuSO4.5H2O(s)").unwrap(),
+            "CuSO<sub>4</sub>·5H<sub>2</sub>O(s)"
+        );
+        assert_eq!(
+            OutputFormat::MathMl.render("Na+").unwrap(),
+            "<math><mrow><msup><mrow><mi mathvariant=\"normal\">Na</mi></mrow>\
+             <mrow><mo>+</mo></mrow></msup></mrow></math>"
+        );
+
+        // skipped tokens are kept, but escaped
+        let (cst, _) = assemble_cst_recovering("H2<O");
+        assert_eq!(cst.render(OutputFormat::Html), "H<sub>2</sub>&lt;O");
+
+        for format in OutputFormat::ALL {
+            assert_eq!(format.to_string().parse::<OutputFormat>().unwrap(), format);
+        }
+        assert!("docx".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn abbreviation_test() {
+        let mut options = ParseOptions {
+            strict: true,
+            abbreviations: Some(Abbreviations::default()),
+        };
+        let ester = Molecule::from_formula_with("PhCO2Me", &options).unwrap();
+        assert_eq!(ester, Molecule::from_formula("C6H5CO2CH3").unwrap());
+        assert!(Molecule::diagnose("BnOTf", &options).is_empty());
+        let err = Molecule::from_formula_with("PhTf", &options).unwrap_err();
+        assert!(matches!(err, StoikError::UnknownElement(loc) if loc == TokenLoc::new(3, 2)));
+
+        // without abbreviations `Ac` is actinium again
+        assert_eq!(Molecule::from_formula("AcCl3").unwrap().get_count("Ac"), 1);
+
+        let balanced = Equation::parse_with("2MeOH + 3O2 -> 2CO2 + 4H2O", &options).unwrap();
+        assert!(balanced.is_balanced());
+        // balancing keeps the abbreviations the species were read with
+        let equation = Equation::parse_with("MeOH + O2 -> CO2 + H2O", &options).unwrap();
+        let balanced = equation.balance().unwrap();
+        assert!(balanced.is_balanced());
+        assert_eq!(balanced.to_string(), "2MeOH + 3O2 -> 2CO2 + 4H2O");
+        assert_eq!(balanced.products[1].loc, TokenLoc::new(23, 4));
+        let equation = Equation::parse_with("MeOH + MnO4- -> HCO2H + Mn2+", &options).unwrap();
+        let redox = equation.balance_redox(Medium::Acidic).unwrap();
+        assert!(redox.overall.is_balanced());
+        assert_eq!(redox.oxidation.reactants[0].formula, "MeOH");
+
+        if let Some(abbreviations) = &mut options.abbreviations {
+            abbreviations.keep = true;
+        }
+        let bistriflate = Molecule::from_formula_with("Me2Si(OTf)2", &options).unwrap();
+        let written = bistriflate.to_string_with(FormulaOrder::Written);
+        assert_eq!(written, "Me2(OTf)2Si");
+        assert_eq!(
+            Molecule::from_formula_with(&written, &options).unwrap(),
+            bistriflate
+        );
+
+        let mut abbreviations = Abbreviations::empty();
+        assert!(abbreviations.insert("tBu", "C(CH3)3").is_err());
+        assert!(abbreviations.insert("Tbs", "Si(CH3)2C(CH3)3)").is_err());
+        assert_eq!(abbreviations.insert("Pr", "C3H7").unwrap(), None);
+        assert_eq!(
+            abbreviations.insert("Pr", "CH2CH2CH3").unwrap().as_deref(),
+            Some("C3H7")
+        );
+        assert_eq!(
+            abbreviations.iter().collect::<Vec<_>>(),
+            [("Pr", "CH2CH2CH3")]
+        );
+    }
+
+    #[test]
+    fn smiles_test() {
+        let hill = |smiles: &str| {
+            Molecule::from_smiles(smiles)
+                .unwrap()
+                .to_string_with(FormulaOrder::Hill)
+        };
+        assert_eq!(hill("C1CCCCC1"), "C6H12");
+        assert_eq!(hill("c1ccccc1"), "C6H6");
+        assert_eq!(hill("c1ccc2ccccc2c1"), "C10H8");
+        assert_eq!(hill("c1cc[nH]c1"), "C4H5N");
+        assert_eq!(hill("C#N"), "CHN");
+        assert_eq!(hill("ClC(Cl)(Cl)Cl"), "CCl4");
+        assert_eq!(hill("CS(=O)(=O)O"), "CH4O3S");
+        assert_eq!(hill("C=1CC=1"), "C3H4");
+        assert_eq!(hill("C%10CC%10"), "C3H6");
+        assert_eq!(hill("[13CH4]"), "[13C]H4");
+        assert_eq!(hill("[se]1cccc1"), "C4H4Se");
+        assert_eq!(hill("[Cu+2].[O-]S(=O)(=O)[O-]"), "CuO4S");
+        assert_eq!(hill("[Fe@@TH2H3]"), "FeH3");
+
+        let ethanol = Graph::parse("CCO").unwrap();
+        assert_eq!(ethanol.neighbours(1).collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(ethanol.atoms[2].loc, TokenLoc::new(3, 1));
+        let ethene = Graph::parse("C=C").unwrap();
+        assert_eq!(ethene.bonds[0].order, BondOrder::Double);
+        assert_eq!(Molecule::from_smiles("[NH4+]").unwrap().charge, 1);
+
+        let err = |smiles: &str| Graph::parse(smiles).unwrap_err();
+        assert!(
+            matches!(err("C(C"), StoikError::UnpairedParenthesis(loc) if loc == TokenLoc::new(2, 1))
+        );
+        assert!(
+            matches!(err("CC)"), StoikError::UnpairedParenthesis(loc) if loc == TokenLoc::new(3, 1))
+        );
+        assert!(
+            matches!(err("C[CH4"), StoikError::UnpairedBracket(loc) if loc == TokenLoc::new(2, 1))
+        );
+        assert!(matches!(err("CC="), StoikError::InvalidToken(loc) if loc == TokenLoc::new(3, 1)));
+        assert!(
+            matches!(err("C1CC2"), StoikError::UnclosedRing(loc) if loc == TokenLoc::new(2, 1))
+        );
+        assert!(matches!(err("C11"), StoikError::InvalidToken(loc) if loc == TokenLoc::new(3, 1)));
+        assert!(matches!(err("CC."), StoikError::InvalidToken(loc) if loc == TokenLoc::new(3, 1)));
+        assert!(matches!(err("Cx"), StoikError::InvalidToken(loc) if loc == TokenLoc::new(2, 1)));
+        assert!(matches!(err("[99999C]"), StoikError::Overflow(loc) if loc == TokenLoc::new(2, 5)));
+        assert!(matches!(err(""), StoikError::EmptyMolecule));
+
+        // SMILES can be mixed with formulas in equations
+        let equation: Equation = "{C1CCCCC1} + O2 -> CO2 + H2O".parse().unwrap();
+        let balanced = equation.balance().unwrap();
+        assert_eq!(balanced.to_string(), "{C1CCCCC1} + 9O2 -> 6CO2 + 6H2O");
+        assert_eq!(
+            balanced.render(OutputFormat::Unicode).unwrap(),
+            "C₆H₁₂ + 9O₂ → 6CO₂ + 6H₂O"
+        );
+        let equation: Equation = "{[Na+]} + {[Cl-]} -> NaCl(s)".parse().unwrap();
+        assert_eq!(equation.reactants.len(), 2);
+        assert!(equation.is_balanced());
+
+        let err = "H2 + {C1CC} -> CH4".parse::<Equation>().unwrap_err();
+        assert!(matches!(err, StoikError::UnclosedRing(loc) if loc == TokenLoc::new(8, 1)));
+        let err = "2{CCO -> CO2".parse::<Equation>().unwrap_err();
+        assert!(matches!(err, StoikError::UnpairedBracket(loc) if loc == TokenLoc::new(2, 1)));
+        let diagnostics = Equation::diagnose("{CC} + {C(C} -> C3H8", &ParseOptions::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Some(TokenLoc::new(10, 1)));
+    }
+
+    #[test]
+    fn inchi_test() {
+        let inchi = |formula: &str| Molecule::from_formula(formula).unwrap().to_inchi().unwrap();
+        assert_eq!(inchi("H2O"), "H2O");
+        assert_eq!(inchi("NH3"), "H3N");
+        assert_eq!(inchi("C6H5CH3"), "C7H8");
+        assert_eq!(inchi("CH3D"), "CH4");
+        assert_eq!(inchi("Fe3+(aq)"), "Fe/q+3");
+        assert_eq!(inchi("2H2O"), "H2O");
+        assert!(Molecule::from_formula("e-").unwrap().to_inchi().is_err());
+        let mut negative = Molecule::from_formula("H2O").unwrap();
+        negative.moles = -1;
+        assert!(negative.to_inchi().is_err());
+
+        // hydrates keep their water as separate components, like PubChem's
+        // `InChI=1S/C2H2O4.2H2O/c3-1(4)2(5)6;;/h(H,3,4)(H,5,6);2*1H2`
+        assert_eq!(inchi("C2H2O4·2H2O"), "C2H2O4.2H2O");
+        assert_eq!(inchi("2(COOH)2.2H2O(s)"), "C2H2O4.2H2O");
+        assert_eq!(inchi("Na2CO3·10H2O"), "CNa2O3.10H2O");
+        let mut changed = Molecule::from_formula("C2H2O4·2H2O").unwrap();
+        changed.increase_atom("C", 1);
+        assert_eq!(changed.to_inchi().unwrap(), "C3H6O6");
+        let pubchem = "InChI=1S/Cu.H2O4S.5H2O/c;1-5(2,3)4;;;;;/h;(H2,1,2,3,4);5*1H2/q+2;;;;;;/p-2";
+        assert_eq!(
+            Molecule::from_inchi(pubchem).unwrap(),
+            Molecule::from_formula("CuSO4·5H2O").unwrap()
+        );
+
+        // real InChIs from PubChem are written back out as they were
+        for (pubchem, layers) in [
+            ("InChI=1S/Ca.2ClH/h;2*1H/q+2;;/p-2", "Ca.2ClH/q+2;;/p-2"),
+            (
+                "InChI=1S/2ClH.Mg.6H2O/h2*1H;;6*1H2/q;;+2;;;;;;/p-2",
+                "2ClH.Mg.6H2O/q;;+2;;;;;;/p-2",
+            ),
+            (
+                "InChI=1S/C2H2O4.2Na/c3-1(4)2(5)6;;/h(H,3,4)(H,5,6);;/q;2*+1/p-2",
+                "C2H2O4.2Na/q;2*+1/p-2",
+            ),
+        ] {
+            assert_eq!(InchiFormula::parse(pubchem).unwrap().to_string(), layers);
+        }
+
+        // a big multiple is written with a `*` rather than one charge each
+        let sodium = InchiFormula::parse("InChI=1S/100000000Na/q100000000*+1").unwrap();
+        assert_eq!(sodium.to_string(), "100000000Na/q100000000*+1");
+
+        // components are sorted biggest first, and the same ones are joined
+        let mixture = ["Na+", "Cl-", "C2H5OH", "H2O", "Na+"]
+            .map(|x| Molecule::from_formula(x).unwrap())
+            .to_vec();
+        let mixture = InchiFormula::new(mixture).unwrap();
+        assert_eq!(mixture.to_string(), "C2H6O.Cl.H2O.2Na/q;-1;;2*+1");
+        let read = InchiFormula::parse(&mixture.to_string()).unwrap();
+        assert_eq!(read.to_string(), mixture.to_string());
+        assert_eq!(
+            read.to_molecule().unwrap(),
+            Molecule::from_formula("C2H8O2NaNaCl^+").unwrap()
+        );
+
+        let graph = Graph::parse("[Fe+3].[Fe+2].[Fe+3].[O-2].[O-2].[O-2].[O-2]").unwrap();
+        let magnetite = InchiFormula::new(graph.components()).unwrap();
+        assert_eq!(magnetite.to_string(), "3Fe.4O/q2*+3;+2;4*-2");
+        assert_eq!(
+            magnetite.to_molecule().unwrap(),
+            Molecule::from_formula("Fe3O4").unwrap()
+        );
+
+        // the other layers are skipped, and protons are added from the `/p` layer
+        let ammonium = Molecule::from_inchi("InChI=1S/H3N/h1H3/p+1").unwrap();
+        assert_eq!(ammonium, Molecule::from_formula("NH4+").unwrap());
+        let proton = Molecule::from_inchi("InChI=1S/p+1").unwrap();
+        assert_eq!(proton, Molecule::from_formula("H+").unwrap());
+        let permanganate = Molecule::from_inchi("InChI=1S/Mn.4O/q;;;;-1").unwrap();
+        assert_eq!(permanganate, Molecule::from_formula("MnO4-").unwrap());
+        let sulfate = Molecule::from_inchi("H2O4S/c1-5(2,3)4/h(H2,1,2,3,4)/p-2").unwrap();
+        assert_eq!(sulfate, Molecule::from_formula("SO4^2-").unwrap());
+        let cisplatin =
+            InchiFormula::parse("InChI=1S/2ClH.2H3N.Pt/h2*1H;2*1H3;/q;;;;+2/p-2").unwrap();
+        assert_eq!(
+            cisplatin
+                .to_molecule()
+                .unwrap()
+                .to_string_with(FormulaOrder::Hill),
+            "Cl2H6N2Pt"
+        );
+
+        let err = |inchi: &str| InchiFormula::parse(inchi).unwrap_err();
+        assert!(
+            matches!(err("InChI=1S/CH4/q+1;+1"), StoikError::InvalidToken(loc) if loc == TokenLoc::new(14, 6))
+        );
+        assert!(
+            matches!(err("InChI=1S/CH4/pp"), StoikError::InvalidToken(loc) if loc == TokenLoc::new(14, 2))
+        );
+        assert!(
+            matches!(err("InChI=1S/CH4.Xx"), StoikError::UnknownElement(loc) if loc == TokenLoc::new(14, 2))
+        );
+        assert!(matches!(err("InChI=1S/"), StoikError::EmptyMolecule));
+        assert!(InchiFormula::parse("InChI=1S/H2O/p-3")
+            .unwrap()
+            .to_molecule()
+            .is_err());
+    }
+
+    #[test]
+    fn valence_test() {
+        let degree = |formula: &str| {
+            Molecule::from_formula(formula)
+                .unwrap()
+                .degree_of_unsaturation()
+                .unwrap()
+        };
+        assert_eq!(degree("C10H8"), 7);
+        assert_eq!(degree("C2H2"), 2);
+        assert_eq!(degree("C6H5Cl"), 4);
+        assert_eq!(degree("C4H9NO2"), 1);
+        assert_eq!(degree("CH3SO3H"), 0);
+        assert_eq!(degree("[13C]O2"), 2);
+        assert!(matches!(
+            Molecule::from_formula("e-")
+                .unwrap()
+                .degree_of_unsaturation(),
+            Err(StoikError::EmptyMolecule)
+        ));
+        assert!(matches!(
+            Molecule::from_formula("Xy2").unwrap().degree_of_unsaturation(),
+            Err(StoikError::NoValence(atom)) if atom == "Xy"
+        ));
+
+        let warned = |formula: &str| Molecule::from_formula(formula).unwrap().check_valence();
+        for formula in ["CH5", "C2H7", "CH3", "NO2", "C2H8", "OH4", "NaCl2", "H3O"] {
+            let warning = warned(formula).unwrap();
+            assert_eq!(warning.severity, Severity::Warning, "{formula}");
+        }
+        for formula in [
+            "H2",
+            "H2O",
+            "CH3+",
+            "H3O+",
+            "OH-",
+            "NaCl",
+            "MgCl2",
+            "Al2O3",
+            "Na2SO4",
+            "PCl5",
+            "XeF4",
+            "C6H12O6",
+            "CuSO4.5H2O",
+            "Fe0.95O",
+            "He",
+            "e-",
+            "2CH4",
+        ] {
+            assert!(warned(formula).is_none(), "{formula}");
+        }
+
+        let equation: Equation = "C2H7 + O2 -> 2 CH5 + H2O".parse().unwrap();
+        let spans = equation
+            .valence_warnings()
+            .into_iter()
+            .map(|x| x.span)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [Some(TokenLoc::new(1, 4)), Some(TokenLoc::new(14, 5))]
+        );
+
+        for (symbol, period, group) in [
+            ("H", 1, Some(1)),
+            ("Li", 2, Some(1)),
+            ("B", 2, Some(13)),
+            ("Ar", 3, Some(18)),
+            ("Sc", 4, Some(3)),
+            ("Kr", 4, Some(18)),
+            ("Ag", 5, Some(11)),
+            ("Ba", 6, Some(2)),
+            ("Ce", 6, None),
+            ("Lu", 6, Some(3)),
+            ("Hg", 6, Some(12)),
+            ("Fr", 7, Some(1)),
+            ("Og", 7, Some(18)),
+        ] {
+            let element = elements::get(symbol).unwrap();
+            assert_eq!(
+                (element.period(), element.group()),
+                (period, group),
+                "{symbol}"
+            );
+        }
+    }
+
+    #[test]
+    fn isotopic_envelope_test() {
+        for element in elements::ELEMENTS {
+            let isotopes = nuclides::of(element.symbol);
+            let natural = isotopes.iter().filter(|x| x.abundance > 0.0);
+            let total = natural.clone().map(|x| x.abundance).sum::<f64>();
+            assert!(
+                total == 0.0 || (total - 1.0).abs() < 1e-4,
+                "{}",
+                element.symbol
+            );
+            // the isotopes should average out to the atomic weight
+            let weight = MolarMass::of_element(element.symbol).unwrap();
+            let mean = natural.map(|x| x.mass * x.abundance).sum::<f64>();
+            assert!(
+                total == 0.0 || (mean - weight.value).abs() < 0.01 + 2.0 * weight.uncertainty,
+                "{}",
+                element.symbol
+            );
+        }
+
+        let mono = |formula: &str| Molecule::from_formula(formula).unwrap().monoisotopic_mass();
+        assert!((mono("C6H12O6").unwrap() - 180.063388).abs() < 1e-6);
+        assert!((mono("C6H5Br").unwrap() - 155.957463).abs() < 1e-6);
+        assert!((mono("NH4+").unwrap() - 18.033826).abs() < 1e-6);
+        assert!((mono("2H2O").unwrap() - 36.021129).abs() < 1e-6);
+        assert!((mono("CD3OD").unwrap() - 36.051322).abs() < 1e-6);
+        assert!(matches!(mono("PmCl3"), Err(StoikError::NoAtomicWeight(atom)) if atom == "Pm"));
+        assert!(matches!(mono("[15C]"), Err(StoikError::NoAtomicWeight(atom)) if atom == "[15C]"));
+
+        let envelope = |formula: &str, threshold: f64| {
+            Molecule::from_formula(formula)
+                .unwrap()
+                .isotopic_envelope(threshold)
+        };
+        // bromine is about half and half 79Br and 81Br
+        let bromobenzene = envelope("C6H5Br", 0.01).unwrap();
+        assert_eq!(bromobenzene.len(), 4);
+        assert!((bromobenzene[2].abundance - 0.9746).abs() < 0.0001);
+        assert!((bromobenzene[1].abundance - 0.0655).abs() < 0.0001);
+        assert!((bromobenzene[1].mass - 156.9608).abs() < 1e-4);
+        let pruned = envelope("C6H5Br", 0.07).unwrap();
+        assert_eq!(pruned.len(), 2);
+        assert!(pruned.iter().all(|x| x.abundance >= 0.07));
+
+        // labelled isotopes only have one peak
+        let labelled = envelope("[13C]D4", 0.0).unwrap();
+        assert_eq!(labelled.len(), 1);
+        assert!((labelled[0].mass - mono("[13C]D4").unwrap()).abs() < 1e-9);
+        assert_eq!(envelope("e-", 0.001).unwrap().len(), 1);
+        assert_eq!(envelope("Na20", 0.0).unwrap().len(), 1);
+        assert!(envelope("Fe0.5", 0.001).is_err());
+        assert!(matches!(
+            envelope("C99999999999999", 0.001),
+            Err(StoikError::Overflow(_))
+        ));
+
+        // large molecules are most likely to have a few heavier isotopes
+        let protein = Molecule::from_formula("C2000H3000N500O600S10").unwrap();
+        let envelope = protein.isotopic_envelope(0.001).unwrap();
+        let tallest = envelope
+            .iter()
+            .max_by(|a, b| a.abundance.total_cmp(&b.abundance))
+            .unwrap();
+        assert_eq!(tallest.mass, protein.most_abundant_mass().unwrap());
+        assert!(tallest.mass > protein.monoisotopic_mass().unwrap() + 20.0);
+        let mean = envelope.iter().map(|x| x.mass * x.abundance).sum::<f64>()
+            / envelope.iter().map(|x| x.abundance).sum::<f64>();
+        assert!((mean - protein.molar_mass().unwrap().value).abs() < 0.5);
+    }
 }
@@ -0,0 +1,389 @@
+//! Data about the isotopes of each element, with their exact masses and natural abundances
+//! from the NIST Atomic Weights and Isotopic Compositions tables
+//!
+//! Every stable isotope is listed, along with long lived ones that are found in nature,
+//! like uranium-235. A few isotopes used for labelling, like tritium and carbon-14, are also
+//! listed with an abundance of zero so their mass is known
+//!
+//! ```
+//! use stoik::nuclides;
+//!
+//! let chlorine = nuclides::of("Cl");
+//! assert_eq!(chlorine.len(), 2);
+//! assert_eq!(chlorine[0].mass_number, 35);
+//! assert_eq!(chlorine[0].abundance, 0.7576);
+//! assert!(nuclides::of("Tc").is_empty());
+//! ```
+
+/// The mass of an electron in daltons
+pub const ELECTRON_MASS: f64 = 0.000548579909;
+
+/// One isotope of an element
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Nuclide {
+    /// The symbol of the element, like `C`
+    pub symbol: &'static str,
+    /// The number of protons and neutrons
+    pub mass_number: u16,
+    /// The mass of one atom in daltons
+    pub mass: f64,
+    /// The fraction of the atoms of the element that are this isotope in nature, from 0 to 1
+    pub abundance: f64,
+}
+
+const fn nuclide(symbol: &'static str, mass_number: u16, mass: f64, abundance: f64) -> Nuclide {
+    Nuclide {
+        symbol,
+        mass_number,
+        mass,
+        abundance,
+    }
+}
+
+/// Every isotope with a known natural abundance, ordered by atomic number then mass number
+pub const NUCLIDES: [Nuclide; 290] = [
+    nuclide("H", 1, 1.00782503223, 0.999885),
+    nuclide("H", 2, 2.01410177812, 0.000115),
+    nuclide("H", 3, 3.0160492779, 0.0),
+    nuclide("He", 3, 3.0160293201, 0.00000134),
+    nuclide("He", 4, 4.00260325413, 0.99999866),
+    nuclide("Li", 6, 6.0151228874, 0.0759),
+    nuclide("Li", 7, 7.0160034366, 0.9241),
+    nuclide("Be", 9, 9.012183065, 1.0),
+    nuclide("B", 10, 10.01293695, 0.199),
+    nuclide("B", 11, 11.00930536, 0.801),
+    nuclide("C", 12, 12.0, 0.9893),
+    nuclide("C", 13, 13.00335483507, 0.0107),
+    nuclide("C", 14, 14.0032419884, 0.0),
+    nuclide("N", 14, 14.00307400443, 0.99636),
+    nuclide("N", 15, 15.00010889888, 0.00364),
+    nuclide("O", 16, 15.99491461957, 0.99757),
+    nuclide("O", 17, 16.99913175650, 0.00038),
+    nuclide("O", 18, 17.99915961286, 0.00205),
+    nuclide("F", 19, 18.99840316273, 1.0),
+    nuclide("Ne", 20, 19.9924401762, 0.9048),
+    nuclide("Ne", 21, 20.993846685, 0.0027),
+    nuclide("Ne", 22, 21.991385114, 0.0925),
+    nuclide("Na", 23, 22.9897692820, 1.0),
+    nuclide("Mg", 24, 23.985041697, 0.7899),
+    nuclide("Mg", 25, 24.985836976, 0.1000),
+    nuclide("Mg", 26, 25.982592968, 0.1101),
+    nuclide("Al", 27, 26.98153853, 1.0),
+    nuclide("Si", 28, 27.97692653465, 0.92223),
+    nuclide("Si", 29, 28.97649466490, 0.04685),
+    nuclide("Si", 30, 29.973770136, 0.03092),
+    nuclide("P", 31, 30.97376199842, 1.0),
+    nuclide("S", 32, 31.9720711744, 0.9499),
+    nuclide("S", 33, 32.9714589098, 0.0075),
+    nuclide("S", 34, 33.967867004, 0.0425),
+    nuclide("S", 36, 35.96708071, 0.0001),
+    nuclide("Cl", 35, 34.968852682, 0.7576),
+    nuclide("Cl", 37, 36.965902602, 0.2424),
+    nuclide("Ar", 36, 35.967545105, 0.003336),
+    nuclide("Ar", 38, 37.96273211, 0.000629),
+    nuclide("Ar", 40, 39.9623831237, 0.996035),
+    nuclide("K", 39, 38.9637064864, 0.932581),
+    nuclide("K", 40, 39.963998166, 0.000117),
+    nuclide("K", 41, 40.9618252579, 0.067302),
+    nuclide("Ca", 40, 39.962590863, 0.96941),
+    nuclide("Ca", 42, 41.95861783, 0.00647),
+    nuclide("Ca", 43, 42.95876644, 0.00135),
+    nuclide("Ca", 44, 43.9554816, 0.02086),
+    nuclide("Ca", 46, 45.953689, 0.00004),
+    nuclide("Ca", 48, 47.95252276, 0.00187),
+    nuclide("Sc", 45, 44.95590828, 1.0),
+    nuclide("Ti", 46, 45.95262772, 0.0825),
+    nuclide("Ti", 47, 46.95175879, 0.0744),
+    nuclide("Ti", 48, 47.94794198, 0.7372),
+    nuclide("Ti", 49, 48.94786568, 0.0541),
+    nuclide("Ti", 50, 49.94478689, 0.0518),
+    nuclide("V", 50, 49.94715601, 0.0025),
+    nuclide("V", 51, 50.94395704, 0.9975),
+    nuclide("Cr", 50, 49.94604183, 0.04345),
+    nuclide("Cr", 52, 51.94050623, 0.83789),
+    nuclide("Cr", 53, 52.94064815, 0.09501),
+    nuclide("Cr", 54, 53.93887916, 0.02365),
+    nuclide("Mn", 55, 54.93804391, 1.0),
+    nuclide("Fe", 54, 53.93960899, 0.05845),
+    nuclide("Fe", 56, 55.93493633, 0.91754),
+    nuclide("Fe", 57, 56.93539284, 0.02119),
+    nuclide("Fe", 58, 57.93327443, 0.00282),
+    nuclide("Co", 59, 58.93319429, 1.0),
+    nuclide("Ni", 58, 57.93534241, 0.68077),
+    nuclide("Ni", 60, 59.93078588, 0.26223),
+    nuclide("Ni", 61, 60.93105557, 0.011399),
+    nuclide("Ni", 62, 61.92834537, 0.036346),
+    nuclide("Ni", 64, 63.92796682, 0.009255),
+    nuclide("Cu", 63, 62.92959772, 0.6915),
+    nuclide("Cu", 65, 64.9277897, 0.3085),
+    nuclide("Zn", 64, 63.92914201, 0.4917),
+    nuclide("Zn", 66, 65.92603381, 0.2773),
+    nuclide("Zn", 67, 66.92712775, 0.0404),
+    nuclide("Zn", 68, 67.92484455, 0.1845),
+    nuclide("Zn", 70, 69.9253192, 0.0061),
+    nuclide("Ga", 69, 68.9255735, 0.60108),
+    nuclide("Ga", 71, 70.92470258, 0.39892),
+    nuclide("Ge", 70, 69.92424875, 0.2057),
+    nuclide("Ge", 72, 71.922075826, 0.2745),
+    nuclide("Ge", 73, 72.923458956, 0.0775),
+    nuclide("Ge", 74, 73.921177761, 0.365),
+    nuclide("Ge", 76, 75.921402726, 0.0773),
+    nuclide("As", 75, 74.92159457, 1.0),
+    nuclide("Se", 74, 73.922475934, 0.0089),
+    nuclide("Se", 76, 75.919213704, 0.0937),
+    nuclide("Se", 77, 76.919914154, 0.0763),
+    nuclide("Se", 78, 77.91730928, 0.2377),
+    nuclide("Se", 80, 79.9165218, 0.4961),
+    nuclide("Se", 82, 81.9166995, 0.0873),
+    nuclide("Br", 79, 78.9183376, 0.5069),
+    nuclide("Br", 81, 80.9162897, 0.4931),
+    nuclide("Kr", 78, 77.92036494, 0.00355),
+    nuclide("Kr", 80, 79.91637808, 0.02286),
+    nuclide("Kr", 82, 81.91348273, 0.11593),
+    nuclide("Kr", 83, 82.91412716, 0.115),
+    nuclide("Kr", 84, 83.9114977282, 0.56987),
+    nuclide("Kr", 86, 85.9106106269, 0.17279),
+    nuclide("Rb", 85, 84.9117897379, 0.7217),
+    nuclide("Rb", 87, 86.909180531, 0.2783),
+    nuclide("Sr", 84, 83.9134191, 0.0056),
+    nuclide("Sr", 86, 85.9092606, 0.0986),
+    nuclide("Sr", 87, 86.9088775, 0.07),
+    nuclide("Sr", 88, 87.9056125, 0.8258),
+    nuclide("Y", 89, 88.9058403, 1.0),
+    nuclide("Zr", 90, 89.9046977, 0.5145),
+    nuclide("Zr", 91, 90.9056396, 0.1122),
+    nuclide("Zr", 92, 91.9050347, 0.1715),
+    nuclide("Zr", 94, 93.9063108, 0.1738),
+    nuclide("Zr", 96, 95.9082714, 0.028),
+    nuclide("Nb", 93, 92.906373, 1.0),
+    nuclide("Mo", 92, 91.90680796, 0.1453),
+    nuclide("Mo", 94, 93.9050849, 0.0915),
+    nuclide("Mo", 95, 94.90583877, 0.1584),
+    nuclide("Mo", 96, 95.90467612, 0.1667),
+    nuclide("Mo", 97, 96.90601812, 0.096),
+    nuclide("Mo", 98, 97.90540482, 0.2439),
+    nuclide("Mo", 100, 99.9074718, 0.0982),
+    nuclide("Ru", 96, 95.90759025, 0.0554),
+    nuclide("Ru", 98, 97.9052868, 0.0187),
+    nuclide("Ru", 99, 98.9059341, 0.1276),
+    nuclide("Ru", 100, 99.9042143, 0.126),
+    nuclide("Ru", 101, 100.9055769, 0.1706),
+    nuclide("Ru", 102, 101.9043441, 0.3155),
+    nuclide("Ru", 104, 103.9054275, 0.1862),
+    nuclide("Rh", 103, 102.905498, 1.0),
+    nuclide("Pd", 102, 101.9056022, 0.0102),
+    nuclide("Pd", 104, 103.9040305, 0.1114),
+    nuclide("Pd", 105, 104.9050796, 0.2233),
+    nuclide("Pd", 106, 105.9034804, 0.2733),
+    nuclide("Pd", 108, 107.9038916, 0.2646),
+    nuclide("Pd", 110, 109.9051722, 0.1172),
+    nuclide("Ag", 107, 106.9050916, 0.51839),
+    nuclide("Ag", 109, 108.9047553, 0.48161),
+    nuclide("Cd", 106, 105.9064599, 0.0125),
+    nuclide("Cd", 108, 107.9041834, 0.0089),
+    nuclide("Cd", 110, 109.90300661, 0.1249),
+    nuclide("Cd", 111, 110.90418287, 0.128),
+    nuclide("Cd", 112, 111.90276287, 0.2413),
+    nuclide("Cd", 113, 112.90440813, 0.1222),
+    nuclide("Cd", 114, 113.90336509, 0.2873),
+    nuclide("Cd", 116, 115.90476315, 0.0749),
+    nuclide("In", 113, 112.90406184, 0.0429),
+    nuclide("In", 115, 114.903878776, 0.9571),
+    nuclide("Sn", 112, 111.90482387, 0.0097),
+    nuclide("Sn", 114, 113.9027827, 0.0066),
+    nuclide("Sn", 115, 114.903344699, 0.0034),
+    nuclide("Sn", 116, 115.9017428, 0.1454),
+    nuclide("Sn", 117, 116.90295398, 0.0768),
+    nuclide("Sn", 118, 117.90160657, 0.2422),
+    nuclide("Sn", 119, 118.90331117, 0.0859),
+    nuclide("Sn", 120, 119.90220163, 0.3258),
+    nuclide("Sn", 122, 121.9034438, 0.0463),
+    nuclide("Sn", 124, 123.9052766, 0.0579),
+    nuclide("Sb", 121, 120.903812, 0.5721),
+    nuclide("Sb", 123, 122.9042132, 0.4279),
+    nuclide("Te", 120, 119.9040593, 0.0009),
+    nuclide("Te", 122, 121.9030435, 0.0255),
+    nuclide("Te", 123, 122.9042698, 0.0089),
+    nuclide("Te", 124, 123.9028171, 0.0474),
+    nuclide("Te", 125, 124.9044299, 0.0707),
+    nuclide("Te", 126, 125.9033109, 0.1884),
+    nuclide("Te", 128, 127.90446128, 0.3174),
+    nuclide("Te", 130, 129.906222748, 0.3408),
+    nuclide("I", 127, 126.9044719, 1.0),
+    nuclide("Xe", 124, 123.905892, 0.000952),
+    nuclide("Xe", 126, 125.9042983, 0.00089),
+    nuclide("Xe", 128, 127.903531, 0.019102),
+    nuclide("Xe", 129, 128.9047808611, 0.264006),
+    nuclide("Xe", 130, 129.903509349, 0.04071),
+    nuclide("Xe", 131, 130.90508406, 0.212324),
+    nuclide("Xe", 132, 131.9041550856, 0.269086),
+    nuclide("Xe", 134, 133.90539466, 0.104357),
+    nuclide("Xe", 136, 135.907214484, 0.088573),
+    nuclide("Cs", 133, 132.905451961, 1.0),
+    nuclide("Ba", 130, 129.9063207, 0.00106),
+    nuclide("Ba", 132, 131.9050611, 0.00101),
+    nuclide("Ba", 134, 133.90450818, 0.02417),
+    nuclide("Ba", 135, 134.90568838, 0.06592),
+    nuclide("Ba", 136, 135.90457573, 0.07854),
+    nuclide("Ba", 137, 136.90582714, 0.11232),
+    nuclide("Ba", 138, 137.905247, 0.71698),
+    nuclide("La", 138, 137.9071149, 0.0008881),
+    nuclide("La", 139, 138.9063563, 0.9991119),
+    nuclide("Ce", 136, 135.90712921, 0.00185),
+    nuclide("Ce", 138, 137.905991, 0.00251),
+    nuclide("Ce", 140, 139.9054431, 0.8845),
+    nuclide("Ce", 142, 141.9092504, 0.11114),
+    nuclide("Pr", 141, 140.9076576, 1.0),
+    nuclide("Nd", 142, 141.907729, 0.27152),
+    nuclide("Nd", 143, 142.90982, 0.12174),
+    nuclide("Nd", 144, 143.910093, 0.23798),
+    nuclide("Nd", 145, 144.9125793, 0.08293),
+    nuclide("Nd", 146, 145.9131226, 0.17189),
+    nuclide("Nd", 148, 147.9168993, 0.05756),
+    nuclide("Nd", 150, 149.9209022, 0.05638),
+    nuclide("Sm", 144, 143.9120065, 0.0307),
+    nuclide("Sm", 147, 146.9149044, 0.1499),
+    nuclide("Sm", 148, 147.9148292, 0.1124),
+    nuclide("Sm", 149, 148.9171921, 0.1382),
+    nuclide("Sm", 150, 149.9172829, 0.0738),
+    nuclide("Sm", 152, 151.9197397, 0.2675),
+    nuclide("Sm", 154, 153.9222169, 0.2275),
+    nuclide("Eu", 151, 150.9198578, 0.4781),
+    nuclide("Eu", 153, 152.921238, 0.5219),
+    nuclide("Gd", 152, 151.9197995, 0.002),
+    nuclide("Gd", 154, 153.9208741, 0.0218),
+    nuclide("Gd", 155, 154.9226305, 0.148),
+    nuclide("Gd", 156, 155.9221312, 0.2047),
+    nuclide("Gd", 157, 156.9239686, 0.1565),
+    nuclide("Gd", 158, 157.9241123, 0.2484),
+    nuclide("Gd", 160, 159.9270624, 0.2186),
+    nuclide("Tb", 159, 158.9253547, 1.0),
+    nuclide("Dy", 156, 155.9242847, 0.00056),
+    nuclide("Dy", 158, 157.9244159, 0.00095),
+    nuclide("Dy", 160, 159.9252046, 0.02329),
+    nuclide("Dy", 161, 160.9269405, 0.18889),
+    nuclide("Dy", 162, 161.9268056, 0.25475),
+    nuclide("Dy", 163, 162.9287383, 0.24896),
+    nuclide("Dy", 164, 163.9291819, 0.2826),
+    nuclide("Ho", 165, 164.9303288, 1.0),
+    nuclide("Er", 162, 161.9287884, 0.00139),
+    nuclide("Er", 164, 163.9292088, 0.01601),
+    nuclide("Er", 166, 165.9302995, 0.33503),
+    nuclide("Er", 167, 166.9320546, 0.22869),
+    nuclide("Er", 168, 167.9323767, 0.26978),
+    nuclide("Er", 170, 169.9354702, 0.1491),
+    nuclide("Tm", 169, 168.9342179, 1.0),
+    nuclide("Yb", 168, 167.9338896, 0.00123),
+    nuclide("Yb", 170, 169.9347664, 0.02982),
+    nuclide("Yb", 171, 170.9363302, 0.1409),
+    nuclide("Yb", 172, 171.9363859, 0.2168),
+    nuclide("Yb", 173, 172.9382151, 0.16103),
+    nuclide("Yb", 174, 173.9388664, 0.32026),
+    nuclide("Yb", 176, 175.9425764, 0.12996),
+    nuclide("Lu", 175, 174.9407752, 0.97401),
+    nuclide("Lu", 176, 175.9426897, 0.02599),
+    nuclide("Hf", 174, 173.9400461, 0.0016),
+    nuclide("Hf", 176, 175.9414076, 0.0526),
+    nuclide("Hf", 177, 176.9432277, 0.186),
+    nuclide("Hf", 178, 177.9437058, 0.2728),
+    nuclide("Hf", 179, 178.9458232, 0.1362),
+    nuclide("Hf", 180, 179.946557, 0.3508),
+    nuclide("Ta", 180, 179.9474648, 0.0001201),
+    nuclide("Ta", 181, 180.9479958, 0.9998799),
+    nuclide("W", 180, 179.9467108, 0.0012),
+    nuclide("W", 182, 181.94820394, 0.265),
+    nuclide("W", 183, 182.95022275, 0.1431),
+    nuclide("W", 184, 183.95093092, 0.3064),
+    nuclide("W", 186, 185.9543628, 0.2843),
+    nuclide("Re", 185, 184.9529545, 0.374),
+    nuclide("Re", 187, 186.9557501, 0.626),
+    nuclide("Os", 184, 183.9524885, 0.0002),
+    nuclide("Os", 186, 185.953835, 0.0159),
+    nuclide("Os", 187, 186.9557474, 0.0196),
+    nuclide("Os", 188, 187.9558352, 0.1324),
+    nuclide("Os", 189, 188.9581442, 0.1615),
+    nuclide("Os", 190, 189.9584437, 0.2626),
+    nuclide("Os", 192, 191.961477, 0.4078),
+    nuclide("Ir", 191, 190.9605893, 0.373),
+    nuclide("Ir", 193, 192.9629216, 0.627),
+    nuclide("Pt", 190, 189.9599297, 0.00012),
+    nuclide("Pt", 192, 191.9610387, 0.00782),
+    nuclide("Pt", 194, 193.9626809, 0.3286),
+    nuclide("Pt", 195, 194.9647917, 0.3378),
+    nuclide("Pt", 196, 195.96495209, 0.2521),
+    nuclide("Pt", 198, 197.9678949, 0.07356),
+    nuclide("Au", 197, 196.96656879, 1.0),
+    nuclide("Hg", 196, 195.9658326, 0.0015),
+    nuclide("Hg", 198, 197.9667686, 0.0997),
+    nuclide("Hg", 199, 198.96828064, 0.1687),
+    nuclide("Hg", 200, 199.96832659, 0.231),
+    nuclide("Hg", 201, 200.97030284, 0.1318),
+    nuclide("Hg", 202, 201.9706434, 0.2986),
+    nuclide("Hg", 204, 203.97349398, 0.0687),
+    nuclide("Tl", 203, 202.9723446, 0.2952),
+    nuclide("Tl", 205, 204.9744278, 0.7048),
+    nuclide("Pb", 204, 203.973044, 0.014),
+    nuclide("Pb", 206, 205.9744657, 0.241),
+    nuclide("Pb", 207, 206.9758973, 0.221),
+    nuclide("Pb", 208, 207.9766525, 0.524),
+    nuclide("Bi", 209, 208.9803991, 1.0),
+    nuclide("Th", 232, 232.0380558, 1.0),
+    nuclide("Pa", 231, 231.0358842, 1.0),
+    nuclide("U", 234, 234.0409523, 0.000054),
+    nuclide("U", 235, 235.0439301, 0.007204),
+    nuclide("U", 238, 238.0507884, 0.992742),
+];
+
+/// Gets every isotope of an element, ordered by mass number. This is empty if the
+/// element has no isotopes found in nature, like technetium
+///
+/// # Examples
+/// ```
+/// use stoik::nuclides;
+///
+/// let masses = nuclides::of("O").iter().map(|x| x.mass_number).collect::<Vec<_>>();
+/// assert_eq!(masses, [16, 17, 18]);
+/// assert!(nuclides::of("Xy").is_empty());
+/// ```
+pub fn of(symbol: &str) -> &'static [Nuclide] {
+    let start = NUCLIDES.iter().position(|x| x.symbol == symbol);
+    let Some(start) = start else {
+        return &[];
+    };
+    let len = NUCLIDES[start..]
+        .iter()
+        .take_while(|x| x.symbol == symbol)
+        .count();
+    &NUCLIDES[start..start + len]
+}
+
+/// Gets one isotope of an element from its mass number
+///
+/// # Examples
+/// ```
+/// use stoik::nuclides;
+///
+/// assert_eq!(nuclides::get("H", 2).unwrap().mass, 2.01410177812);
+/// assert!(nuclides::get("C", 15).is_none());
+/// ```
+pub fn get(symbol: &str, mass_number: u16) -> Option<&'static Nuclide> {
+    of(symbol).iter().find(|x| x.mass_number == mass_number)
+}
+
+/// Gets the most abundant isotope of an element, which is what the monoisotopic mass is made from
+///
+/// # Examples
+/// ```
+/// use stoik::nuclides;
+///
+/// assert_eq!(nuclides::most_abundant("C").unwrap().mass_number, 12);
+/// assert_eq!(nuclides::most_abundant("Se").unwrap().mass_number, 80);
+/// assert!(nuclides::most_abundant("Pm").is_none());
+/// ```
+pub fn most_abundant(symbol: &str) -> Option<&'static Nuclide> {
+    of(symbol)
+        .iter()
+        .max_by(|a, b| a.abundance.total_cmp(&b.abundance))
+}
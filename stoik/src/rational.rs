@@ -0,0 +1,338 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use crate::err::StoikError;
+
+/// An exact fraction, always kept in its lowest terms with a positive denominator
+///
+/// This is used for the counts of atoms, so that non-stoichiometric compounds
+/// like `Fe0.95O` can be written and still compared exactly.
+///
+/// The operators never panic: if the exact result does not fit, they give the nearest rational
+/// with a denominator of at most 2^62, saturating at ±[`i128::MAX`]. Use the `checked_*`
+/// methods, like [`checked_add`](Self::checked_add), to find out when this happens.
+///
+/// # Examples
+///
+/// ```
+/// use stoik::Rational;
+///
+/// let count: Rational = "0.95".parse()?;
+/// assert_eq!(count, Rational::new(19, 20));
+/// assert_eq!(count.to_string(), "0.95");
+/// assert_eq!(Rational::new(1, 3).to_string(), "1/3");
+/// assert_eq!(count + "0.05".parse()?, 1);
+///
+/// let big = Rational::from(i64::MAX) * Rational::from(i64::MAX);
+/// assert_eq!(big * big, Rational::new(i128::MAX, 1));
+/// assert_eq!(big.checked_mul(big), None);
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    /// Zero
+    pub const ZERO: Self = Self { num: 0, den: 1 };
+    /// One
+    pub const ONE: Self = Self { num: 1, den: 1 };
+
+    /// Creates a new rational, panicking if `den` is 0
+    ///
+    /// See [`checked_new`](Self::checked_new) for a version that never panics
+    pub fn new(num: i128, den: i128) -> Self {
+        assert!(den != 0, "Rational with a denominator of 0");
+        let div = gcd(num, den) * den.signum();
+        Self {
+            num: num / div,
+            den: den / div,
+        }
+    }
+
+    /// Creates a new rational, giving `None` if `den` is 0 or either part is [`i128::MIN`]
+    pub fn checked_new(num: i128, den: i128) -> Option<Self> {
+        if den == 0 || num == i128::MIN || den == i128::MIN {
+            None
+        } else {
+            Some(Self::new(num, den))
+        }
+    }
+
+    /// Adds two rationals, giving `None` if the result would overflow
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        let num = self
+            .num
+            .checked_mul(rhs.den)?
+            .checked_add(rhs.num.checked_mul(self.den)?)?;
+        Self::checked_new(num, self.den.checked_mul(rhs.den)?)
+    }
+
+    /// Subtracts two rationals, giving `None` if the result would overflow
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(Self::checked_new(rhs.num.checked_neg()?, rhs.den)?)
+    }
+
+    /// Multiplies two rationals, giving `None` if the result would overflow
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Self::checked_new(
+            self.num.checked_mul(rhs.num)?,
+            self.den.checked_mul(rhs.den)?,
+        )
+    }
+
+    /// Divides two rationals, giving `None` if `rhs` is 0 or the result would overflow
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        Self::checked_new(
+            self.num.checked_mul(rhs.den)?,
+            self.den.checked_mul(rhs.num)?,
+        )
+    }
+
+    /// Gets the nearest rational to `value` with a denominator of at most 2^62, saturating at
+    /// ±[`i128::MAX`], which is what the operators give when the exact result overflows
+    fn saturate(value: f64) -> Self {
+        const DEN: i128 = 1 << 62;
+        let scaled = value * DEN as f64;
+        if scaled.abs() < i128::MAX as f64 {
+            Self::new(scaled.round() as i128, DEN)
+        } else {
+            // casting saturates, and NaN becomes 0
+            Self::new((value as i128).max(-i128::MAX), 1)
+        }
+    }
+
+    /// Returns true if both the numerator and denominator fit in an [`i64`]. Adding or
+    /// multiplying two such rationals can never overflow
+    pub(crate) fn fits_i64(&self) -> bool {
+        i64::try_from(self.num).is_ok() && i64::try_from(self.den).is_ok()
+    }
+
+    /// Gets the numerator, which carries the sign
+    pub fn num(&self) -> i128 {
+        self.num
+    }
+
+    /// Gets the denominator, which is always positive
+    pub fn den(&self) -> i128 {
+        self.den
+    }
+
+    /// Returns true if the rational is 0
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    /// Returns true if the rational is a whole number
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    /// Converts the rational to the nearest [`f64`]
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Self {
+            num: value as i128,
+            den: 1,
+        }
+    }
+}
+
+impl PartialEq<i64> for Rational {
+    fn eq(&self, other: &i64) -> bool {
+        self.den == 1 && self.num == *other as i128
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // compares the whole parts, then the reciprocals of the fractional parts, like the
+        // euclidean algorithm, so that nothing is multiplied and it cannot overflow
+        let (mut a, mut b) = ((self.num, self.den), (other.num, other.den));
+        loop {
+            let whole = a.0.div_euclid(a.1).cmp(&b.0.div_euclid(b.1));
+            if whole != Ordering::Equal {
+                return whole;
+            }
+            match (a.0.rem_euclid(a.1), b.0.rem_euclid(b.1)) {
+                (0, 0) => return Ordering::Equal,
+                (0, _) => return Ordering::Less,
+                (_, 0) => return Ordering::Greater,
+                // a bigger fraction has a smaller reciprocal, so the sides swap
+                (a_rem, b_rem) => (a, b) = ((b.1, b_rem), (a.1, a_rem)),
+            }
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs)
+            .unwrap_or_else(|| Self::saturate(self.to_f64() + rhs.to_f64()))
+    }
+}
+
+impl AddAssign for Rational {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for Rational {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs)
+            .unwrap_or_else(|| Self::saturate(self.to_f64() - rhs.to_f64()))
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs)
+            .unwrap_or_else(|| Self::saturate(self.to_f64() * rhs.to_f64()))
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+
+    /// Divides like [`checked_div`](Rational::checked_div), but dividing by 0 saturates
+    /// like an overflow, and 0 / 0 is 0
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs)
+            .unwrap_or_else(|| Self::saturate(self.to_f64() / rhs.to_f64()))
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            num: self.num.saturating_neg(),
+            den: self.den,
+        }
+    }
+}
+
+impl FromStr for Rational {
+    type Err = StoikError;
+
+    /// Parses a whole number, a decimal like `0.95` or a fraction like `1/3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || StoikError::InvalidInput(format!("`{s}` is not a number"));
+        let digits = |x: &str| !x.is_empty() && x.chars().all(|c| c.is_ascii_digit());
+        let (negative, unsigned) = match s.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim()),
+        };
+
+        let value = if let Some((num, den)) = unsigned.split_once('/') {
+            if !digits(num) || !digits(den) {
+                return Err(invalid());
+            }
+            let den = den.parse::<i128>().map_err(|_| invalid())?;
+            if den == 0 {
+                return Err(invalid());
+            }
+            Self::new(num.parse().map_err(|_| invalid())?, den)
+        } else {
+            let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+            if !digits(whole) || !(fraction.is_empty() || digits(fraction)) {
+                return Err(invalid());
+            }
+            let den = u32::try_from(fraction.len())
+                .ok()
+                .and_then(|len| 10i128.checked_pow(len))
+                .ok_or_else(invalid)?;
+            let num = format!("{whole}{fraction}")
+                .parse::<i128>()
+                .map_err(|_| invalid())?;
+            Self::new(num, den)
+        };
+        Ok(if negative { -value } else { value })
+    }
+}
+
+impl Display for Rational {
+    /// Writes the rational as a decimal if it ends, like `0.95`, and as a fraction otherwise
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.den == 1 {
+            return write!(f, "{}", self.num);
+        }
+
+        // the decimal ends if the denominator divides some power of 10
+        let places = (1..=30).find(|x| 10i128.pow(*x) % self.den == 0);
+        match places {
+            Some(places) => {
+                let scaled = self
+                    .num
+                    .checked_abs()
+                    .and_then(|x| x.checked_mul(10i128.pow(places) / self.den));
+                let Some(scaled) = scaled else {
+                    // too big to write as a decimal
+                    return write!(f, "{}/{}", self.num, self.den);
+                };
+                let digits = format!("{scaled:0>width$}", width = places as usize + 1);
+                let (whole, fraction) = digits.split_at(digits.len() - places as usize);
+                let sign = if self.num < 0 { "-" } else { "" };
+                write!(f, "{sign}{whole}.{}", fraction.trim_end_matches('0'))
+            }
+            None => write!(f, "{}/{}", self.num, self.den),
+        }
+    }
+}
+
+/// The greatest common divisor of two numbers, always positive. This is 1 if both are 0,
+/// and saturates at [`i128::MAX`] for the divisor of [`i128::MIN`] and itself
+pub(crate) fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    match a {
+        0 => 1,
+        a => i128::try_from(a).unwrap_or(i128::MAX),
+    }
+}
+
+/// The lowest common multiple of two numbers, giving `None` if it would overflow
+pub(crate) fn lcm(a: i128, b: i128) -> Option<i128> {
+    (a / gcd(a, b)).checked_mul(b)?.checked_abs()
+}
@@ -0,0 +1,418 @@
+//! Rendering formulas and equations for other programs, like LaTeX with mhchem, HTML and MathML
+//!
+//! Everything is rendered from the [`CstNode`] of each formula, so groups keep the brackets
+//! they were written with and numbers are only written as subscripts where they are multipliers
+//!
+//! # Examples
+//!
+//! ```
+//! use stoik::equation::Equation;
+//! use stoik::render::OutputFormat;
+//!
+//! let equation: Equation = "2H2 + O2 -> 2H2O(l)".parse()?;
+//! assert_eq!(equation.render(OutputFormat::Unicode)?, "2H₂ + O₂ → 2H₂O(l)");
+//! assert_eq!(equation.render(OutputFormat::Mhchem)?, r"\ce{2H2 + O2 -> 2H2O(l)}");
+//! assert_eq!(
+//!     equation.render(OutputFormat::Html)?,
+//!     "2H<sub>2</sub> + O<sub>2</sub> → 2H<sub>2</sub>O(l)"
+//! );
+//! # Ok::<(), stoik::StoikError>(())
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    equation::{Arrow, Equation, Species},
+    err::StoikError,
+    formula::{self, assemble_cst, Bracket, CstKind, CstNode, FormulaOrder},
+};
+
+/// The formats a formula or equation can be rendered to
+///
+/// # Examples
+///
+/// ```
+/// use stoik::render::OutputFormat;
+///
+/// let sulfate = "[13C]O2·SO4^2-";
+/// assert_eq!(OutputFormat::Text.render(sulfate)?, "[13C]O2·SO4^2-");
+/// assert_eq!(OutputFormat::Unicode.render(sulfate)?, "¹³CO₂·SO₄²⁻");
+/// assert_eq!(OutputFormat::Mhchem.render(sulfate)?, r"\ce{^{13}CO2*SO4^2-}");
+/// assert_eq!(
+///     OutputFormat::Latex.render(sulfate)?,
+///     r"$\mathrm{{}^{13}CO_{2}\cdot SO_{4}^{2-}}$"
+/// );
+/// assert_eq!(
+///     OutputFormat::Html.render(sulfate)?,
+///     "<sup>13</sup>CO<sub>2</sub>·SO<sub>4</sub><sup>2-</sup>"
+/// );
+///
+/// let water = OutputFormat::MathMl.render("H2O")?;
+/// assert_eq!(
+///     water,
+///     "<math><mrow><msub><mrow><mi mathvariant=\"normal\">H</mi></mrow><mn>2</mn></msub>\
+///      <mi mathvariant=\"normal\">O</mi></mrow></math>"
+/// );
+///
+/// assert_eq!("latex".parse::<OutputFormat>()?, OutputFormat::Latex);
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputFormat {
+    /// Plain text that can be parsed again, like `SO4^2-`
+    #[default]
+    Text,
+    /// Text with Unicode subscripts and superscripts, like `SO₄²⁻`
+    Unicode,
+    /// LaTeX using the mhchem package, like `\ce{SO4^2-}`
+    Mhchem,
+    /// LaTeX without any packages, like `$\mathrm{SO_{4}^{2-}}$`
+    Latex,
+    /// HTML using `<sub>` and `<sup>`, like `SO<sub>4</sub><sup>2-</sup>`
+    Html,
+    /// Presentation MathML in a `<math>` element
+    MathMl,
+}
+
+impl OutputFormat {
+    /// Every format, in the order they are declared
+    pub const ALL: [OutputFormat; 6] = [
+        OutputFormat::Text,
+        OutputFormat::Unicode,
+        OutputFormat::Mhchem,
+        OutputFormat::Latex,
+        OutputFormat::Html,
+        OutputFormat::MathMl,
+    ];
+
+    /// Parses a formula and renders it in this format
+    ///
+    /// # Errors
+    ///
+    /// The same as [`assemble_cst`]
+    pub fn render(self, formula: &str) -> Result<String, StoikError> {
+        Ok(assemble_cst(formula)?.render(self))
+    }
+
+    /// Wraps a rendered formula or equation so it can be used on its own
+    fn wrap(self, inner: String) -> String {
+        match self {
+            OutputFormat::Text | OutputFormat::Unicode | OutputFormat::Html => inner,
+            OutputFormat::Mhchem => format!("\\ce{{{inner}}}"),
+            OutputFormat::Latex => format!("$\\mathrm{{{inner}}}$"),
+            OutputFormat::MathMl => format!("<math><mrow>{inner}</mrow></math>"),
+        }
+    }
+
+    fn atom(self, symbol: &str) -> String {
+        match self {
+            OutputFormat::MathMl => format!("<mi mathvariant=\"normal\">{symbol}</mi>"),
+            _ => symbol.to_string(),
+        }
+    }
+
+    fn isotope(self, symbol: &str, mass_number: u16) -> String {
+        match self {
+            OutputFormat::Text => format!("[{mass_number}{symbol}]"),
+            OutputFormat::Unicode => format!("{}{symbol}", superscript(&mass_number.to_string())),
+            OutputFormat::Mhchem => format!("^{{{mass_number}}}{symbol}"),
+            OutputFormat::Latex => format!("{{}}^{{{mass_number}}}{symbol}"),
+            OutputFormat::Html => format!("<sup>{mass_number}</sup>{symbol}"),
+            OutputFormat::MathMl => format!(
+                "<mmultiscripts>{}<mprescripts/><none/><mn>{mass_number}</mn></mmultiscripts>",
+                self.atom(symbol)
+            ),
+        }
+    }
+
+    /// A number on its own, like the mole count of a formula
+    fn number(self, n: &str) -> String {
+        match self {
+            OutputFormat::MathMl => format!("<mn>{n}</mn>"),
+            _ => n.to_string(),
+        }
+    }
+
+    fn subscript(self, base: String, n: &str) -> String {
+        match self {
+            OutputFormat::Text => base + n,
+            OutputFormat::Unicode => base + &n.chars().map(subscript).collect::<String>(),
+            // mhchem reads a decimal straight after an atom as part of the formula,
+            // but it is safer to be explicit
+            OutputFormat::Mhchem if n.contains(['.', '/']) => format!("{base}_{{{n}}}"),
+            OutputFormat::Mhchem => base + n,
+            OutputFormat::Latex => format!("{base}_{{{n}}}"),
+            OutputFormat::Html => format!("{base}<sub>{n}</sub>"),
+            OutputFormat::MathMl => format!("<msub><mrow>{base}</mrow><mn>{n}</mn></msub>"),
+        }
+    }
+
+    fn charge(self, base: String, charge: i64) -> String {
+        if charge == 0 {
+            return base;
+        }
+        let sign = if charge > 0 { "+" } else { "-" };
+        let size = match charge.unsigned_abs() {
+            1 => String::new(),
+            n => n.to_string(),
+        };
+        match self {
+            OutputFormat::Text => base + &formula::charge_string(charge),
+            OutputFormat::Unicode => base + &superscript(&format!("{size}{sign}")),
+            OutputFormat::Mhchem => format!("{base}^{size}{sign}"),
+            OutputFormat::Latex => format!("{base}^{{{size}{sign}}}"),
+            OutputFormat::Html => format!("{base}<sup>{size}{sign}</sup>"),
+            OutputFormat::MathMl => {
+                let size = if size.is_empty() {
+                    size
+                } else {
+                    self.number(&size)
+                };
+                let sign = if charge > 0 { "+" } else { "\u{2212}" };
+                format!("<msup><mrow>{base}</mrow><mrow>{size}<mo>{sign}</mo></mrow></msup>")
+            }
+        }
+    }
+
+    fn group(self, bracket: Bracket, inner: String) -> String {
+        let (open, close) = match bracket {
+            Bracket::Paren => ("(", ")"),
+            Bracket::Square => ("[", "]"),
+        };
+        match self {
+            OutputFormat::MathMl => format!("<mrow><mo>{open}</mo>{inner}<mo>{close}</mo></mrow>"),
+            _ => format!("{open}{inner}{close}"),
+        }
+    }
+
+    /// The seperator between the parts of an adduct
+    fn dot(self) -> &'static str {
+        match self {
+            OutputFormat::Mhchem => "*",
+            OutputFormat::Latex => "\\cdot ",
+            OutputFormat::MathMl => "<mo>·</mo>",
+            _ => "·",
+        }
+    }
+
+    fn state(self, symbol: &str) -> String {
+        match self {
+            OutputFormat::MathMl => format!("<mtext>({symbol})</mtext>"),
+            _ => format!("({symbol})"),
+        }
+    }
+
+    /// The `+` between the species of an equation
+    fn plus(self) -> &'static str {
+        match self {
+            OutputFormat::MathMl => "<mo>+</mo>",
+            _ => " + ",
+        }
+    }
+
+    /// The arrow between the sides of an equation
+    fn arrow(self, arrow: Arrow) -> &'static str {
+        match (arrow, self) {
+            (Arrow::Forward, OutputFormat::Text | OutputFormat::Mhchem) => " -> ",
+            (Arrow::Forward, OutputFormat::Unicode | OutputFormat::Html) => " → ",
+            (Arrow::Forward, OutputFormat::Latex) => " \\rightarrow ",
+            (Arrow::Forward, OutputFormat::MathMl) => "<mo>→</mo>",
+            (Arrow::Equilibrium, OutputFormat::Mhchem) => " <=> ",
+            (Arrow::Equilibrium, OutputFormat::Latex) => " \\rightleftharpoons ",
+            (Arrow::Equilibrium, OutputFormat::MathMl) => "<mo>⇌</mo>",
+            (Arrow::Equilibrium, _) => " ⇌ ",
+            (Arrow::Reversible, OutputFormat::Mhchem) => " <--> ",
+            (Arrow::Reversible, OutputFormat::Latex) => " \\rightleftarrows ",
+            (Arrow::Reversible, OutputFormat::MathMl) => "<mo>⇄</mo>",
+            (Arrow::Reversible, _) => " ⇄ ",
+        }
+    }
+
+    /// Text that could not be parsed, which is kept as it was written
+    fn escape(self, text: &str) -> String {
+        match self {
+            OutputFormat::Text | OutputFormat::Unicode => text.to_string(),
+            OutputFormat::Mhchem | OutputFormat::Latex => text
+                .chars()
+                .map(|c| match c {
+                    '#' | '$' | '%' | '&' | '_' | '{' | '}' => format!("\\{c}"),
+                    '\\' => "\\backslash ".to_string(),
+                    '^' | '~' => String::new(),
+                    c => c.to_string(),
+                })
+                .collect(),
+            OutputFormat::Html | OutputFormat::MathMl => {
+                let escaped = text
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;");
+                match self {
+                    OutputFormat::MathMl => format!("<mtext>{escaped}</mtext>"),
+                    _ => escaped,
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = StoikError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                StoikError::InvalidInput(format!(
+                    "`{s}` is not an output format, use text, unicode, mhchem, latex, html or mathml"
+                ))
+            })
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "Text"),
+            OutputFormat::Unicode => write!(f, "Unicode"),
+            OutputFormat::Mhchem => write!(f, "mhchem"),
+            OutputFormat::Latex => write!(f, "LaTeX"),
+            OutputFormat::Html => write!(f, "HTML"),
+            OutputFormat::MathMl => write!(f, "MathML"),
+        }
+    }
+}
+
+impl CstNode {
+    /// Renders the formula the node is the root of in the given format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stoik::formula::assemble_cst;
+    /// use stoik::render::OutputFormat;
+    ///
+    /// let cst = assemble_cst("2 [Fe(CN)6]4-")?;
+    /// assert_eq!(cst.render(OutputFormat::Text), "2[Fe(CN)6]^4-");
+    /// assert_eq!(cst.render(OutputFormat::Unicode), "2[Fe(CN)₆]⁴⁻");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn render(&self, format: OutputFormat) -> String {
+        format.wrap(render_node(self, format))
+    }
+}
+
+impl Species {
+    /// Renders the species in the given format, see [`CstNode::render`]. Species written
+    /// as [SMILES](Species::smiles) are rendered as their formula in Hill order
+    ///
+    /// # Errors
+    ///
+    /// The same as [`assemble_cst`], if the formula has been changed since it was parsed
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::equation::Species;
+    /// use stoik::render::OutputFormat;
+    ///
+    /// let acetate = Species::new("{CC(=O)[O-]}(aq)")?;
+    /// assert_eq!(acetate.render(OutputFormat::Unicode)?, "C₂H₃O₂⁻(aq)");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn render(&self, format: OutputFormat) -> Result<String, StoikError> {
+        Ok(self.cst()?.render(format))
+    }
+
+    fn cst(&self) -> Result<CstNode, StoikError> {
+        match self.smiles() {
+            Some(_) => assemble_cst(&self.molecule.to_string_with(FormulaOrder::Hill)),
+            None => assemble_cst(&self.formula),
+        }
+    }
+}
+
+impl Equation {
+    /// Renders the whole equation in the given format, see the [module docs](self)
+    ///
+    /// # Errors
+    ///
+    /// The same as [`assemble_cst`], if a formula has been changed since it was parsed
+    pub fn render(&self, format: OutputFormat) -> Result<String, StoikError> {
+        let side = |species: &[Species]| -> Result<String, StoikError> {
+            Ok(species
+                .iter()
+                .map(|x| Ok(render_node(&x.cst()?, format)))
+                .collect::<Result<Vec<_>, StoikError>>()?
+                .join(format.plus()))
+        };
+        let inner = side(&self.reactants)? + format.arrow(self.arrow) + &side(&self.products)?;
+        Ok(format.wrap(inner))
+    }
+}
+
+fn render_node(node: &CstNode, format: OutputFormat) -> String {
+    let render_all = |node: &CstNode| {
+        node.nodes()
+            .map(|x| render_node(x, format))
+            .collect::<String>()
+    };
+    match node.kind() {
+        CstKind::Formula => {
+            let (mut moles, mut body, mut charge, mut state) =
+                (String::new(), String::new(), 0, String::new());
+            for child in node.nodes() {
+                match child.kind() {
+                    CstKind::Moles(_) => moles = render_node(child, format),
+                    CstKind::Charge(value) => charge = *value,
+                    CstKind::State(value) => state = format.state(value.symbol()),
+                    _ => body += &render_node(child, format),
+                }
+            }
+            moles + &format.charge(body, charge) + &state
+        }
+        CstKind::Moles(n) => format.number(&n.to_string()),
+        CstKind::Adduct => node
+            .nodes()
+            .map(|x| render_node(x, format))
+            .collect::<Vec<_>>()
+            .join(format.dot()),
+        CstKind::Part | CstKind::Sequence => render_all(node),
+        CstKind::Group(bracket) => format.group(*bracket, render_all(node)),
+        CstKind::Multiplier(mul) => format.subscript(render_all(node), &mul.to_string()),
+        CstKind::Atom(symbol) => format.atom(symbol),
+        CstKind::Isotope {
+            symbol,
+            mass_number,
+        } => format.isotope(symbol, *mass_number),
+        CstKind::Electron => format.atom("e"),
+        // charges and states are rendered with the formula they are part of
+        CstKind::Charge(_) | CstKind::State(_) => String::new(),
+        CstKind::Error => format.escape(node.to_string().trim()),
+    }
+}
+
+fn subscript(c: char) -> char {
+    match c.to_digit(10) {
+        Some(n) => char::from_u32('₀' as u32 + n).unwrap_or(c),
+        None => c,
+    }
+}
+
+fn superscript(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4'..='9' => c
+                .to_digit(10)
+                .and_then(|n| char::from_u32('⁰' as u32 + n))
+                .unwrap_or(c),
+            '+' => '⁺',
+            '-' => '⁻',
+            c => c,
+        })
+        .collect()
+}
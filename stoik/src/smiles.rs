@@ -0,0 +1,635 @@
+//! This module is for reading SMILES strings, like `CCO` for ethanol, into a graph of
+//! atoms and bonds that can be turned into a [`Molecule`]
+//!
+//! See the documentation for [`Graph`] for more info
+//! ```
+//! use stoik::formula::{FormulaOrder, Molecule};
+//!
+//! let aspirin = Molecule::from_smiles("CC(=O)Oc1ccccc1C(=O)O")?;
+//! assert_eq!(aspirin.to_string_with(FormulaOrder::Hill), "C9H8O4");
+//! # Ok::<(), stoik::StoikError>(())
+//! ```
+//!
+//! In an equation, a species is read as SMILES when it is written in braces,
+//! see [`Species::smiles`](crate::equation::Species::smiles)
+
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{
+    elements,
+    err::StoikError,
+    formula::{Isotope, Locator, Molecule, TokenLoc},
+};
+
+/// The classes of chirality that can follow an `@` in brackets, like the `TH` in `[C@TH1]`
+const CHIRAL_CLASSES: [&str; 5] = ["TH", "AL", "SP", "TB", "OH"];
+
+/// An atom in a [`Graph`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Atom {
+    /// The symbol of the element, which is capitalised even if the atom was written
+    /// as aromatic, so `c` is `C`
+    pub symbol: String,
+    /// The mass number of the atom, if one was given in brackets like `[13C]`
+    pub mass_number: Option<u16>,
+    /// If the atom is part of an aromatic ring, which is written in lowercase
+    pub aromatic: bool,
+    /// The charge on the atom
+    pub charge: i64,
+    /// The number of hydrogens on the atom, either written in brackets or implicit
+    pub hydrogens: u8,
+    /// The location of the atom in the SMILES string
+    pub loc: TokenLoc,
+}
+
+/// The order of a [`Bond`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BondOrder {
+    /// A single bond, written as `-`, `/`, `\` or nothing at all
+    #[default]
+    Single,
+    /// A double bond, written as `=`
+    Double,
+    /// A triple bond, written as `#`
+    Triple,
+    /// A quadruple bond, written as `$`
+    Quadruple,
+    /// A bond in an aromatic ring, written as `:` or nothing between aromatic atoms
+    Aromatic,
+}
+
+impl BondOrder {
+    /// Gets the bond order a symbol stands for, like [`Double`](Self::Double) for `=`
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '-' | '/' | '\\' => Some(Self::Single),
+            '=' => Some(Self::Double),
+            '#' => Some(Self::Triple),
+            '$' => Some(Self::Quadruple),
+            ':' => Some(Self::Aromatic),
+            _ => None,
+        }
+    }
+
+    /// Gets how much the bond counts towards the valence of its atoms. Aromatic bonds count
+    /// as one, as the extra electrons in the ring are counted for each aromatic atom instead
+    pub fn valence(self) -> u32 {
+        match self {
+            Self::Single | Self::Aromatic => 1,
+            Self::Double => 2,
+            Self::Triple => 3,
+            Self::Quadruple => 4,
+        }
+    }
+}
+
+/// A bond between two atoms in a [`Graph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bond {
+    /// The indices of the bonded atoms in [`Graph::atoms`], in the order they were written
+    pub atoms: (usize, usize),
+    /// The order of the bond
+    pub order: BondOrder,
+}
+
+/// The atoms and bonds of a molecule, read from a SMILES string
+///
+/// Hydrogens are stored as a count on each atom instead of as atoms of their own, unless they
+/// were written as atoms in brackets like `[H]`. Atoms outside of brackets get the hydrogens
+/// needed to fill their lowest normal valence, so `C` is methane and `c1ccccc1` is benzene.
+/// Stereochemistry and atom classes are read, but not kept
+///
+/// # Examples
+///
+/// ```
+/// use stoik::smiles::{BondOrder, Graph};
+///
+/// let acetic_acid: Graph = "CC(=O)O".parse()?;
+/// assert_eq!(acetic_acid.atoms.len(), 4);
+/// assert_eq!(acetic_acid.bonds[1].order, BondOrder::Double);
+/// assert_eq!(acetic_acid.neighbours(1).collect::<Vec<_>>(), [0, 2, 3]);
+/// assert_eq!(acetic_acid.atoms[0].hydrogens, 3);
+///
+/// // brackets give isotopes, charges and hydrogens exactly
+/// let ammonium = Graph::parse("[15NH4+]")?;
+/// assert_eq!(ammonium.atoms[0].mass_number, Some(15));
+/// assert_eq!(ammonium.atoms[0].charge, 1);
+/// assert_eq!(ammonium.atoms[0].hydrogens, 4);
+///
+/// let pyridine = Graph::parse("c1ccncc1")?;
+/// assert!(pyridine.bonds.iter().all(|x| x.order == BondOrder::Aromatic));
+/// assert_eq!(pyridine.to_molecule().to_string(), "C5H5N");
+/// # Ok::<(), stoik::StoikError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Graph {
+    /// Every atom in the order they were written
+    pub atoms: Vec<Atom>,
+    /// Every bond between the atoms
+    pub bonds: Vec<Bond>,
+}
+
+impl Graph {
+    /// Parses a SMILES string into a graph
+    ///
+    /// # Errors
+    ///
+    /// - [`StoikError::InvalidToken`] for anything that is not SMILES, or a bond with no atom on one side
+    /// - [`StoikError::UnpairedParenthesis`] and [`StoikError::UnpairedBracket`] for unclosed branches and atoms
+    /// - [`StoikError::UnclosedRing`] for a ring bond that is opened but never closed
+    /// - [`StoikError::UnknownElement`] for an element in brackets that is not in the periodic table
+    /// - [`StoikError::Overflow`] for a mass number or charge that is too big
+    /// - [`StoikError::EmptyMolecule`] if there are no atoms
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::TokenLoc;
+    /// use stoik::smiles::Graph;
+    /// use stoik::StoikError;
+    ///
+    /// let err = Graph::parse("C1CCC").unwrap_err();
+    /// assert!(matches!(err, StoikError::UnclosedRing(loc) if loc == TokenLoc::new(2, 1)));
+    /// let err = Graph::parse("CC[Xy]").unwrap_err();
+    /// assert!(matches!(err, StoikError::UnknownElement(loc) if loc == TokenLoc::new(4, 2)));
+    /// ```
+    pub fn parse(smiles: &str) -> Result<Self, StoikError> {
+        Parser::new(smiles).parse()
+    }
+
+    /// Gets the indices of the atoms bonded to the atom at `atom`
+    pub fn neighbours(&self, atom: usize) -> impl Iterator<Item = usize> + '_ {
+        self.bonds.iter().filter_map(move |bond| match bond.atoms {
+            (a, b) if a == atom => Some(b),
+            (a, b) if b == atom => Some(a),
+            _ => None,
+        })
+    }
+
+    /// Makes the [`Molecule`] with every atom and hydrogen in the graph
+    pub fn to_molecule(&self) -> Molecule {
+        molecule_of(&self.atoms)
+    }
+
+    /// Makes a [`Molecule`] for each disconnected part of the graph, like the ions of
+    /// `[Na+].[Cl-]`, in the order their first atoms were written
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::InchiFormula;
+    /// use stoik::smiles::Graph;
+    ///
+    /// let sodium_acetate = Graph::parse("CC(=O)[O-].[Na+]")?;
+    /// let components = sodium_acetate.components();
+    /// assert_eq!(components.len(), 2);
+    /// assert_eq!(components[1].charge, 1);
+    /// assert_eq!(InchiFormula::new(components)?.to_string(), "C2H3O2.Na/q-1;+1");
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn components(&self) -> Vec<Molecule> {
+        let mut seen = vec![false; self.atoms.len()];
+        let mut components = Vec::new();
+        for first in 0..self.atoms.len() {
+            if seen[first] {
+                continue;
+            }
+            seen[first] = true;
+            let mut atoms = vec![first];
+            let mut i = 0;
+            while let Some(atom) = atoms.get(i) {
+                for next in self.neighbours(*atom).collect::<Vec<_>>() {
+                    if !seen[next] {
+                        seen[next] = true;
+                        atoms.push(next);
+                    }
+                }
+                i += 1;
+            }
+            atoms.sort();
+            components.push(molecule_of(atoms.iter().map(|x| &self.atoms[*x])));
+        }
+        components
+    }
+
+    /// Gets the total bond order of the atom at `atom`
+    fn bond_valence(&self, atom: usize) -> u32 {
+        self.bonds
+            .iter()
+            .filter(|x| x.atoms.0 == atom || x.atoms.1 == atom)
+            .map(|x| x.order.valence())
+            .sum()
+    }
+}
+
+impl FromStr for Graph {
+    type Err = StoikError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Molecule {
+    /// Parses a molecule from a SMILES string, see [`Graph`]
+    ///
+    /// # Examples
+    /// ```
+    /// use stoik::formula::Molecule;
+    ///
+    /// assert_eq!(Molecule::from_smiles("CCO")?, Molecule::from_formula("C2H6O")?);
+    /// assert_eq!(Molecule::from_smiles("[Na+].[Cl-]")?, Molecule::from_formula("NaCl")?);
+    /// # Ok::<(), stoik::StoikError>(())
+    /// ```
+    pub fn from_smiles(smiles: &str) -> Result<Self, StoikError> {
+        Ok(Graph::parse(smiles)?.to_molecule())
+    }
+}
+
+/// Makes a [`Molecule`] from atoms and their hydrogens
+fn molecule_of<'a>(atoms: impl IntoIterator<Item = &'a Atom>) -> Molecule {
+    let mut molecule = Molecule::default();
+    for atom in atoms {
+        molecule.increase_isotope(Isotope::new(&atom.symbol, atom.mass_number), 1);
+        if atom.hydrogens > 0 {
+            molecule.increase_atom("H", i64::from(atom.hydrogens));
+        }
+        molecule.charge += atom.charge;
+    }
+    molecule
+}
+
+/// Gets the number of implicit hydrogens on an atom written outside of brackets, which
+/// fill the lowest normal valence of the element that is at least the atom's bond order
+fn implicit_hydrogens(symbol: &str, aromatic: bool, bonds: u32) -> u8 {
+    let valences: &[u32] = match symbol {
+        "B" => &[3],
+        "C" => &[4],
+        "N" => &[3, 5],
+        "O" => &[2],
+        "P" => &[3, 5],
+        "S" => &[2, 4, 6],
+        _ => &[1],
+    };
+    let hydrogens = if aromatic {
+        // one bond's worth of the valence goes to the ring
+        valences[0].saturating_sub(bonds + 1)
+    } else {
+        valences
+            .iter()
+            .find(|x| **x >= bonds)
+            .map_or(0, |x| x - bonds)
+    };
+    hydrogens as u8
+}
+
+/// Reads a SMILES string one character at a time
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    locator: Locator<'a>,
+    graph: Graph,
+    /// Whether each atom was written in brackets, so has no implicit hydrogens
+    bracketed: Vec<bool>,
+}
+
+/// A ring bond that has been opened but not closed yet
+struct OpenRing {
+    atom: usize,
+    order: Option<BondOrder>,
+    loc: TokenLoc,
+}
+
+impl<'a> Parser<'a> {
+    fn new(smiles: &'a str) -> Self {
+        Self {
+            chars: smiles.chars().collect(),
+            pos: 0,
+            locator: Locator::new(smiles),
+            graph: Graph::default(),
+            bracketed: Vec::new(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Graph, StoikError> {
+        // the atom the next atom bonds to, and the bond it was given
+        let mut prev: Option<usize> = None;
+        let mut bond: Option<(BondOrder, usize)> = None;
+        // the atom before each open branch, and where the branch starts
+        let mut branches: Vec<(Option<usize>, usize)> = Vec::new();
+        let mut rings: HashMap<u8, OpenRing> = HashMap::new();
+
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            match c {
+                '(' | '.' if prev.is_none() || bond.is_some() => {
+                    return Err(StoikError::InvalidToken(self.loc(start, 1)));
+                }
+                '(' => {
+                    branches.push((prev, start));
+                    self.pos += 1;
+                }
+                ')' => {
+                    let Some((before, _)) = branches.pop() else {
+                        return Err(StoikError::UnpairedParenthesis(self.loc(start, 1)));
+                    };
+                    // a bond at the end of a branch, or an empty branch like `C()`
+                    if bond.is_some() || prev == before {
+                        return Err(StoikError::InvalidToken(self.loc(start, 1)));
+                    }
+                    prev = before;
+                    self.pos += 1;
+                }
+                '.' => {
+                    prev = None;
+                    self.pos += 1;
+                }
+                c if BondOrder::from_symbol(c).is_some() => {
+                    if prev.is_none() || bond.is_some() {
+                        return Err(StoikError::InvalidToken(self.loc(start, 1)));
+                    }
+                    bond = BondOrder::from_symbol(c).map(|x| (x, start));
+                    self.pos += 1;
+                }
+                '0'..='9' | '%' => {
+                    let number = self.ring_number()?;
+                    let loc = self.loc(start, self.pos - start);
+                    let Some(atom) = prev else {
+                        return Err(StoikError::InvalidToken(loc));
+                    };
+                    let order = bond.take().map(|x| x.0);
+                    let Some(open) = rings.remove(&number) else {
+                        rings.insert(number, OpenRing { atom, order, loc });
+                        continue;
+                    };
+                    let order = match (open.order, order) {
+                        (Some(a), Some(b)) if a != b => {
+                            return Err(StoikError::InvalidToken(loc));
+                        }
+                        (a, b) => a.or(b),
+                    };
+                    if open.atom == atom || self.graph.neighbours(atom).any(|x| x == open.atom) {
+                        return Err(StoikError::InvalidToken(loc));
+                    }
+                    self.bond(open.atom, atom, order);
+                }
+                _ => {
+                    let atom = self.atom()?;
+                    if let Some(prev) = prev {
+                        self.bond(prev, atom, bond.take().map(|x| x.0));
+                    }
+                    prev = Some(atom);
+                }
+            }
+        }
+
+        if let Some((_, start)) = branches.first() {
+            return Err(StoikError::UnpairedParenthesis(self.loc(*start, 1)));
+        }
+        if let Some((_, start)) = bond {
+            return Err(StoikError::InvalidToken(self.loc(start, 1)));
+        }
+        if let Some(open) = rings.into_values().min_by_key(|x| x.loc.start()) {
+            return Err(StoikError::UnclosedRing(open.loc));
+        }
+        if self.graph.atoms.is_empty() {
+            return Err(StoikError::EmptyMolecule);
+        }
+        if prev.is_none() {
+            // only a `.` leaves no atom to bond to
+            return Err(StoikError::InvalidToken(self.loc(self.chars.len() - 1, 1)));
+        }
+
+        let mut charge = 0i64;
+        for atom in &self.graph.atoms {
+            charge = charge
+                .checked_add(atom.charge)
+                .ok_or_else(|| StoikError::Overflow(atom.loc.clone()))?;
+        }
+
+        for i in 0..self.graph.atoms.len() {
+            if !self.bracketed[i] {
+                let bonds = self.graph.bond_valence(i);
+                let atom = &mut self.graph.atoms[i];
+                atom.hydrogens = implicit_hydrogens(&atom.symbol, atom.aromatic, bonds);
+            }
+        }
+        Ok(self.graph)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Gets the location of `len` characters starting at the character at `start`, counting from 0
+    fn loc(&mut self, start: usize, len: usize) -> TokenLoc {
+        self.locator.locate(start + 1, len)
+    }
+
+    /// Adds a bond between two atoms, which is aromatic if no order is given and both
+    /// atoms are aromatic
+    fn bond(&mut self, a: usize, b: usize, order: Option<BondOrder>) {
+        let atoms = &self.graph.atoms;
+        let order = order.unwrap_or(if atoms[a].aromatic && atoms[b].aromatic {
+            BondOrder::Aromatic
+        } else {
+            BondOrder::Single
+        });
+        self.graph.bonds.push(Bond {
+            atoms: (a, b),
+            order,
+        });
+    }
+
+    /// Reads a ring bond number, which is one digit or two after a `%`
+    fn ring_number(&mut self) -> Result<u8, StoikError> {
+        let start = self.pos;
+        let (skip, len) = if self.peek() == Some('%') {
+            (1, 2)
+        } else {
+            (0, 1)
+        };
+        let digits = self.chars[start + skip..]
+            .iter()
+            .take(len)
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+        if digits.len() != len {
+            return Err(StoikError::InvalidToken(
+                self.loc(start, skip + digits.len()),
+            ));
+        }
+        self.pos += skip + len;
+        // only digits are taken, so this cannot fail
+        Ok(digits.parse().unwrap_or_default())
+    }
+
+    /// Reads an atom, adding it to the graph and giving its index
+    fn atom(&mut self) -> Result<usize, StoikError> {
+        let start = self.pos;
+        let (atom, bracketed) = if self.peek() == Some('[') {
+            (self.bracket_atom()?, true)
+        } else {
+            let rest = self.chars[start..].iter().take(2).collect::<String>();
+            let symbol = ["Cl", "Br", "B", "C", "N", "O", "P", "S", "F", "I"]
+                .into_iter()
+                .chain(["b", "c", "n", "o", "p", "s"])
+                .find(|x| rest.starts_with(x))
+                .ok_or_else(|| StoikError::InvalidToken(self.loc(start, 1)))?;
+            self.pos += symbol.len();
+            let atom = Atom {
+                symbol: capitalise(symbol),
+                mass_number: None,
+                aromatic: symbol.starts_with(char::is_lowercase),
+                charge: 0,
+                hydrogens: 0,
+                loc: self.loc(start, symbol.len()),
+            };
+            (atom, false)
+        };
+        self.graph.atoms.push(atom);
+        self.bracketed.push(bracketed);
+        Ok(self.graph.atoms.len() - 1)
+    }
+
+    /// Reads an atom in brackets, like `[13CH3+:1]`
+    fn bracket_atom(&mut self) -> Result<Atom, StoikError> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let digits = self.take_digits();
+        let mass_number =
+            match digits.is_empty() {
+                true => None,
+                false => Some(digits.parse::<u16>().map_err(|_| {
+                    StoikError::Overflow(self.loc(start + 1, digits.chars().count()))
+                })?),
+            };
+
+        if self.peek().is_none() {
+            return Err(StoikError::UnpairedBracket(self.loc(start, 1)));
+        }
+        let symbol_start = self.pos;
+        let symbol = self.bracket_symbol()?;
+        let aromatic = symbol.starts_with(char::is_lowercase);
+        let symbol = capitalise(&symbol);
+        if elements::get(&symbol).is_none() {
+            let loc = self.loc(symbol_start, self.pos - symbol_start);
+            return Err(StoikError::UnknownElement(loc));
+        }
+
+        if self.peek() == Some('@') {
+            while self.peek() == Some('@') {
+                self.pos += 1;
+            }
+            let class = self.chars[self.pos..].iter().take(2).collect::<String>();
+            if CHIRAL_CLASSES.contains(&class.as_str()) {
+                self.pos += 2;
+                self.take_digits();
+            }
+        }
+
+        let mut hydrogens = 0;
+        if self.peek() == Some('H') {
+            self.pos += 1;
+            hydrogens = match self.peek().and_then(|c| c.to_digit(10)) {
+                Some(n) => {
+                    self.pos += 1;
+                    n as u8
+                }
+                None => 1,
+            };
+        }
+
+        let mut charge = 0;
+        if let Some(sign @ ('+' | '-')) = self.peek() {
+            let charge_start = self.pos;
+            self.pos += 1;
+            let digits = self.take_digits();
+            let size = if digits.is_empty() {
+                let mut size = 1;
+                while self.peek() == Some(sign) {
+                    self.pos += 1;
+                    size += 1;
+                }
+                size
+            } else {
+                digits.parse::<i64>().map_err(|_| {
+                    StoikError::Overflow(self.loc(charge_start, self.pos - charge_start))
+                })?
+            };
+            charge = if sign == '+' { size } else { -size };
+        }
+
+        if self.peek() == Some(':') {
+            self.pos += 1;
+            if self.take_digits().is_empty() {
+                return Err(StoikError::InvalidToken(self.loc(self.pos - 1, 1)));
+            }
+        }
+
+        match self.peek() {
+            Some(']') => self.pos += 1,
+            Some(_) => return Err(StoikError::InvalidToken(self.loc(self.pos, 1))),
+            None => return Err(StoikError::UnpairedBracket(self.loc(start, 1))),
+        }
+        Ok(Atom {
+            symbol,
+            mass_number,
+            aromatic,
+            charge,
+            hydrogens,
+            loc: self.loc(start, self.pos - start),
+        })
+    }
+
+    /// Reads the element symbol of an atom in brackets, which is either a capital and
+    /// possibly a lowercase letter, or an aromatic symbol in lowercase
+    fn bracket_symbol(&mut self) -> Result<String, StoikError> {
+        let rest = self.chars[self.pos..].iter().take(2).collect::<String>();
+        let symbol = match rest.chars().next() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let one = c.to_string();
+                // `[Co]` is cobalt, but `[CH4]` is carbon with hydrogens
+                match rest.chars().nth(1) {
+                    Some(next)
+                        if next.is_ascii_lowercase()
+                            && (elements::get(&rest).is_some()
+                                || elements::get(&one).is_none()) =>
+                    {
+                        rest
+                    }
+                    _ => one,
+                }
+            }
+            _ => ["se", "as", "te", "b", "c", "n", "o", "p", "s"]
+                .into_iter()
+                .find(|x| rest.starts_with(x))
+                .map(str::to_string)
+                .ok_or_else(|| StoikError::InvalidToken(self.loc(self.pos, 1)))?,
+        };
+        self.pos += symbol.chars().count();
+        Ok(symbol)
+    }
+
+    /// Reads every digit from the current position
+    fn take_digits(&mut self) -> String {
+        let digits = self.chars[self.pos..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+        self.pos += digits.len();
+        digits
+    }
+}
+
+/// Capitalises the first letter of an element symbol, so `se` becomes `Se`
+fn capitalise(symbol: &str) -> String {
+    let mut chars = symbol.chars();
+    chars
+        .next()
+        .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}